@@ -0,0 +1,192 @@
+use cosmwasm_std::{Int256, Order, StdResult, Storage, Uint64};
+
+use crate::{
+    error::ContractError,
+    models::IndexAggregate,
+    msg::{IndexMetadata, IndexType},
+    state::{
+        has_index_entry, ContractID, PartitionID, INDEX_AGGREGATES, INDEX_METADATA, VALUES_I32,
+        VALUES_TIME, VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8,
+    },
+    util::build_index_storage_key,
+};
+
+// Running aggregates hold the count and sum of each partition's entries in a
+// numeric custom index, for indices created with aggregates enabled. Each
+// change to an entry must be applied to its partition's aggregate.
+
+/// Load the metadata of each of the given custom indices that keeps running
+/// aggregates.
+pub fn load_aggregated_indices(
+    storage: &dyn Storage,
+    index_names: &[&String],
+) -> Result<Vec<IndexMetadata>, ContractError> {
+    let mut indices: Vec<IndexMetadata> = Vec::with_capacity(index_names.len());
+    for index_name in index_names.iter() {
+        if let Some(meta) = INDEX_METADATA.may_load(storage, (*index_name).clone())? {
+            if meta.aggregate.unwrap_or(false) && !indices.iter().any(|m| m.name == meta.name) {
+                indices.push(meta);
+            }
+        }
+    }
+    Ok(indices)
+}
+
+/// Load a contract's value in a numeric custom index, provided that it has
+/// an entry in the index within the given partition.
+pub fn load_indexed_value(
+    storage: &dyn Storage,
+    meta: &IndexMetadata,
+    partition: PartitionID,
+    contract_id: ContractID,
+) -> Result<Option<Int256>, ContractError> {
+    let storage_key = &build_index_storage_key(&meta.name);
+    let key = (contract_id, &meta.name);
+    Ok(match meta.index_type {
+        IndexType::Timestamp => match VALUES_TIME.may_load(storage, key)? {
+            Some(v) => has_index_entry(storage, storage_key, (partition, v.nanos(), contract_id))
+                .then(|| v.nanos().into()),
+            None => None,
+        },
+        IndexType::Int32 => match VALUES_I32.may_load(storage, key)? {
+            Some(v) => {
+                has_index_entry(storage, storage_key, (partition, v, contract_id)).then(|| v.into())
+            },
+            None => None,
+        },
+        IndexType::Uint8 => match VALUES_U8.may_load(storage, key)? {
+            Some(v) => {
+                has_index_entry(storage, storage_key, (partition, v, contract_id)).then(|| v.into())
+            },
+            None => None,
+        },
+        IndexType::Uint16 => match VALUES_U16.may_load(storage, key)? {
+            Some(v) => {
+                has_index_entry(storage, storage_key, (partition, v, contract_id)).then(|| v.into())
+            },
+            None => None,
+        },
+        IndexType::Uint32 => match VALUES_U32.may_load(storage, key)? {
+            Some(v) => {
+                has_index_entry(storage, storage_key, (partition, v, contract_id)).then(|| v.into())
+            },
+            None => None,
+        },
+        IndexType::Uint64 => match VALUES_U64.may_load(storage, key)? {
+            Some(v) => has_index_entry(storage, storage_key, (partition, v.u64(), contract_id))
+                .then(|| v.into()),
+            None => None,
+        },
+        IndexType::Uint128 => match VALUES_U128.may_load(storage, key)? {
+            Some(v) => has_index_entry(storage, storage_key, (partition, v.u128(), contract_id))
+                .then(|| v.into()),
+            None => None,
+        },
+        IndexType::String | IndexType::Bool | IndexType::Binary => None,
+    })
+}
+
+/// Apply a change in a contract's indexed value to the partition's aggregate.
+pub fn update_aggregate(
+    storage: &mut dyn Storage,
+    index_name: &String,
+    partition: PartitionID,
+    prev_value: Option<Int256>,
+    value: Option<Int256>,
+) -> Result<(), ContractError> {
+    if prev_value == value {
+        return Ok(());
+    }
+    INDEX_AGGREGATES.update(
+        storage,
+        (index_name, partition),
+        |maybe_agg| -> Result<_, ContractError> {
+            let mut agg = maybe_agg.unwrap_or_default();
+            if let Some(v) = prev_value {
+                agg.count = agg.count.checked_sub(Uint64::one()).map_err(|e| {
+                    ContractError::UnexpectedError {
+                        reason: e.to_string(),
+                    }
+                })?;
+                agg.sum -= v;
+            }
+            if let Some(v) = value {
+                agg.count += Uint64::one();
+                agg.sum += v;
+            }
+            Ok(agg)
+        },
+    )?;
+    Ok(())
+}
+
+/// Remove a contract's values from the aggregates of its partition. This
+/// must be called before its custom index entries are removed.
+pub fn remove_contract(
+    storage: &mut dyn Storage,
+    partition: PartitionID,
+    contract_id: ContractID,
+    index_names: &[&String],
+) -> Result<(), ContractError> {
+    for meta in load_aggregated_indices(storage, index_names)?.iter() {
+        let value = load_indexed_value(storage, meta, partition, contract_id)?;
+        update_aggregate(storage, &meta.name, partition, value, None)?;
+    }
+    Ok(())
+}
+
+/// Move a contract's values between partition aggregates. This must be called
+/// before its custom index entries are moved.
+pub fn move_contract(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    src: PartitionID,
+    dst: PartitionID,
+    index_names: &[&String],
+) -> Result<(), ContractError> {
+    for meta in load_aggregated_indices(storage, index_names)?.iter() {
+        let value = load_indexed_value(storage, meta, src, contract_id)?;
+        update_aggregate(storage, &meta.name, src, value, None)?;
+        update_aggregate(storage, &meta.name, dst, None, value)?;
+    }
+    Ok(())
+}
+
+/// Remove the aggregates of a deleted index from every partition.
+pub fn clear_index(
+    storage: &mut dyn Storage,
+    index_name: &String,
+) -> Result<(), ContractError> {
+    for partition in INDEX_AGGREGATES
+        .prefix(index_name)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<PartitionID>>>()?
+    {
+        INDEX_AGGREGATES.remove(storage, (index_name, partition));
+    }
+    Ok(())
+}
+
+/// Remove the aggregates of a deleted partition from every index.
+pub fn clear_partition(
+    storage: &mut dyn Storage,
+    partition: PartitionID,
+) -> Result<(), ContractError> {
+    for index_name in INDEX_METADATA
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?
+    {
+        INDEX_AGGREGATES.remove(storage, (&index_name, partition));
+    }
+    Ok(())
+}
+
+pub fn load_aggregate(
+    storage: &dyn Storage,
+    index_name: &String,
+    partition: PartitionID,
+) -> Result<IndexAggregate, ContractError> {
+    Ok(INDEX_AGGREGATES
+        .may_load(storage, (index_name, partition))?
+        .unwrap_or_default())
+}
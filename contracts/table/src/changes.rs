@@ -0,0 +1,256 @@
+use cosmwasm_std::{Addr, Env, Order, Storage, Uint64};
+
+use crate::{
+    error::ContractError,
+    models::{Change, ChangeKind},
+    state::{
+        load_contract_addr, ContractID, CHANGES, CHANGE_SEQ, CONFIG_CHANGE_RETENTION,
+        CONTRACT_METADATA,
+    },
+};
+
+// Max number of changes pruned each time a change is recorded. Since this is
+// more than one, pruning catches up with the retention limits over time.
+const MAX_PRUNE_SIZE: usize = 5;
+
+/// Append a change to the change log under the next sequence number, pruning
+/// changes beyond the configured retention. This must be called before a
+/// contract's metadata is deleted.
+pub fn record_change(
+    storage: &mut dyn Storage,
+    env: &Env,
+    contract_id: ContractID,
+    initiator: &Addr,
+    kind: ChangeKind,
+) -> Result<(), ContractError> {
+    let seq = CHANGE_SEQ.may_load(storage)?.unwrap_or_default() + Uint64::one();
+    let change = Change {
+        seq,
+        kind,
+        contract: load_contract_addr(storage, contract_id)?,
+        id: contract_id.into(),
+        partition: CONTRACT_METADATA.load(storage, contract_id)?.partition,
+        initiator: initiator.clone(),
+        time: env.block.time,
+        height: env.block.height.into(),
+    };
+
+    CHANGE_SEQ.save(storage, &seq)?;
+    CHANGES.save(storage, seq.u64(), &change)?;
+
+    prune(storage, env, seq)
+}
+
+fn prune(
+    storage: &mut dyn Storage,
+    env: &Env,
+    latest_seq: Uint64,
+) -> Result<(), ContractError> {
+    let retention = match CONFIG_CHANGE_RETENTION.may_load(storage)? {
+        Some(retention) => retention,
+        None => return Ok(()),
+    };
+
+    let mut pruned: Vec<u64> = Vec::with_capacity(MAX_PRUNE_SIZE);
+
+    for result in CHANGES
+        .range(storage, None, None, Order::Ascending)
+        .take(MAX_PRUNE_SIZE)
+    {
+        let (seq, change) = result?;
+        let is_over_size = retention
+            .max_size
+            .map(|n| latest_seq.u64() - seq >= n.u64())
+            .unwrap_or(false);
+        // Compare ages rather than adding max_age to the change's time, which
+        // could overflow.
+        let is_over_age = retention
+            .max_age
+            .map(|secs| {
+                env.block.time.nanos().saturating_sub(change.time.nanos())
+                    > secs.saturating_mul(1_000_000_000)
+            })
+            .unwrap_or(false);
+        if !(is_over_size || is_over_age) {
+            break;
+        }
+        pruned.push(seq);
+    }
+
+    for seq in pruned.into_iter() {
+        CHANGES.remove(storage, seq);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env},
+        Addr, Env, Order, Storage, Uint64,
+    };
+
+    use super::record_change;
+    use crate::{
+        models::{ChangeKind, ContractMetadata},
+        msg::{ChangeRetention, TableChangesQueryParams},
+        query::table::changes as query_changes,
+        state::{
+            ContractID, CHANGES, CONFIG_CHANGE_RETENTION, CONTRACT_ID_2_ADDR, CONTRACT_METADATA,
+        },
+    };
+
+    const CONTRACT_ID: ContractID = 1;
+
+    fn register_contract(
+        storage: &mut dyn Storage,
+        env: &Env,
+    ) {
+        CONTRACT_ID_2_ADDR
+            .save(storage, CONTRACT_ID, &Addr::unchecked("contract"))
+            .unwrap();
+        CONTRACT_METADATA
+            .save(
+                storage,
+                CONTRACT_ID,
+                &ContractMetadata {
+                    id: CONTRACT_ID.into(),
+                    code_id: Uint64::one(),
+                    created_by: Addr::unchecked("creator"),
+                    created_at: env.block.time,
+                    created_at_height: env.block.height.into(),
+                    is_managed: true,
+                    partition: 0,
+                },
+            )
+            .unwrap();
+    }
+
+    fn record_changes(
+        storage: &mut dyn Storage,
+        env: &Env,
+        n: usize,
+    ) {
+        for _ in 0..n {
+            record_change(
+                storage,
+                env,
+                CONTRACT_ID,
+                &Addr::unchecked("initiator"),
+                ChangeKind::Update,
+            )
+            .unwrap();
+        }
+    }
+
+    fn retained_seqs(storage: &dyn Storage) -> Vec<u64> {
+        CHANGES
+            .keys(storage, None, None, Order::Ascending)
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn paginates_changes_by_seq() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        register_contract(deps.as_mut().storage, &env);
+        record_changes(deps.as_mut().storage, &env, 5);
+
+        let page = |since_seq: Option<Uint64>| {
+            query_changes(
+                deps.as_ref(),
+                TableChangesQueryParams {
+                    since_seq,
+                    limit: Some(2),
+                },
+            )
+            .unwrap()
+        };
+
+        let first = page(None);
+        let seqs: Vec<u64> = first.changes.iter().map(|c| c.seq.u64()).collect();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(first.cursor, Some(Uint64::from(2u64)));
+        assert_eq!(first.latest_seq, Uint64::from(5u64));
+
+        let second = page(first.cursor);
+        let seqs: Vec<u64> = second.changes.iter().map(|c| c.seq.u64()).collect();
+        assert_eq!(seqs, vec![3, 4]);
+        assert_eq!(second.cursor, Some(Uint64::from(4u64)));
+
+        let last = page(second.cursor);
+        let seqs: Vec<u64> = last.changes.iter().map(|c| c.seq.u64()).collect();
+        assert_eq!(seqs, vec![5]);
+        assert_eq!(last.cursor, None);
+    }
+
+    #[test]
+    fn prunes_changes_beyond_max_size() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        register_contract(deps.as_mut().storage, &env);
+        CONFIG_CHANGE_RETENTION
+            .save(
+                deps.as_mut().storage,
+                &ChangeRetention {
+                    max_age: None,
+                    max_size: Some(Uint64::from(3u64)),
+                },
+            )
+            .unwrap();
+
+        record_changes(deps.as_mut().storage, &env, 6);
+
+        assert_eq!(retained_seqs(deps.as_ref().storage), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn prunes_changes_beyond_max_age() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        register_contract(deps.as_mut().storage, &env);
+        CONFIG_CHANGE_RETENTION
+            .save(
+                deps.as_mut().storage,
+                &ChangeRetention {
+                    max_age: Some(10),
+                    max_size: None,
+                },
+            )
+            .unwrap();
+
+        record_changes(deps.as_mut().storage, &env, 2);
+        env.block.time = env.block.time.plus_seconds(10);
+        record_changes(deps.as_mut().storage, &env, 1);
+
+        // Changes exactly max_age old are retained.
+        assert_eq!(retained_seqs(deps.as_ref().storage), vec![1, 2, 3]);
+
+        env.block.time = env.block.time.plus_seconds(1);
+        record_changes(deps.as_mut().storage, &env, 1);
+
+        assert_eq!(retained_seqs(deps.as_ref().storage), vec![3, 4]);
+    }
+
+    #[test]
+    fn does_not_overflow_with_max_age_of_u64_max() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        register_contract(deps.as_mut().storage, &env);
+        CONFIG_CHANGE_RETENTION
+            .save(
+                deps.as_mut().storage,
+                &ChangeRetention {
+                    max_age: Some(u64::MAX),
+                    max_size: None,
+                },
+            )
+            .unwrap();
+
+        record_changes(deps.as_mut().storage, &env, 3);
+
+        assert_eq!(retained_seqs(deps.as_ref().storage), vec![1, 2, 3]);
+    }
+}
@@ -2,8 +2,8 @@ use cosmwasm_std::{to_json_binary, Addr, Binary, Coin, StdResult, Uint64, WasmMs
 
 use crate::{
     msg::{
-        ClientMsg, CreationParams, ExecuteMsg, FlagParams, KeyValue, PartitionSelector,
-        Relationship, RelationshipUpdates, TagUpdate, TagUpdates, UpdateParams,
+        BatchCreationParams, ClientMsg, CreationParams, ExecuteMsg, FlagParams, KeyValue,
+        PartitionSelector, Relationship, RelationshipUpdates, TagUpdate, TagUpdates, UpdateParams,
     },
     state::GroupID,
 };
@@ -24,6 +24,7 @@ impl Table {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         &self,
         initiator: Option<Addr>,
@@ -35,7 +36,7 @@ impl Table {
         admin: Option<Addr>,
         groups: Option<Vec<GroupID>>,
         tags: Option<Vec<String>>,
-        funds: &Vec<Coin>,
+        funds: &[Coin],
     ) -> StdResult<WasmMsg> {
         Ok(WasmMsg::Execute {
             contract_addr: self.table_addr.clone().into(),
@@ -46,11 +47,33 @@ impl Table {
                 label: Some(label),
                 use_lifecycle_hooks: Some(use_lifecycle_hooks),
                 admin,
-                partition,
+                partition: Some(partition),
                 groups,
                 tags,
+                funds: None,
+                expires_at: None,
             })))?,
-            funds: funds.clone(),
+            funds: funds.to_vec(),
+        })
+    }
+
+    /// Create several contracts at once. Funds must equal the sum of each
+    /// entry's funds.
+    pub fn create_many(
+        &self,
+        entries: Vec<CreationParams>,
+        best_effort: bool,
+        funds: &[Coin],
+    ) -> StdResult<WasmMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.table_addr.clone().into(),
+            msg: to_json_binary(&ExecuteMsg::Client(ClientMsg::CreateMany(
+                BatchCreationParams {
+                    entries,
+                    best_effort: Some(best_effort),
+                },
+            )))?,
+            funds: funds.to_vec(),
         })
     }
 
@@ -69,6 +92,7 @@ impl Table {
                 values,
                 tags,
                 relationships,
+                expires_at: None,
             })))?,
             funds: vec![],
         })
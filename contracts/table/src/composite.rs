@@ -0,0 +1,282 @@
+use std::collections::BTreeSet;
+
+use cosmwasm_std::{Binary, Order, Storage, Timestamp, Uint128, Uint64};
+use cw_storage_plus::Map;
+
+use crate::{
+    error::ContractError,
+    msg::{CompositeIndexColumn, CompositeIndexMetadata, IndexType, IndexValue},
+    state::{
+        incr_decr_composite_index_size, CompositeIndexMap, ContractID, PartitionID,
+        COMPOSITE_INDEX_COLUMNS, COMPOSITE_INDEX_METADATA, VALUES_BINARY, VALUES_BOOL, VALUES_I32,
+        VALUES_STRING, VALUES_TIME, VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8, X,
+    },
+    util::{build_composite_index_storage_key, parse, parse_bool},
+};
+
+// Composite index keys are the concatenation of each column's value, encoded
+// as fixed-width, order-preserving bytes. Since every key in a given composite
+// index has the same length, the byte-wise ordering of keys matches the
+// column-wise ordering of their values. String values are padded (or
+// truncated) to the configured max string length.
+
+/// Load the metadata of each composite index that includes any of the given
+/// custom indices as a column.
+pub fn load_affected_indices(
+    storage: &dyn Storage,
+    column_names: &[&String],
+) -> Result<Vec<CompositeIndexMetadata>, ContractError> {
+    let mut index_names: BTreeSet<String> = BTreeSet::new();
+    for column_name in column_names.iter() {
+        for result in COMPOSITE_INDEX_COLUMNS.prefix((*column_name).clone()).keys(
+            storage,
+            None,
+            None,
+            Order::Ascending,
+        ) {
+            index_names.insert(result?);
+        }
+    }
+    let mut indices: Vec<CompositeIndexMetadata> = Vec::with_capacity(index_names.len());
+    for index_name in index_names.into_iter() {
+        indices.push(COMPOSITE_INDEX_METADATA.load(storage, index_name)?);
+    }
+    Ok(indices)
+}
+
+/// Build a contract's key in the given composite index from its currently
+/// indexed values, or None if any column value is missing.
+pub fn load_key(
+    storage: &dyn Storage,
+    contract_id: ContractID,
+    meta: &CompositeIndexMetadata,
+    max_str_len: usize,
+) -> Result<Option<Vec<u8>>, ContractError> {
+    let mut key: Vec<u8> = Vec::with_capacity(32);
+    for column in meta.columns.iter() {
+        if let Some(value) = load_value(storage, contract_id, column)? {
+            key.extend(encode_value(&value, max_str_len));
+        } else {
+            return Ok(None);
+        }
+    }
+    Ok(Some(key))
+}
+
+pub fn save_entry(
+    storage: &mut dyn Storage,
+    meta: &CompositeIndexMetadata,
+    partition: PartitionID,
+    key: &[u8],
+    contract_id: ContractID,
+) -> Result<(), ContractError> {
+    let storage_key = build_composite_index_storage_key(&meta.name);
+    let index: CompositeIndexMap = Map::new(&storage_key);
+    if !index.has(storage, (partition, key, contract_id)) {
+        index.save(storage, (partition, key, contract_id), &X)?;
+        incr_decr_composite_index_size(storage, &meta.name, true)?;
+    }
+    Ok(())
+}
+
+pub fn remove_entry(
+    storage: &mut dyn Storage,
+    meta: &CompositeIndexMetadata,
+    partition: PartitionID,
+    key: &[u8],
+    contract_id: ContractID,
+) -> Result<(), ContractError> {
+    let storage_key = build_composite_index_storage_key(&meta.name);
+    let index: CompositeIndexMap = Map::new(&storage_key);
+    if index.has(storage, (partition, key, contract_id)) {
+        index.remove(storage, (partition, key, contract_id));
+        incr_decr_composite_index_size(storage, &meta.name, false)?;
+    }
+    Ok(())
+}
+
+/// Remove a contract from every composite index that includes any of the
+/// given custom indices.
+pub fn remove_contract(
+    storage: &mut dyn Storage,
+    partition: PartitionID,
+    contract_id: ContractID,
+    column_names: &[&String],
+    max_str_len: usize,
+) -> Result<(), ContractError> {
+    for meta in load_affected_indices(storage, column_names)?.iter() {
+        if let Some(key) = load_key(storage, contract_id, meta, max_str_len)? {
+            remove_entry(storage, meta, partition, &key, contract_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Move a contract's composite index entries between partitions.
+pub fn move_contract(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    src: PartitionID,
+    dst: PartitionID,
+    column_names: &[&String],
+    max_str_len: usize,
+) -> Result<(), ContractError> {
+    for meta in load_affected_indices(storage, column_names)?.iter() {
+        if let Some(key) = load_key(storage, contract_id, meta, max_str_len)? {
+            let storage_key = build_composite_index_storage_key(&meta.name);
+            let index: CompositeIndexMap = Map::new(&storage_key);
+            index.remove(storage, (src, &key, contract_id));
+            index.save(storage, (dst, &key, contract_id), &X)?;
+        }
+    }
+    Ok(())
+}
+
+/// Build inclusive min and max keys for paginating a composite index, where
+/// leading columns equal the given prefix values and the next column lies
+/// between the given start and stop values. Remaining columns are unbounded.
+pub fn build_range_keys(
+    meta: &CompositeIndexMetadata,
+    prefix: &[String],
+    start: Option<String>,
+    stop: Option<String>,
+    max_str_len: usize,
+) -> Result<(Vec<u8>, Vec<u8>), ContractError> {
+    if prefix.len() >= meta.columns.len() {
+        return Err(ContractError::ValidationError {
+            reason: format!(
+                "composite index {} prefix must have fewer than {} values",
+                meta.name,
+                meta.columns.len()
+            ),
+        });
+    }
+
+    let mut min: Vec<u8> = Vec::with_capacity(32);
+
+    for (column, raw_value) in meta.columns.iter().zip(prefix.iter()) {
+        let value = parse_value(column, raw_value.clone())?;
+        min.extend(encode_value(&value, max_str_len));
+    }
+
+    let mut max = min.clone();
+    let range_column = &meta.columns[prefix.len()];
+    let width = get_width(&range_column.index_type, max_str_len);
+
+    if let Some(raw_value) = start {
+        let value = parse_value(range_column, raw_value)?;
+        min.extend(encode_value(&value, max_str_len));
+    } else {
+        min.extend(vec![u8::MIN; width]);
+    }
+
+    if let Some(raw_value) = stop {
+        let value = parse_value(range_column, raw_value)?;
+        max.extend(encode_value(&value, max_str_len));
+    } else {
+        max.extend(vec![u8::MAX; width]);
+    }
+
+    for column in meta.columns.iter().skip(prefix.len() + 1) {
+        let width = get_width(&column.index_type, max_str_len);
+        min.extend(vec![u8::MIN; width]);
+        max.extend(vec![u8::MAX; width]);
+    }
+
+    Ok((min, max))
+}
+
+fn get_width(
+    index_type: &IndexType,
+    max_str_len: usize,
+) -> usize {
+    match index_type {
+        IndexType::String => max_str_len,
+        IndexType::Bool | IndexType::Uint8 => 1,
+        IndexType::Uint16 => 2,
+        IndexType::Int32 | IndexType::Uint32 => 4,
+        IndexType::Timestamp | IndexType::Uint64 => 8,
+        IndexType::Uint128 => 16,
+        IndexType::Binary => 0,
+    }
+}
+
+fn encode_value(
+    value: &IndexValue,
+    max_str_len: usize,
+) -> Vec<u8> {
+    match value {
+        IndexValue::String(v) => {
+            let mut bytes = v.as_bytes().to_vec();
+            bytes.resize(max_str_len, 0);
+            bytes
+        },
+        IndexValue::Bool(v) => vec![if *v { 1 } else { 0 }],
+        IndexValue::Timestamp(v) => v.nanos().to_be_bytes().to_vec(),
+        // Flip the sign bit so that negative values sort before positive ones.
+        IndexValue::Int32(v) => ((*v as u32) ^ 0x8000_0000).to_be_bytes().to_vec(),
+        IndexValue::Uint8(v) => vec![*v],
+        IndexValue::Uint16(v) => v.to_be_bytes().to_vec(),
+        IndexValue::Uint32(v) => v.to_be_bytes().to_vec(),
+        IndexValue::Uint64(v) => v.u64().to_be_bytes().to_vec(),
+        IndexValue::Uint128(v) => v.u128().to_be_bytes().to_vec(),
+        IndexValue::Binary(v) => v.to_vec(),
+    }
+}
+
+fn parse_value(
+    column: &CompositeIndexColumn,
+    raw_value: String,
+) -> Result<IndexValue, ContractError> {
+    Ok(match column.index_type {
+        IndexType::String => IndexValue::String(normalize_string(column, raw_value)),
+        IndexType::Bool => IndexValue::Bool(parse_bool(raw_value)? != 0),
+        IndexType::Timestamp => IndexValue::Timestamp(Timestamp::from_nanos(parse(raw_value)?)),
+        IndexType::Int32 => IndexValue::Int32(parse(raw_value)?),
+        IndexType::Uint8 => IndexValue::Uint8(parse(raw_value)?),
+        IndexType::Uint16 => IndexValue::Uint16(parse(raw_value)?),
+        IndexType::Uint32 => IndexValue::Uint32(parse(raw_value)?),
+        IndexType::Uint64 => IndexValue::Uint64(Uint64::from(parse::<u64>(raw_value)?)),
+        IndexType::Uint128 => IndexValue::Uint128(Uint128::from(parse::<u128>(raw_value)?)),
+        IndexType::Binary => IndexValue::Binary(Binary::from_base64(&raw_value)?),
+    })
+}
+
+fn load_value(
+    storage: &dyn Storage,
+    contract_id: ContractID,
+    column: &CompositeIndexColumn,
+) -> Result<Option<IndexValue>, ContractError> {
+    let key = (contract_id, &column.name);
+    Ok(match column.index_type {
+        IndexType::String => VALUES_STRING
+            .may_load(storage, key)?
+            .map(|v| IndexValue::String(normalize_string(column, v))),
+        IndexType::Bool => VALUES_BOOL.may_load(storage, key)?.map(IndexValue::Bool),
+        IndexType::Timestamp => VALUES_TIME
+            .may_load(storage, key)?
+            .map(IndexValue::Timestamp),
+        IndexType::Int32 => VALUES_I32.may_load(storage, key)?.map(IndexValue::Int32),
+        IndexType::Uint8 => VALUES_U8.may_load(storage, key)?.map(IndexValue::Uint8),
+        IndexType::Uint16 => VALUES_U16.may_load(storage, key)?.map(IndexValue::Uint16),
+        IndexType::Uint32 => VALUES_U32.may_load(storage, key)?.map(IndexValue::Uint32),
+        IndexType::Uint64 => VALUES_U64.may_load(storage, key)?.map(IndexValue::Uint64),
+        IndexType::Uint128 => VALUES_U128.may_load(storage, key)?.map(IndexValue::Uint128),
+        IndexType::Binary => VALUES_BINARY
+            .may_load(storage, key)?
+            .map(IndexValue::Binary),
+    })
+}
+
+/// Lowercase string values of case-insensitive columns, as is done for keys in
+/// the column's own index.
+fn normalize_string(
+    column: &CompositeIndexColumn,
+    value: String,
+) -> String {
+    if column.case_insensitive.unwrap_or(false) {
+        value.to_lowercase()
+    } else {
+        value
+    }
+}
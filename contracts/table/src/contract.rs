@@ -1,15 +1,18 @@
 use crate::context::Context;
 use crate::error::ContractError;
 use crate::execute;
+use crate::execute::client::sweep::TEARDOWN_REPLY_ID;
 use crate::models::ReplyJob;
 use crate::msg::{
     AdminMsg, ClientMsg, ContractQueryMsg, ContractsQueryMsg, ExecuteMsg, InstantiateMsg,
     MigrateMsg, QueryMsg, TableQueryMsg,
 };
 use crate::query;
-use crate::state::{self, load_reply_job};
+use crate::state::{self, load_reply_job, REPLY_JOBS};
+use crate::subscriptions::EVENT_REPLY_ID;
 use cosmwasm_std::{
     entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    SubMsgResult,
 };
 use cw2::set_contract_version;
 
@@ -42,15 +45,21 @@ pub fn execute(
         // this "table" OR by accounts with owner auth:
         ExecuteMsg::Client(msg) => match msg {
             ClientMsg::Create(params) => execute::client::create::on_execute(ctx, params),
+            ClientMsg::CreateMany(params) => execute::client::create_many::on_execute(ctx, params),
             ClientMsg::Update(params) => execute::client::update::on_execute(ctx, params),
             ClientMsg::Delete(addr) => execute::client::delete::on_execute(ctx, addr),
             ClientMsg::Flag(params) => execute::client::flag::on_execute(ctx, params),
+            ClientMsg::SyncAdmin(addr) => execute::client::sync_admin::on_execute(ctx, addr),
+            ClientMsg::Sweep(params) => execute::client::sweep::on_execute(ctx, params),
         },
+        // CW20 hooks, sent by CW20 token contracts on behalf of clients:
+        ExecuteMsg::Receive(msg) => execute::client::receive::on_execute(ctx, msg),
         // Admin functions - require "owner" auth:
         ExecuteMsg::Admin(msg) => match msg {
             AdminMsg::SetOwner(owner) => execute::admin::set_owner::on_execute(ctx, owner),
             AdminMsg::UpdateInfo(info) => execute::admin::update_info::on_execute(ctx, info),
             AdminMsg::Unsuspend(addr) => execute::admin::unsuspend::on_execute(ctx, addr),
+            AdminMsg::Restore(params) => execute::admin::restore::on_execute(ctx, params),
 
             // Config operations
             AdminMsg::UpdateConfig(config) => {
@@ -60,17 +69,56 @@ pub fn execute(
 
             // Index operations
             AdminMsg::CreateIndex(params) => execute::admin::create_index::on_execute(ctx, params),
+            AdminMsg::CreateCompositeIndex(params) => {
+                execute::admin::create_composite_index::on_execute(ctx, params)
+            },
             AdminMsg::DeleteIndex(name) => execute::admin::delete_index::on_execute(ctx, name),
+            AdminMsg::RebuildIndex(params) => {
+                execute::admin::rebuild_index::on_execute(ctx, params)
+            },
+            AdminMsg::ResolveFlags(params) => {
+                execute::admin::resolve_flags::on_execute(ctx, params)
+            },
+            AdminMsg::AllowCodeId(params) => execute::admin::allow_code_id::on_execute(ctx, params),
+            AdminMsg::DisallowCodeId(code_id) => {
+                execute::admin::disallow_code_id::on_execute(ctx, code_id)
+            },
+            AdminMsg::Adopt(params) => execute::admin::adopt::on_execute(ctx, params),
+            AdminMsg::BulkUpdate(params) => execute::admin::bulk_update::on_execute(ctx, params),
+            AdminMsg::ResumeBulkUpdate(limit) => {
+                execute::admin::resume_bulk_update::on_execute(ctx, limit)
+            },
+            AdminMsg::CancelBulkUpdate() => execute::admin::cancel_bulk_update::on_execute(ctx),
+            AdminMsg::MigrateContracts(params) => {
+                execute::admin::migrate_contracts::on_execute(ctx, params)
+            },
+            AdminMsg::ReleaseAdmin(params) => {
+                execute::admin::release_admin::on_execute(ctx, params)
+            },
+            AdminMsg::Subscribe(params) => execute::admin::subscribe::on_execute(ctx, params),
+            AdminMsg::Unsubscribe(addr) => execute::admin::unsubscribe::on_execute(ctx, addr),
 
             // Partition operations
             AdminMsg::CreatePartition(params) => {
                 execute::admin::create_partition::on_execute(ctx, params)
             },
+            AdminMsg::UpdatePartition(params) => {
+                execute::admin::update_partition::on_execute(ctx, params)
+            },
+            AdminMsg::DeletePartition(partition) => {
+                execute::admin::delete_partition::on_execute(ctx, partition)
+            },
+            AdminMsg::MovePartition(params) => {
+                execute::admin::move_partition::on_execute(ctx, params)
+            },
             AdminMsg::SetPartition(addr, partition) => {
                 execute::admin::set_partition::on_execute(ctx, addr, partition)
             },
             // Group operations
             AdminMsg::CreateGroup(params) => execute::admin::create_group::on_execute(ctx, params),
+            AdminMsg::UpdateGroup(params) => execute::admin::update_group::on_execute(ctx, params),
+            AdminMsg::DeleteGroup(params) => execute::admin::delete_group::on_execute(ctx, params),
+            AdminMsg::MergeGroups(params) => execute::admin::merge_groups::on_execute(ctx, params),
             AdminMsg::AssignGroups(updates) => {
                 execute::admin::assign_groups::on_execute(ctx, updates)
             },
@@ -84,46 +132,111 @@ pub fn reply(
     env: Env,
     reply: Reply,
 ) -> Result<Response, ContractError> {
+    // Subscriber errors are tolerated, leaving the table's changes in place.
+    if reply.id == EVENT_REPLY_ID {
+        let mut resp = Response::new();
+        if let SubMsgResult::Err(error) = reply.result {
+            resp = resp.add_attribute("subscriber_error", error);
+        }
+        return Ok(resp);
+    }
+
+    // Likewise, swept contracts are deleted even if their Teardown hook fails.
+    if reply.id == TEARDOWN_REPLY_ID {
+        let mut resp = Response::new();
+        if let SubMsgResult::Err(error) = reply.result {
+            resp = resp.add_attribute("teardown_error", error);
+        }
+        return Ok(resp);
+    }
+
+    // Each job gets exactly one reply, after which it's no longer needed.
     let job = load_reply_job(deps.storage, reply.id)?;
+    REPLY_JOBS.remove(deps.storage, reply.id);
+
     return Ok(match job {
-        ReplyJob::Create { params, initiator } => {
-            execute::client::create::on_reply(deps, env, reply, params, initiator)
-        },
+        ReplyJob::Create {
+            params,
+            initiator,
+            best_effort,
+            sender,
+            fee,
+        } => execute::client::create::on_reply(
+            deps,
+            env,
+            reply,
+            *params,
+            initiator,
+            best_effort,
+            sender,
+            fee,
+        ),
+        ReplyJob::Migrate {
+            contract_id,
+            code_id,
+        } => execute::admin::migrate_contracts::on_reply(
+            deps,
+            env,
+            reply,
+            contract_id.into(),
+            code_id,
+        ),
     }?);
 }
 
 #[entry_point]
 pub fn query(
     deps: Deps,
-    _env: Env,
+    env: Env,
     msg: QueryMsg,
 ) -> Result<Binary, ContractError> {
     let result = match msg {
         // Paginate top-level data structures related to the table.
         QueryMsg::Table(msg) => match msg {
             TableQueryMsg::Indices(params) => to_json_binary(&query::table::indices(deps, params)?),
+            TableQueryMsg::CompositeIndices(params) => {
+                to_json_binary(&query::table::composite_indices(deps, params)?)
+            },
             TableQueryMsg::Partitions(params) => {
                 to_json_binary(&query::table::partitions(deps, params)?)
             },
             TableQueryMsg::Tags(params) => to_json_binary(&query::table::tags(deps, params)?),
             TableQueryMsg::Groups(params) => to_json_binary(&query::table::groups(deps, params)?),
+            TableQueryMsg::Flagged(params) => to_json_binary(&query::table::flagged(deps, params)?),
+            TableQueryMsg::CodeIds(params) => {
+                to_json_binary(&query::table::code_ids(deps, params)?)
+            },
+            TableQueryMsg::Aggregate(params) => {
+                to_json_binary(&query::table::aggregate(deps, params)?)
+            },
+            TableQueryMsg::Facets(params) => to_json_binary(&query::table::facets(deps, params)?),
+            TableQueryMsg::Changes(params) => to_json_binary(&query::table::changes(deps, params)?),
+            TableQueryMsg::CreationFailures(params) => {
+                to_json_binary(&query::table::creation_failures(deps, params)?)
+            },
         },
         // Paginate collections of contracts by various means.
         QueryMsg::Contracts(msg) => match msg {
             ContractsQueryMsg::Range(params) => {
-                to_json_binary(&query::contracts::range(deps, params)?)
+                to_json_binary(&query::contracts::range(deps, env, params)?)
             },
             ContractsQueryMsg::WithTag(params) => {
-                to_json_binary(&query::contracts::with_tag(deps, params)?)
+                to_json_binary(&query::contracts::with_tag(deps, env, params)?)
+            },
+            ContractsQueryMsg::WithTagMatch(params) => {
+                to_json_binary(&query::contracts::with_tag_match(deps, env, params)?)
             },
             ContractsQueryMsg::InGroup(params) => {
-                to_json_binary(&query::contracts::in_group(deps, params)?)
+                to_json_binary(&query::contracts::in_group(deps, env, params)?)
             },
             ContractsQueryMsg::ByAddresses(mut params) => {
-                to_json_binary(&query::contracts::by_addresses(deps, &mut params)?)
+                to_json_binary(&query::contracts::by_addresses(deps, env, &mut params)?)
             },
             ContractsQueryMsg::RelatedTo(params) => {
-                to_json_binary(&query::contracts::related_to(deps, params)?)
+                to_json_binary(&query::contracts::related_to(deps, env, params)?)
+            },
+            ContractsQueryMsg::Where(params) => {
+                to_json_binary(&query::contracts::select_where(deps, env, params)?)
             },
         },
         // Paginate relationshps, groups, & tags associated with a given contract.
@@ -138,6 +251,15 @@ pub fn query(
             ContractQueryMsg::IsRelatedTo(params) => {
                 to_json_binary(&query::contract::is_related_to(deps, params)?)
             },
+            ContractQueryMsg::Values(params) => {
+                to_json_binary(&query::contract::values(deps, params)?)
+            },
+            ContractQueryMsg::Flags(params) => {
+                to_json_binary(&query::contract::flags(deps, params)?)
+            },
+            ContractQueryMsg::Archived(params) => {
+                to_json_binary(&query::contract::archived(deps, params)?)
+            },
         },
     }?;
     Ok(result)
@@ -150,5 +272,6 @@ pub fn migrate(
     _msg: MigrateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    state::rebuild_code_id_sizes(deps.storage)?;
     Ok(Response::default())
 }
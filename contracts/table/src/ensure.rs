@@ -1,8 +1,11 @@
-use cosmwasm_std::Storage;
+use cosmwasm_std::{Addr, Env, Order, Storage, Timestamp};
 
 use crate::{
     error::ContractError,
-    state::{CODE_ID_ALLOWLIST, CONFIG_CODE_ID_ALLOWLIST_ENABLED},
+    state::{
+        PartitionID, CODE_ID_ALLOWLIST, CONFIG_CODE_ID_ALLOWLIST_ENABLED, IX_CREATED_BY,
+        PARTITION_METADATA, PARTITION_SIZES,
+    },
 };
 
 pub fn ensure_authorized_code_id(
@@ -18,3 +21,82 @@ pub fn ensure_authorized_code_id(
     }
     Ok(())
 }
+
+/// Ensure that the partition's policy allows a new contract with the given
+/// code ID and initiator, created by a contract in the table if `is_child`.
+/// Contracts moved or restored into the partition are checked the same way.
+pub fn ensure_partition_policy(
+    storage: &dyn Storage,
+    partition: PartitionID,
+    code_id: u64,
+    initiator: &Addr,
+    is_child: bool,
+) -> Result<(), ContractError> {
+    let policy = match PARTITION_METADATA.may_load(storage, partition)? {
+        Some(meta) => match meta.policy {
+            Some(policy) => policy,
+            None => return Ok(()),
+        },
+        None => {
+            return Err(ContractError::PartitionNotFound {
+                reason: format!("Partition ID {} does not exist", partition),
+            })
+        },
+    };
+
+    if is_child && !policy.allow_child_creation.unwrap_or(true) {
+        return Err(ContractError::ChildCreationNotAllowed { partition });
+    }
+
+    if let Some(code_ids) = &policy.code_ids {
+        if !code_ids.iter().any(|id| id.u64() == code_id) {
+            return Err(ContractError::CodeIdNotAllowed { partition, code_id });
+        }
+    }
+
+    if let Some(max_size) = policy.max_size {
+        let size = PARTITION_SIZES
+            .may_load(storage, partition)?
+            .unwrap_or_default();
+        if size >= max_size {
+            return Err(ContractError::PartitionFull {
+                partition,
+                max_size: max_size.u64(),
+            });
+        }
+    }
+
+    // Count the initiator's contracts via the created-by index, stopping at
+    // the cap.
+    if let Some(max) = policy.max_per_initiator {
+        let n = IX_CREATED_BY
+            .prefix((partition, initiator.to_string()))
+            .keys_raw(storage, None, None, Order::Ascending)
+            .take(max as usize)
+            .count();
+        if n >= max as usize {
+            return Err(ContractError::InitiatorQuotaExceeded {
+                partition,
+                initiator: initiator.to_string(),
+                max,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Ensure that a contract's new expiration time, if any, is in the future.
+pub fn ensure_valid_expiration(
+    env: &Env,
+    expires_at: Option<Timestamp>,
+) -> Result<(), ContractError> {
+    if let Some(expires_at) = expires_at {
+        if expires_at <= env.block.time {
+            return Err(ContractError::ValidationError {
+                reason: "expires_at must be in the future".to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use cosmwasm_std::StdError;
 use thiserror::Error;
 
-use crate::state::ContractID;
+use crate::state::{ContractID, PartitionID};
 
 #[derive(Debug, Error)]
 pub enum ContractError {
@@ -38,6 +38,31 @@ pub enum ContractError {
     #[error("ContractSuspended: contract {contract_id:?} has been flagged and suspended")]
     ContractSuspended { contract_id: ContractID },
 
+    #[error("PartitionFull: partition {partition:?} has reached its max size of {max_size:?}")]
+    PartitionFull {
+        partition: PartitionID,
+        max_size: u64,
+    },
+
+    #[error("CodeIdNotAllowed: code ID {code_id:?} not allowed in partition {partition:?}")]
+    CodeIdNotAllowed {
+        partition: PartitionID,
+        code_id: u64,
+    },
+
+    #[error("InitiatorQuotaExceeded: {initiator:?} is at {max:?} contracts in {partition:?}")]
+    InitiatorQuotaExceeded {
+        partition: PartitionID,
+        initiator: String,
+        max: u32,
+    },
+
+    #[error("ChildCreationNotAllowed: partition {partition:?} does not allow child creation")]
+    ChildCreationNotAllowed { partition: PartitionID },
+
+    #[error("InsufficientFee: {reason:?}")]
+    InsufficientFee { reason: String },
+
     #[error("InvalidCursor: {reason:?}")]
     InvalidCursor { reason: String },
 
@@ -0,0 +1,67 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Uint64};
+
+use crate::state::PartitionID;
+
+#[cw_serde]
+pub enum TableEventKind {
+    Create,
+    Update,
+    Flag,
+    Suspend,
+    Resume,
+    Move,
+    Delete,
+    Restore,
+}
+
+#[cw_serde]
+pub struct TableEventArgs {
+    pub table: Addr,
+    pub contract: Addr,
+    pub id: Uint64,
+    pub initiator: Addr,
+}
+
+#[cw_serde]
+pub struct TableMoveEventArgs {
+    pub table: Addr,
+    pub contract: Addr,
+    pub id: Uint64,
+    pub initiator: Addr,
+    pub src: PartitionID,
+    pub dst: PartitionID,
+}
+
+/// Events sent to subscriber contracts.
+#[cw_serde]
+pub enum TableEvent {
+    Create(TableEventArgs),
+    Update(TableEventArgs),
+    Flag(TableEventArgs),
+    Suspend(TableEventArgs),
+    Resume(TableEventArgs),
+    Move(TableMoveEventArgs),
+    Delete(TableEventArgs),
+    Restore(TableEventArgs),
+}
+
+#[cw_serde]
+pub enum TableEventMsgEnvelope {
+    TableEvent(TableEvent),
+}
+
+impl TableEvent {
+    pub fn kind(&self) -> TableEventKind {
+        match self {
+            Self::Create(_) => TableEventKind::Create,
+            Self::Update(_) => TableEventKind::Update,
+            Self::Flag(_) => TableEventKind::Flag,
+            Self::Suspend(_) => TableEventKind::Suspend,
+            Self::Resume(_) => TableEventKind::Resume,
+            Self::Move(_) => TableEventKind::Move,
+            Self::Delete(_) => TableEventKind::Delete,
+            Self::Restore(_) => TableEventKind::Restore,
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use cosmwasm_std::{attr, Binary, Event, Response};
+
+use crate::{
+    context::Context,
+    ensure::ensure_authorized_code_id,
+    error::ContractError,
+    events::TableEvent,
+    execute::client::create::{apply_code_id_defaults, build_setup_msg, register_contract},
+    msg::{AdoptionParams, CreationParams},
+    state::{ensure_allowed_by_acl, exists_contract_address},
+    subscriptions::{build_event_args, build_event_submsgs},
+};
+
+/// Register an existing contract with the table as if it had been created
+/// through it, indexing it and adding it to its groups and tags in the same
+/// way.
+pub fn on_execute(
+    ctx: Context,
+    params: AdoptionParams,
+) -> Result<Response, ContractError> {
+    let action = "adopt";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/adopt")?;
+
+    let contract_addr = deps.api.addr_validate(params.contract.as_str())?;
+
+    if contract_addr == env.contract.address
+        || exists_contract_address(deps.storage, &contract_addr)
+    {
+        return Err(ContractError::ValidationError {
+            reason: format!("contract {} cannot be adopted", contract_addr),
+        });
+    }
+
+    // Make sure the given code ID is the contract's actual code ID.
+    let contract_info = deps.querier.query_wasm_contract_info(&contract_addr)?;
+    if contract_info.code_id != params.code_id.u64() {
+        return Err(ContractError::ValidationError {
+            reason: format!(
+                "contract {} has code ID {}, not {}",
+                contract_addr, contract_info.code_id, params.code_id
+            ),
+        });
+    }
+
+    ensure_authorized_code_id(deps.storage, params.code_id.into())?;
+
+    let admin = contract_info
+        .admin
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let is_managed = admin.as_ref() == Some(&env.contract.address);
+    let initiator = params.initiator.unwrap_or(info.sender);
+
+    let creation_params = apply_code_id_defaults(
+        deps.storage,
+        CreationParams {
+            code_id: params.code_id,
+            instantiate_msg: Binary::default(),
+            admin,
+            initiator: Some(initiator.clone()),
+            partition: params.partition,
+            label: None,
+            groups: params.groups,
+            tags: params.tags,
+            use_lifecycle_hooks: params.use_lifecycle_hooks,
+            funds: None,
+            expires_at: None,
+        },
+    )?;
+
+    let use_lifecycle_hooks = creation_params.use_lifecycle_hooks.unwrap_or_default();
+    let contract_id = register_contract(
+        deps.storage,
+        &env,
+        &contract_addr,
+        creation_params,
+        &initiator,
+        is_managed,
+    )?;
+
+    let mut resp = Response::new()
+        .add_attributes(vec![
+            attr("action", action),
+            attr("contract_id", contract_id.to_string()),
+        ])
+        .add_event(
+            Event::new("post_create")
+                .add_attribute("contract_address", contract_addr.to_string())
+                .add_attribute("contract_id", contract_id.to_string()),
+        );
+
+    resp = resp.add_submessages(build_event_submsgs(
+        deps.storage,
+        TableEvent::Create(build_event_args(
+            &env,
+            &contract_addr,
+            contract_id,
+            &initiator,
+        )),
+    )?);
+
+    if use_lifecycle_hooks && params.setup.unwrap_or(false) {
+        resp = resp.add_message(build_setup_msg(
+            &env,
+            &contract_addr,
+            initiator,
+            contract_id,
+        )?);
+    }
+
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env},
+        Addr, Binary, Order, StdResult, Timestamp, Uint64,
+    };
+
+    use crate::{
+        execute::client::create::register_contract,
+        msg::{CreationParams, PartitionMetadata, PartitionSelector},
+        state::{
+            CONFIG_STR_MAX_LEN, CONTRACT_ID_COUNTER, CONTRACT_TAGS, IX_TAG, PARTITION_METADATA,
+            PARTITION_TAG_COUNTS,
+        },
+        util::{pad, trim_padding},
+    };
+
+    #[test]
+    fn adopted_contract_gets_its_tags() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let storage = deps.as_mut().storage;
+        let max_str_len = 16;
+
+        CONFIG_STR_MAX_LEN
+            .save(storage, &(max_str_len as u16))
+            .unwrap();
+        CONTRACT_ID_COUNTER.save(storage, &Uint64::zero()).unwrap();
+        PARTITION_METADATA
+            .save(
+                storage,
+                1,
+                &PartitionMetadata {
+                    name: "default".to_owned(),
+                    description: None,
+                    created_at: Timestamp::default(),
+                    policy: None,
+                },
+            )
+            .unwrap();
+
+        // The params that Adopt builds for an adopted contract.
+        let contract_id = register_contract(
+            storage,
+            &env,
+            &Addr::unchecked("adopted"),
+            CreationParams {
+                code_id: Uint64::one(),
+                instantiate_msg: Binary::default(),
+                admin: None,
+                initiator: Some(Addr::unchecked("initiator")),
+                partition: Some(PartitionSelector::Id(1)),
+                label: None,
+                groups: None,
+                tags: Some(vec!["blue".to_owned(), "red".to_owned()]),
+                use_lifecycle_hooks: None,
+                funds: None,
+                expires_at: None,
+            },
+            &Addr::unchecked("initiator"),
+            false,
+        )
+        .unwrap();
+
+        let tags: Vec<String> = CONTRACT_TAGS
+            .prefix(contract_id)
+            .keys(storage, None, None, Order::Ascending)
+            .map(|r| r.map(|tag| trim_padding(&tag)))
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(tags, vec!["blue".to_owned(), "red".to_owned()]);
+
+        for tag in tags.iter() {
+            let tag = pad(tag, max_str_len);
+            assert!(IX_TAG.has(storage, (1, &tag, contract_id)));
+            assert_eq!(PARTITION_TAG_COUNTS.load(storage, (1, &tag)).unwrap(), 1);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use cosmwasm_std::{attr, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    msg::{CodeIdAllowanceParams, CodeIdMetadata},
+    state::{
+        ensure_allowed_by_acl, ensure_partition_exists, resolve_partition_id, CODE_ID_ALLOWLIST,
+        GROUP_METADATA,
+    },
+};
+
+/// Add a code ID to the allowlist or replace its existing metadata.
+pub fn on_execute(
+    ctx: Context,
+    params: CodeIdAllowanceParams,
+) -> Result<Response, ContractError> {
+    let action = "allow_code_id";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/allow-code-id")?;
+
+    let partition = match params.partition {
+        Some(selector) => {
+            let partition = resolve_partition_id(deps.storage, selector)?;
+            ensure_partition_exists(deps.storage, partition)?;
+            Some(partition)
+        },
+        None => None,
+    };
+
+    for group_id in params.groups.as_ref().unwrap_or(&vec![]).iter() {
+        if !GROUP_METADATA.has(deps.storage, *group_id) {
+            return Err(ContractError::GroupNotFound {
+                reason: format!("Group ID {} does not exist", group_id),
+            });
+        }
+    }
+
+    CODE_ID_ALLOWLIST.save(
+        deps.storage,
+        params.code_id.into(),
+        &CodeIdMetadata {
+            name: params.name,
+            version: params.version,
+            use_lifecycle_hooks: params.use_lifecycle_hooks,
+            partition,
+            groups: params.groups,
+            tags: params.tags,
+            creation_fee: params.creation_fee,
+            created_by: info.sender,
+            created_at: env.block.time,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("code_id", params.code_id.to_string()),
+    ]))
+}
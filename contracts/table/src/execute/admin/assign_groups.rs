@@ -1,35 +1,66 @@
-use cosmwasm_std::Response;
+use cosmwasm_std::{Response, Storage};
 
 use crate::{
+    changes::record_change,
     context::Context,
     error::ContractError,
+    models::ChangeKind,
     msg::GroupUpdates,
-    state::{append_group, ensure_allowed_by_acl, load_contract_id, remove_from_group},
+    state::{
+        append_group, ensure_allowed_by_acl, load_contract_id, remove_from_group, ContractID,
+        GroupID,
+    },
 };
 
 pub fn on_execute(
     ctx: Context,
     updates: GroupUpdates,
 ) -> Result<Response, ContractError> {
-    let Context { deps, info, .. } = ctx;
+    let Context { deps, info, env } = ctx;
     ensure_allowed_by_acl(&deps, &info.sender, "/table/assign-groups")?;
 
     let contract_addr = updates.contract;
     let contract_id = load_contract_id(deps.storage, &contract_addr)?;
 
+    assign_groups(
+        deps.storage,
+        contract_id,
+        updates.remove.as_ref(),
+        updates.add.as_ref(),
+    )?;
+
+    record_change(
+        deps.storage,
+        &env,
+        contract_id,
+        &info.sender,
+        ChangeKind::Groups,
+    )?;
+
+    Ok(Response::new().add_attribute("action", "assign_groups"))
+}
+
+/// Remove a contract from the groups in `remove` and then add it to those in
+/// `add`.
+pub fn assign_groups(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    remove: Option<&Vec<GroupID>>,
+    add: Option<&Vec<GroupID>>,
+) -> Result<(), ContractError> {
     // Remove contract from the given groups.
-    if let Some(group_ids) = updates.remove {
+    if let Some(group_ids) = remove {
         for group_id in group_ids.iter() {
-            remove_from_group(deps.storage, *group_id, contract_id)?;
+            remove_from_group(storage, *group_id, contract_id)?;
         }
     }
 
     // Add contract to the given groups.
-    if let Some(group_ids) = updates.add {
+    if let Some(group_ids) = add {
         for group_id in group_ids.iter() {
-            append_group(deps.storage, *group_id, contract_id)?;
+            append_group(storage, *group_id, contract_id)?;
         }
     }
 
-    Ok(Response::new().add_attribute("action", "assign_groups"))
+    Ok(())
 }
@@ -0,0 +1,255 @@
+use cosmwasm_std::{attr, Addr, Deps, DepsMut, Env, Response, Storage, Uint64};
+
+use crate::{
+    changes::record_change,
+    composite::load_affected_indices,
+    context::Context,
+    error::ContractError,
+    events::TableEvent,
+    execute::{
+        admin::assign_groups::assign_groups,
+        client::update::{get_key_name, update_indices, update_tags, upsert_metadata},
+    },
+    models::{BulkUpdateJob, ChangeKind},
+    msg::{BulkUpdateParams, BulkUpdateSelector, KeyValue, RangeSelector},
+    query::contracts::{in_group, range, with_tag},
+    state::{
+        ensure_allowed_by_acl, ContractID, BULK_UPDATE_JOB, CONFIG_STR_MAX_LEN, CONTRACT_ADDR_2_ID,
+        CONTRACT_METADATA,
+    },
+    subscriptions::{build_event_args, build_event_submsgs},
+};
+
+pub const DEFAULT_BATCH_SIZE: u32 = 25;
+pub const MAX_BATCH_SIZE: u32 = 100;
+
+/// Start a bulk update, applying it to the first batch of selected contracts.
+/// Only one bulk update may be in progress at a time.
+pub fn on_execute(
+    ctx: Context,
+    params: Box<BulkUpdateParams>,
+) -> Result<Response, ContractError> {
+    let action = "bulk_update";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/bulk-update")?;
+
+    if BULK_UPDATE_JOB.exists(deps.storage) {
+        return Err(ContractError::ValidationError {
+            reason: "a bulk update is already in progress".to_owned(),
+        });
+    }
+
+    let BulkUpdateParams {
+        selector,
+        initiator,
+        values,
+        tags,
+        groups,
+        limit,
+    } = *params;
+
+    if let Some(values) = &values {
+        ensure_stable_selector(deps.storage, &selector, values)?;
+    }
+
+    let initiator = match initiator {
+        Some(addr) => deps.api.addr_validate(addr.as_str())?,
+        None => info.sender.clone(),
+    };
+
+    let mut job = BulkUpdateJob {
+        selector,
+        initiator,
+        values,
+        tags,
+        groups,
+        n_updated: Uint64::zero(),
+        created_by: info.sender,
+        created_at: env.block.time,
+    };
+
+    process_batch(deps, &env, &mut job, limit, action)
+}
+
+/// Ensure that updating the given values doesn't re-key contracts in the
+/// index scanned by a Range selector. Otherwise, updated contracts could move
+/// past the selector's cursor and be updated again.
+fn ensure_stable_selector(
+    storage: &dyn Storage,
+    selector: &BulkUpdateSelector,
+    values: &[KeyValue],
+) -> Result<(), ContractError> {
+    let index = match selector {
+        BulkUpdateSelector::Range(params) => &params.index,
+        _ => return Ok(()),
+    };
+    let column_names: Vec<&String> = values.iter().map(get_key_name).collect();
+    let is_rekeyed = match index {
+        RangeSelector::UpdatedAt | RangeSelector::UpdatedBy | RangeSelector::Rev => true,
+        RangeSelector::CreatedAt
+        | RangeSelector::CreatedBy
+        | RangeSelector::CodeId
+        | RangeSelector::Id => false,
+        RangeSelector::Composite(name) => load_affected_indices(storage, &column_names)?
+            .iter()
+            .any(|meta| meta.name == *name),
+        RangeSelector::String(name)
+        | RangeSelector::Bool(name)
+        | RangeSelector::Timestamp(name)
+        | RangeSelector::Int32(name)
+        | RangeSelector::Uint8(name)
+        | RangeSelector::Uint16(name)
+        | RangeSelector::Uint32(name)
+        | RangeSelector::Uint64(name)
+        | RangeSelector::Uint128(name)
+        | RangeSelector::Binary(name) => column_names.contains(&name),
+    };
+    if is_rekeyed {
+        return Err(ContractError::ValidationError {
+            reason: "values can't be updated when selecting contracts by a range over \
+                     updated_at, updated_by, rev or an index of an updated value"
+                .to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Update the job's next batch of contracts, saving the job for the next call
+/// if any contracts remain or removing it otherwise.
+pub fn process_batch(
+    deps: DepsMut,
+    env: &Env,
+    job: &mut BulkUpdateJob,
+    limit: Option<u32>,
+    action: &str,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE).clamp(1, MAX_BATCH_SIZE);
+    let (addrs, is_done) = load_next_batch(deps.as_ref(), env, &mut job.selector, limit)?;
+    let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
+    let mut n_updated: u64 = 0;
+    let mut resp = Response::new();
+
+    for addr in addrs.iter() {
+        // Skip given addresses that aren't in the table.
+        if let Some(contract_id) = CONTRACT_ADDR_2_ID.may_load(deps.storage, addr)? {
+            let contract_id: ContractID = contract_id.into();
+            update_contract(deps.storage, env, job, contract_id, max_str_len)?;
+            resp = resp.add_submessages(build_event_submsgs(
+                deps.storage,
+                TableEvent::Update(build_event_args(env, addr, contract_id, &job.initiator)),
+            )?);
+            n_updated += 1;
+        }
+    }
+
+    job.n_updated += Uint64::from(n_updated);
+
+    if is_done {
+        BULK_UPDATE_JOB.remove(deps.storage);
+    } else {
+        BULK_UPDATE_JOB.save(deps.storage, job)?;
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", action),
+        attr("n_updated", n_updated.to_string()),
+        attr("total_updated", job.n_updated.to_string()),
+        attr("status", if is_done { "done" } else { "pending" }),
+    ]))
+}
+
+/// Load the addresses of the next batch of selected contracts, advancing the
+/// selector past them. Returns true if no contracts remain.
+fn load_next_batch(
+    deps: Deps,
+    env: &Env,
+    selector: &mut BulkUpdateSelector,
+    limit: u32,
+) -> Result<(Vec<Addr>, bool), ContractError> {
+    Ok(match selector {
+        BulkUpdateSelector::Addresses(addrs) => {
+            let n = (limit as usize).min(addrs.len());
+            let batch: Vec<Addr> = addrs.drain(..n).collect();
+            (batch, addrs.is_empty())
+        },
+        BulkUpdateSelector::Tag(params) => {
+            params.limit = Some(limit);
+            params.details = None;
+            params.select = None;
+            let resp = with_tag(deps, env.clone(), params.clone())?;
+            params.cursor = resp.cursor;
+            (
+                resp.contracts.into_iter().map(|c| c.address).collect(),
+                params.cursor.is_none(),
+            )
+        },
+        BulkUpdateSelector::Group(params) => {
+            params.limit = Some(limit);
+            params.details = None;
+            params.select = None;
+            let resp = in_group(deps, env.clone(), params.clone())?;
+            params.cursor = resp.cursor;
+            (
+                resp.contracts.into_iter().map(|c| c.address).collect(),
+                params.cursor.is_none(),
+            )
+        },
+        BulkUpdateSelector::Range(params) => {
+            params.limit = Some(limit);
+            params.details = None;
+            params.select = None;
+            let resp = range(deps, env.clone(), params.clone())?;
+            params.cursor = resp.cursor;
+            (
+                resp.contracts.into_iter().map(|c| c.address).collect(),
+                params.cursor.is_none(),
+            )
+        },
+    })
+}
+
+fn update_contract(
+    storage: &mut dyn Storage,
+    env: &Env,
+    job: &BulkUpdateJob,
+    contract_id: ContractID,
+    max_str_len: usize,
+) -> Result<(), ContractError> {
+    let partition = CONTRACT_METADATA.load(storage, contract_id)?.partition;
+
+    if let Some(values) = &job.values {
+        upsert_metadata(storage, env, partition, &job.initiator, contract_id)?;
+        update_indices(storage, partition, contract_id, values.clone(), max_str_len)?;
+        record_change(
+            storage,
+            env,
+            contract_id,
+            &job.initiator,
+            ChangeKind::Update,
+        )?;
+    }
+
+    if let Some(tags) = &job.tags {
+        update_tags(storage, partition, contract_id, tags.clone(), max_str_len)?;
+        record_change(storage, env, contract_id, &job.initiator, ChangeKind::Tags)?;
+    }
+
+    if let Some(groups) = &job.groups {
+        assign_groups(
+            storage,
+            contract_id,
+            groups.remove.as_ref(),
+            groups.add.as_ref(),
+        )?;
+        record_change(
+            storage,
+            env,
+            contract_id,
+            &job.initiator,
+            ChangeKind::Groups,
+        )?;
+    }
+
+    Ok(())
+}
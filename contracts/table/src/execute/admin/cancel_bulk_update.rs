@@ -0,0 +1,28 @@
+use cosmwasm_std::{attr, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    state::{ensure_allowed_by_acl, BULK_UPDATE_JOB},
+};
+
+/// Abandon the bulk update in progress. Contracts already updated keep their
+/// updates.
+pub fn on_execute(ctx: Context) -> Result<Response, ContractError> {
+    let action = "cancel_bulk_update";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/bulk-update")?;
+
+    let n_updated = BULK_UPDATE_JOB
+        .may_load(deps.storage)?
+        .map(|job| job.n_updated)
+        .unwrap_or_default();
+
+    BULK_UPDATE_JOB.remove(deps.storage);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("total_updated", n_updated.to_string()),
+    ]))
+}
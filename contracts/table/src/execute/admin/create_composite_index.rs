@@ -0,0 +1,21 @@
+use cosmwasm_std::Response;
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    msg::CompositeIndexCreationParams,
+    state::{create_composite_index, ensure_allowed_by_acl},
+};
+
+pub fn on_execute(
+    ctx: Context,
+    params: CompositeIndexCreationParams,
+) -> Result<Response, ContractError> {
+    let action = "create_composite_index";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/create-index")?;
+    create_composite_index(deps.storage, params)?;
+
+    Ok(Response::new().add_attribute("action", action))
+}
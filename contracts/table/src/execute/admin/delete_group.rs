@@ -0,0 +1,133 @@
+use cosmwasm_std::{attr, Order, Response, StdResult, Storage};
+
+use crate::{
+    changes::record_change,
+    context::Context,
+    error::ContractError,
+    models::ChangeKind,
+    msg::GroupDeletionParams,
+    state::{
+        ensure_allowed_by_acl, remove_from_group, ContractID, GroupID, CODE_ID_ALLOWLIST,
+        GROUP_IX_CREATED_AT, GROUP_IX_NAME, GROUP_METADATA, GROUP_REPLACEMENTS, IX_GROUP,
+        SUBSCRIPTIONS,
+    },
+};
+
+pub const DEFAULT_BATCH_SIZE: u32 = 100;
+pub const MAX_BATCH_SIZE: u32 = 500;
+
+/// Remove a batch of contracts from a group, removing the group itself once
+/// it's empty.
+pub fn on_execute(
+    ctx: Context,
+    params: GroupDeletionParams,
+) -> Result<Response, ContractError> {
+    let action = "delete_group";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/delete-group")?;
+
+    let group_id = params.group;
+
+    if !GROUP_METADATA.has(deps.storage, group_id) {
+        return Err(ContractError::GroupNotFound {
+            reason: format!("group {} does not exist", group_id),
+        });
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE) as usize;
+
+    let contract_ids = load_group_batch(deps.storage, group_id, limit)?;
+
+    for contract_id in contract_ids.iter() {
+        remove_from_group(deps.storage, group_id, *contract_id)?;
+        record_change(
+            deps.storage,
+            &env,
+            *contract_id,
+            &info.sender,
+            ChangeKind::Groups,
+        )?;
+    }
+
+    let is_done = contract_ids.len() < limit;
+    if is_done {
+        remove_group(deps.storage, group_id, None)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("group", group_id.to_string()),
+        attr("removed", contract_ids.len().to_string()),
+        attr("status", if is_done { "deleted" } else { "deleting" }),
+    ]))
+}
+
+/// Load the IDs of the next batch of contracts in a group.
+pub fn load_group_batch(
+    storage: &dyn Storage,
+    group_id: GroupID,
+    limit: usize,
+) -> Result<Vec<ContractID>, ContractError> {
+    Ok(IX_GROUP
+        .prefix(group_id)
+        .keys(storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?)
+}
+
+/// Remove an empty group's metadata, replacing references to it in code ID
+/// defaults and subscription filters with the given group, if any. The
+/// replacement is remembered so that archived members, which aren't in the
+/// group's index, can follow it when restored.
+pub fn remove_group(
+    storage: &mut dyn Storage,
+    group_id: GroupID,
+    replacement: Option<GroupID>,
+) -> Result<(), ContractError> {
+    let meta = GROUP_METADATA.load(storage, group_id)?;
+
+    GROUP_METADATA.remove(storage, group_id);
+    GROUP_IX_NAME.remove(storage, (meta.name, group_id));
+    GROUP_IX_CREATED_AT.remove(storage, (meta.created_at.nanos(), group_id));
+
+    if let Some(id) = replacement {
+        GROUP_REPLACEMENTS.save(storage, group_id, &id)?;
+    }
+
+    let replace = |group_ids: &mut Vec<GroupID>| -> bool {
+        if !group_ids.contains(&group_id) {
+            return false;
+        }
+        group_ids.retain(|id| *id != group_id);
+        if let Some(id) = replacement {
+            if !group_ids.contains(&id) {
+                group_ids.push(id);
+            }
+        }
+        true
+    };
+
+    for (code_id, mut code_id_meta) in CODE_ID_ALLOWLIST
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        if code_id_meta.groups.as_mut().map(replace).unwrap_or(false) {
+            CODE_ID_ALLOWLIST.save(storage, code_id, &code_id_meta)?;
+        }
+    }
+
+    for (subscriber, mut subscription) in SUBSCRIPTIONS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        if subscription.groups.as_mut().map(replace).unwrap_or(false) {
+            SUBSCRIPTIONS.save(storage, &subscriber, &subscription)?;
+        }
+    }
+
+    Ok(())
+}
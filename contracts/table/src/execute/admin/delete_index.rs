@@ -1,12 +1,17 @@
-use cosmwasm_std::Response;
+use cosmwasm_std::{Order, Response};
 use cw_storage_plus::Map;
 
 use crate::{
+    aggregates,
     context::Context,
     error::ContractError,
+    facets,
     msg::IndexType,
-    state::{ensure_allowed_by_acl, ContractID, PartitionID, INDEX_METADATA},
-    util::build_index_storage_key,
+    state::{
+        ensure_allowed_by_acl, CompositeIndexMap, ContractID, PartitionID, COMPOSITE_INDEX_COLUMNS,
+        COMPOSITE_INDEX_METADATA, INDEX_METADATA,
+    },
+    util::{build_composite_index_storage_key, build_index_storage_key},
 };
 
 pub fn on_execute(
@@ -17,6 +22,33 @@ pub fn on_execute(
 
     ensure_allowed_by_acl(&deps, &info.sender, "/table/delete-index")?;
 
+    // Deleting a composite index only clears its own entries and column links.
+    if let Some(meta) = COMPOSITE_INDEX_METADATA.may_load(deps.storage, index_name.clone())? {
+        COMPOSITE_INDEX_METADATA.remove(deps.storage, index_name.clone());
+        for column in meta.columns.iter() {
+            COMPOSITE_INDEX_COLUMNS.remove(deps.storage, (column.name.clone(), index_name.clone()));
+        }
+        let map_name = &build_composite_index_storage_key(&index_name);
+        let map: CompositeIndexMap = Map::new(map_name);
+        map.clear(deps.storage);
+        return Ok(Response::new().add_attribute("action", "delete_index"));
+    }
+
+    // A custom index can't be deleted while a composite index depends on it.
+    if let Some(composite_name) = COMPOSITE_INDEX_COLUMNS
+        .prefix(index_name.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .transpose()?
+    {
+        return Err(ContractError::ValidationError {
+            reason: format!(
+                "index '{}' is a column of composite index '{}'",
+                index_name, composite_name
+            ),
+        });
+    }
+
     if let Some(meta) = INDEX_METADATA.may_load(deps.storage, index_name.clone())? {
         INDEX_METADATA.remove(deps.storage, index_name.clone());
 
@@ -64,6 +96,9 @@ pub fn on_execute(
                 map.clear(deps.storage);
             },
         };
+
+        aggregates::clear_index(deps.storage, &index_name)?;
+        facets::clear_index(deps.storage, &index_name)?;
     } else {
         return Err(ContractError::NotAuthorized {
             reason: format!("index metadata does not exist for '{}'", index_name),
@@ -0,0 +1,80 @@
+use cosmwasm_std::{attr, Order, Response, StdResult};
+
+use crate::{
+    aggregates,
+    context::Context,
+    error::ContractError,
+    facets,
+    msg::PartitionSelector,
+    state::{
+        ensure_allowed_by_acl, ensure_partition_exists, resolve_partition_id, CODE_ID_ALLOWLIST,
+        PARTITION_METADATA, PARTITION_NAME_2_ID, PARTITION_SIZES, SUBSCRIPTIONS,
+    },
+};
+
+/// Delete an empty partition. Contracts can be moved out of a partition
+/// beforehand with MovePartition.
+pub fn on_execute(
+    ctx: Context,
+    selector: PartitionSelector,
+) -> Result<Response, ContractError> {
+    let action = "delete_partition";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/delete-partition")?;
+
+    let partition = resolve_partition_id(deps.storage, selector)?;
+
+    ensure_partition_exists(deps.storage, partition)?;
+
+    let size = PARTITION_SIZES
+        .may_load(deps.storage, partition)?
+        .unwrap_or_default();
+
+    if !size.is_zero() {
+        return Err(ContractError::ValidationError {
+            reason: format!("partition {} still has {} contracts", partition, size),
+        });
+    }
+
+    // Code IDs that create contracts in the partition by default must be given
+    // a new default partition first.
+    for result in CODE_ID_ALLOWLIST.range(deps.storage, None, None, Order::Ascending) {
+        let (code_id, code_id_meta) = result?;
+        if code_id_meta.partition == Some(partition) {
+            return Err(ContractError::ValidationError {
+                reason: format!(
+                    "partition {} is the default partition of code ID {}",
+                    partition, code_id
+                ),
+            });
+        }
+    }
+
+    let meta = PARTITION_METADATA.load(deps.storage, partition)?;
+
+    PARTITION_METADATA.remove(deps.storage, partition);
+    PARTITION_NAME_2_ID.remove(deps.storage, meta.name);
+    PARTITION_SIZES.remove(deps.storage, partition);
+
+    aggregates::clear_partition(deps.storage, partition)?;
+    facets::clear_partition(deps.storage, partition)?;
+
+    // Drop the partition from subscription filters.
+    for (subscriber, mut subscription) in SUBSCRIPTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        if let Some(partitions) = subscription.partitions.as_mut() {
+            if partitions.contains(&partition) {
+                partitions.retain(|p| *p != partition);
+                SUBSCRIPTIONS.save(deps.storage, &subscriber, &subscription)?;
+            }
+        }
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("partition", partition.to_string()),
+    ]))
+}
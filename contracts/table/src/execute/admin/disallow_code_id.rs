@@ -0,0 +1,26 @@
+use cosmwasm_std::{attr, Response, Uint64};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    state::{ensure_allowed_by_acl, CODE_ID_ALLOWLIST},
+};
+
+/// Remove a code ID from the allowlist. Existing contracts created from it
+/// are unaffected.
+pub fn on_execute(
+    ctx: Context,
+    code_id: Uint64,
+) -> Result<Response, ContractError> {
+    let action = "disallow_code_id";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/disallow-code-id")?;
+
+    CODE_ID_ALLOWLIST.remove(deps.storage, code_id.into());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("code_id", code_id.to_string()),
+    ]))
+}
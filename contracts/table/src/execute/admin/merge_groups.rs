@@ -0,0 +1,73 @@
+use cosmwasm_std::{attr, Response};
+
+use crate::{
+    changes::record_change,
+    context::Context,
+    error::ContractError,
+    models::ChangeKind,
+    msg::GroupMergeParams,
+    state::{append_group, ensure_allowed_by_acl, remove_from_group, GROUP_METADATA},
+};
+
+use super::delete_group::{load_group_batch, remove_group, DEFAULT_BATCH_SIZE, MAX_BATCH_SIZE};
+
+/// Move a batch of contracts from the src group into the dst group, removing
+/// the src group once it's empty. Contracts already in both groups are counted
+/// once in the dst group's size.
+pub fn on_execute(
+    ctx: Context,
+    params: GroupMergeParams,
+) -> Result<Response, ContractError> {
+    let action = "merge_groups";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/merge-groups")?;
+
+    let GroupMergeParams { src, dst, .. } = params;
+
+    if src == dst {
+        return Err(ContractError::ValidationError {
+            reason: "cannot merge a group into itself".to_owned(),
+        });
+    }
+
+    for group_id in [src, dst] {
+        if !GROUP_METADATA.has(deps.storage, group_id) {
+            return Err(ContractError::GroupNotFound {
+                reason: format!("group {} does not exist", group_id),
+            });
+        }
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE) as usize;
+
+    let contract_ids = load_group_batch(deps.storage, src, limit)?;
+
+    for contract_id in contract_ids.iter() {
+        remove_from_group(deps.storage, src, *contract_id)?;
+        append_group(deps.storage, dst, *contract_id)?;
+        record_change(
+            deps.storage,
+            &env,
+            *contract_id,
+            &info.sender,
+            ChangeKind::Groups,
+        )?;
+    }
+
+    let is_done = contract_ids.len() < limit;
+    if is_done {
+        remove_group(deps.storage, src, Some(dst))?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("src", src.to_string()),
+        attr("dst", dst.to_string()),
+        attr("moved", contract_ids.len().to_string()),
+        attr("status", if is_done { "merged" } else { "merging" }),
+    ]))
+}
@@ -0,0 +1,194 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{
+    attr, DepsMut, Env, Event, Order, Reply, Response, StdResult, Storage, SubMsg, SubMsgResult,
+    Uint64, WasmMsg,
+};
+use cw_storage_plus::Bound;
+
+use crate::{
+    context::Context,
+    ensure::ensure_authorized_code_id,
+    error::ContractError,
+    models::{MigrationFailure, ReplyJob},
+    msg::{MigrationParams, MigrationSelector},
+    state::{
+        create_reply_job, decrement_code_id_size, ensure_allowed_by_acl, increment_code_id_size,
+        load_contract_addr, ContractID, PartitionID, CONTRACT_ADDR_2_ID, CONTRACT_METADATA,
+        IX_CODE_ID, IX_GROUP, MIGRATION_FAILURES, PARTITION_METADATA, X,
+    },
+};
+
+pub const DEFAULT_BATCH_SIZE: u32 = 25;
+pub const MAX_BATCH_SIZE: u32 = 100;
+
+/// Migrate a page of the selected contracts to the given code ID. Contracts
+/// not managed by the table or already using the code ID are skipped.
+pub fn on_execute(
+    ctx: Context,
+    params: MigrationParams,
+) -> Result<Response, ContractError> {
+    let action = "migrate_contracts";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/migrate-contracts")?;
+    ensure_authorized_code_id(deps.storage, params.code_id.into())?;
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE) as usize;
+
+    let contract_ids = load_contract_ids(
+        deps.storage,
+        &params.contracts,
+        params.cursor.map(|id| id.u64()),
+        limit,
+    )?;
+
+    let mut resp = Response::new();
+    let mut n_migrating: u32 = 0;
+
+    for contract_id in contract_ids.iter().copied() {
+        let meta = CONTRACT_METADATA.load(deps.storage, contract_id)?;
+        if !meta.is_managed || meta.code_id == params.code_id {
+            continue;
+        }
+        let job_id = create_reply_job(
+            deps.storage,
+            ReplyJob::Migrate {
+                contract_id: contract_id.into(),
+                code_id: params.code_id,
+            },
+        )?;
+        resp = resp.add_submessage(SubMsg::reply_always(
+            WasmMsg::Migrate {
+                contract_addr: load_contract_addr(deps.storage, contract_id)?.into(),
+                new_code_id: params.code_id.into(),
+                msg: params.msg.clone(),
+            },
+            job_id,
+        ));
+        n_migrating += 1;
+    }
+
+    let cursor = if contract_ids.len() == limit {
+        contract_ids.last().map(|id| id.to_string())
+    } else {
+        None
+    };
+
+    Ok(resp.add_attributes(vec![
+        attr("action", action),
+        attr("code_id", params.code_id.to_string()),
+        attr("n_migrating", n_migrating.to_string()),
+        attr("cursor", cursor.unwrap_or_default()),
+    ]))
+}
+
+/// Update a migrated contract's code ID or, if its migration failed, record
+/// the failure without reverting other migrations.
+pub fn on_reply(
+    deps: DepsMut,
+    env: Env,
+    reply: Reply,
+    contract_id: ContractID,
+    code_id: Uint64,
+) -> Result<Response, ContractError> {
+    let mut resp = Response::new();
+    let event = Event::new("post_migrate")
+        .add_attribute("contract_id", contract_id.to_string())
+        .add_attribute("code_id", code_id.to_string());
+
+    match reply.result {
+        SubMsgResult::Ok(_) => {
+            let mut meta = CONTRACT_METADATA.load(deps.storage, contract_id)?;
+            let p = meta.partition;
+
+            IX_CODE_ID.remove(deps.storage, (p, meta.code_id.into(), contract_id));
+            IX_CODE_ID.save(deps.storage, (p, code_id.into(), contract_id), &X)?;
+            decrement_code_id_size(deps.storage, meta.code_id.into())?;
+            increment_code_id_size(deps.storage, code_id.into())?;
+
+            meta.code_id = code_id;
+            CONTRACT_METADATA.save(deps.storage, contract_id, &meta)?;
+            MIGRATION_FAILURES.remove(deps.storage, contract_id);
+
+            resp = resp.add_event(event.add_attribute("status", "ok"));
+        },
+        SubMsgResult::Err(error) => {
+            MIGRATION_FAILURES.save(
+                deps.storage,
+                contract_id,
+                &MigrationFailure {
+                    code_id,
+                    error: error.clone(),
+                    height: env.block.height.into(),
+                    time: env.block.time,
+                },
+            )?;
+
+            resp = resp.add_event(
+                event
+                    .add_attribute("status", "error")
+                    .add_attribute("error", error),
+            );
+        },
+    }
+
+    Ok(resp)
+}
+
+/// Load the IDs of the selected contracts, in ascending order, after the
+/// given contract ID cursor.
+fn load_contract_ids(
+    storage: &dyn Storage,
+    selector: &MigrationSelector,
+    cursor: Option<ContractID>,
+    limit: usize,
+) -> Result<Vec<ContractID>, ContractError> {
+    let min = || cursor.map(|id| Bound::Exclusive((id, PhantomData)));
+
+    Ok(match selector {
+        MigrationSelector::CodeId(code_id) => {
+            // Contracts with the code ID may be in any partition, so take the
+            // first page from each and keep the lowest IDs.
+            let partitions = PARTITION_METADATA
+                .keys(storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<PartitionID>>>()?;
+            let mut ids: Vec<ContractID> = Vec::with_capacity(limit);
+            for partition in partitions.iter() {
+                for result in IX_CODE_ID
+                    .prefix((*partition, code_id.u64()))
+                    .keys(storage, min(), None, Order::Ascending)
+                    .take(limit)
+                {
+                    ids.push(result?);
+                }
+            }
+            ids.sort_unstable();
+            ids.truncate(limit);
+            ids
+        },
+        MigrationSelector::Group(group_id) => IX_GROUP
+            .prefix(*group_id)
+            .keys(storage, min(), None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<ContractID>>>()?,
+        MigrationSelector::Addresses(addrs) => {
+            let mut ids: Vec<ContractID> = Vec::with_capacity(addrs.len());
+            for addr in addrs.iter() {
+                if let Some(id) = CONTRACT_ADDR_2_ID.may_load(storage, addr)? {
+                    let id: ContractID = id.into();
+                    if cursor.map(|c| id > c).unwrap_or(true) {
+                        ids.push(id);
+                    }
+                }
+            }
+            ids.sort_unstable();
+            ids.dedup();
+            ids.truncate(limit);
+            ids
+        },
+    })
+}
@@ -1,11 +1,31 @@
+pub mod adopt;
+pub mod allow_code_id;
 pub mod assign_groups;
+pub mod bulk_update;
+pub mod cancel_bulk_update;
+pub mod create_composite_index;
 pub mod create_group;
 pub mod create_index;
 pub mod create_partition;
+pub mod delete_group;
 pub mod delete_index;
+pub mod delete_partition;
+pub mod disallow_code_id;
+pub mod merge_groups;
+pub mod migrate_contracts;
+pub mod move_partition;
+pub mod rebuild_index;
+pub mod release_admin;
+pub mod resolve_flags;
+pub mod resume_bulk_update;
+pub mod restore;
 pub mod revert_config;
 pub mod set_owner;
 pub mod set_partition;
+pub mod subscribe;
+pub mod unsubscribe;
 pub mod unsuspend;
 pub mod update_config;
+pub mod update_group;
 pub mod update_info;
+pub mod update_partition;
@@ -0,0 +1,90 @@
+use cosmwasm_std::{attr, Order, Response, StdResult};
+
+use crate::{
+    changes::record_change,
+    context::Context,
+    error::ContractError,
+    events::{TableEvent, TableMoveEventArgs},
+    models::ChangeKind,
+    msg::PartitionMoveParams,
+    state::{
+        ensure_allowed_by_acl, ensure_partition_exists, load_contract_addr, resolve_partition_id,
+        ContractID, IX_CONTRACT_ID,
+    },
+    subscriptions::build_event_submsgs,
+};
+
+use super::set_partition::update_contract_partition;
+
+pub const DEFAULT_BATCH_SIZE: u32 = 50;
+pub const MAX_BATCH_SIZE: u32 = 200;
+
+/// Move a batch of contracts from the src partition to the dst partition,
+/// including suspended contracts, so that the src partition can be emptied.
+pub fn on_execute(
+    ctx: Context,
+    params: PartitionMoveParams,
+) -> Result<Response, ContractError> {
+    let action = "move_partition";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/move-partition")?;
+
+    let src = resolve_partition_id(deps.storage, params.src)?;
+    let dst = resolve_partition_id(deps.storage, params.dst)?;
+
+    ensure_partition_exists(deps.storage, src)?;
+    ensure_partition_exists(deps.storage, dst)?;
+
+    if src == dst {
+        return Err(ContractError::ValidationError {
+            reason: "cannot move a partition into itself".to_owned(),
+        });
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE) as usize;
+
+    let contract_ids: Vec<ContractID> = IX_CONTRACT_ID
+        .sub_prefix(src)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(id, _)| id))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut resp = Response::new();
+
+    for contract_id in contract_ids.iter().copied() {
+        update_contract_partition(deps.storage, contract_id, src, dst)?;
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &info.sender,
+            ChangeKind::Move { src, dst },
+        )?;
+        resp = resp.add_submessages(build_event_submsgs(
+            deps.storage,
+            TableEvent::Move(TableMoveEventArgs {
+                table: env.contract.address.clone(),
+                contract: load_contract_addr(deps.storage, contract_id)?,
+                id: contract_id.into(),
+                initiator: info.sender.clone(),
+                src,
+                dst,
+            }),
+        )?);
+    }
+
+    let is_done = contract_ids.len() < limit;
+
+    Ok(resp.add_attributes(vec![
+        attr("action", action),
+        attr("src", src.to_string()),
+        attr("dst", dst.to_string()),
+        attr("moved", contract_ids.len().to_string()),
+        attr("status", if is_done { "moved" } else { "moving" }),
+    ]))
+}
@@ -0,0 +1,311 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{attr, Order, Response, StdResult, Storage, Uint64};
+use cw_storage_plus::{Bound, KeyDeserialize, Map, Prefixer, PrimaryKey};
+
+use crate::{
+    aggregates::{load_indexed_value, update_aggregate},
+    composite,
+    context::Context,
+    error::ContractError,
+    facets::{load_facet_value, update_facet},
+    msg::{CompositeIndexMetadata, IndexMetadata, IndexRebuildParams, IndexStatus, IndexType},
+    state::{
+        ensure_allowed_by_acl, ContractID, CustomIndexMap, PartitionID, COMPOSITE_INDEX_METADATA,
+        CONFIG_STR_MAX_LEN, CONTRACT_ARCHIVES, CONTRACT_INDEX_TYPES, CONTRACT_METADATA,
+        INDEX_METADATA, VALUES_BINARY, VALUES_BOOL, VALUES_I32, VALUES_STRING, VALUES_TIME,
+        VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8, X,
+    },
+    util::{build_index_storage_key, build_string_index_key},
+};
+
+pub const DEFAULT_BATCH_SIZE: u32 = 100;
+pub const MAX_BATCH_SIZE: u32 = 500;
+
+/// Scan a batch of contracts, adding any missing entries to the given custom
+/// or composite index from each contract's stored values. Each call resumes
+/// from where the previous one left off until all contracts have been scanned.
+pub fn on_execute(
+    ctx: Context,
+    params: IndexRebuildParams,
+) -> Result<Response, ContractError> {
+    let action = "rebuild_index";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/rebuild-index")?;
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE) as usize;
+
+    if let Some(meta) = COMPOSITE_INDEX_METADATA.may_load(deps.storage, params.name.clone())? {
+        return rebuild_composite_index(deps.storage, meta, limit);
+    }
+
+    let mut meta = INDEX_METADATA
+        .may_load(deps.storage, params.name.clone())?
+        .ok_or_else(|| ContractError::ValidationError {
+            reason: format!("index {} does not exist", params.name),
+        })?;
+
+    // Resume the build in progress, if any, or else start a new one.
+    let (cursor, mut scanned) = match &meta.status {
+        Some(IndexStatus::Building { cursor, scanned }) => (*cursor, *scanned),
+        _ => (None, Uint64::zero()),
+    };
+
+    let contracts = load_next_batch(deps.storage, cursor, limit)?;
+
+    let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
+    let mut n_added: u64 = 0;
+
+    // Composite indices with the rebuilt index as a column are rebuilt too.
+    let composite_indices = composite::load_affected_indices(deps.storage, &[&meta.name])?;
+
+    for (contract_id, partition) in contracts.iter() {
+        // Archived contracts are indexed when restored.
+        if CONTRACT_ARCHIVES.has(deps.storage, *contract_id) {
+            continue;
+        }
+        if backfill(deps.storage, &meta, *partition, *contract_id, max_str_len)? {
+            n_added += 1;
+        }
+        for composite_meta in composite_indices.iter() {
+            backfill_composite(
+                deps.storage,
+                composite_meta,
+                *partition,
+                *contract_id,
+                max_str_len,
+            )?;
+        }
+    }
+
+    scanned += Uint64::from(contracts.len() as u64);
+    meta.size += Uint64::from(n_added);
+    meta.status = Some(build_status(&contracts, limit, scanned));
+
+    INDEX_METADATA.save(deps.storage, meta.name.clone(), &meta)?;
+
+    Ok(build_response(
+        action,
+        &meta.name,
+        scanned,
+        meta.size,
+        &meta.status,
+    ))
+}
+
+/// Scan a batch of contracts, adding any missing entries to the given
+/// composite index from each contract's stored column values.
+fn rebuild_composite_index(
+    storage: &mut dyn Storage,
+    meta: CompositeIndexMetadata,
+    limit: usize,
+) -> Result<Response, ContractError> {
+    let action = "rebuild_index";
+
+    let (cursor, mut scanned) = match &meta.status {
+        Some(IndexStatus::Building { cursor, scanned }) => (*cursor, *scanned),
+        _ => (None, Uint64::zero()),
+    };
+
+    let contracts = load_next_batch(storage, cursor, limit)?;
+    let max_str_len = CONFIG_STR_MAX_LEN.load(storage)? as usize;
+
+    for (contract_id, partition) in contracts.iter() {
+        if CONTRACT_ARCHIVES.has(storage, *contract_id) {
+            continue;
+        }
+        backfill_composite(storage, &meta, *partition, *contract_id, max_str_len)?;
+    }
+
+    scanned += Uint64::from(contracts.len() as u64);
+
+    // Reload the metadata, since saving entries updates the index size.
+    let mut meta = COMPOSITE_INDEX_METADATA.load(storage, meta.name)?;
+    meta.status = Some(build_status(&contracts, limit, scanned));
+
+    COMPOSITE_INDEX_METADATA.save(storage, meta.name.clone(), &meta)?;
+
+    Ok(build_response(
+        action,
+        &meta.name,
+        scanned,
+        meta.size,
+        &meta.status,
+    ))
+}
+
+/// Add a contract to the given composite index if it has a value for every
+/// column and isn't already in it.
+fn backfill_composite(
+    storage: &mut dyn Storage,
+    meta: &CompositeIndexMetadata,
+    partition: PartitionID,
+    contract_id: ContractID,
+    max_str_len: usize,
+) -> Result<(), ContractError> {
+    if let Some(key) = composite::load_key(storage, contract_id, meta, max_str_len)? {
+        composite::save_entry(storage, meta, partition, &key, contract_id)?;
+    }
+    Ok(())
+}
+
+fn load_next_batch(
+    storage: &dyn Storage,
+    cursor: Option<Uint64>,
+    limit: usize,
+) -> Result<Vec<(ContractID, PartitionID)>, ContractError> {
+    Ok(CONTRACT_METADATA
+        .range(
+            storage,
+            cursor.map(|id| Bound::Exclusive((id.u64(), PhantomData))),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|r| r.map(|(id, contract_meta)| (id, contract_meta.partition)))
+        .collect::<StdResult<Vec<_>>>()?)
+}
+
+fn build_status(
+    contracts: &[(ContractID, PartitionID)],
+    limit: usize,
+    scanned: Uint64,
+) -> IndexStatus {
+    if contracts.len() < limit {
+        IndexStatus::Ready
+    } else {
+        IndexStatus::Building {
+            cursor: contracts.last().map(|(id, _)| Uint64::from(*id)),
+            scanned,
+        }
+    }
+}
+
+fn build_response(
+    action: &str,
+    index_name: &str,
+    scanned: Uint64,
+    size: Uint64,
+    status: &Option<IndexStatus>,
+) -> Response {
+    Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("index", index_name),
+        attr("scanned", scanned.to_string()),
+        attr("size", size.to_string()),
+        attr(
+            "status",
+            if matches!(status, Some(IndexStatus::Ready)) {
+                "ready"
+            } else {
+                "building"
+            },
+        ),
+    ])
+}
+
+/// Add a contract's stored value for the given index to the index, returning
+/// true if a new entry was added.
+pub fn backfill(
+    storage: &mut dyn Storage,
+    meta: &IndexMetadata,
+    partition: PartitionID,
+    contract_id: ContractID,
+    max_str_len: usize,
+) -> Result<bool, ContractError> {
+    let index_name = &meta.name;
+
+    // Values left behind by a deleted index of the same name but a different
+    // type don't belong in this index.
+    if let Some(index_type) = CONTRACT_INDEX_TYPES.may_load(storage, (contract_id, index_name))? {
+        if index_type != meta.index_type {
+            return Ok(false);
+        }
+    }
+
+    let storage_key = &build_index_storage_key(index_name);
+    let value_key = (contract_id, index_name);
+
+    let is_added = match meta.index_type {
+        IndexType::String => match VALUES_STRING.may_load(storage, value_key)? {
+            Some(v) => {
+                let case_insensitive = meta.case_insensitive.unwrap_or(false);
+                let key = build_string_index_key(&v, case_insensitive, max_str_len);
+                save_entry(storage, storage_key, (partition, &key, contract_id))?
+            },
+            None => false,
+        },
+        IndexType::Bool => match VALUES_BOOL.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v as u8, contract_id))?,
+            None => false,
+        },
+        IndexType::Timestamp => match VALUES_TIME.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v.nanos(), contract_id))?,
+            None => false,
+        },
+        IndexType::Int32 => match VALUES_I32.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v, contract_id))?,
+            None => false,
+        },
+        IndexType::Uint8 => match VALUES_U8.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v, contract_id))?,
+            None => false,
+        },
+        IndexType::Uint16 => match VALUES_U16.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v, contract_id))?,
+            None => false,
+        },
+        IndexType::Uint32 => match VALUES_U32.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v, contract_id))?,
+            None => false,
+        },
+        IndexType::Uint64 => match VALUES_U64.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v.u64(), contract_id))?,
+            None => false,
+        },
+        IndexType::Uint128 => match VALUES_U128.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v.u128(), contract_id))?,
+            None => false,
+        },
+        IndexType::Binary => match VALUES_BINARY.may_load(storage, value_key)? {
+            Some(v) => save_entry(storage, storage_key, (partition, v.as_slice(), contract_id))?,
+            None => false,
+        },
+    };
+
+    if is_added && !CONTRACT_INDEX_TYPES.has(storage, value_key) {
+        CONTRACT_INDEX_TYPES.save(storage, value_key, &meta.index_type)?;
+    }
+
+    if is_added && meta.aggregate.unwrap_or(false) {
+        let value = load_indexed_value(storage, meta, partition, contract_id)?;
+        update_aggregate(storage, index_name, partition, None, value)?;
+    }
+
+    if is_added && meta.facet.unwrap_or(false) {
+        let value = load_facet_value(storage, meta, partition, contract_id, max_str_len)?;
+        update_facet(storage, index_name, partition, None, value)?;
+    }
+
+    Ok(is_added)
+}
+
+fn save_entry<'a, T>(
+    storage: &mut dyn Storage,
+    storage_key: &'a str,
+    key: (PartitionID, T, ContractID),
+) -> Result<bool, ContractError>
+where
+    T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize + Clone,
+{
+    let index: CustomIndexMap<T> = Map::new(storage_key);
+    if index.has(storage, key.clone()) {
+        return Ok(false);
+    }
+    index.save(storage, key, &X)?;
+    Ok(true)
+}
@@ -0,0 +1,60 @@
+use cosmwasm_std::{attr, Response, WasmMsg};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    msg::AdminReleaseParams,
+    state::{ensure_allowed_by_acl, load_contract_id, CONTRACT_METADATA},
+};
+
+/// Transfer or clear the wasm admin of a contract managed by the table, after
+/// which the contract is no longer managed.
+pub fn on_execute(
+    ctx: Context,
+    params: AdminReleaseParams,
+) -> Result<Response, ContractError> {
+    let action = "release_admin";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/release-admin")?;
+
+    let contract_addr = deps.api.addr_validate(params.contract.as_str())?;
+    let contract_id = load_contract_id(deps.storage, &contract_addr)?;
+    let mut meta = CONTRACT_METADATA.load(deps.storage, contract_id)?;
+
+    if !meta.is_managed {
+        return Err(ContractError::ValidationError {
+            reason: format!("contract {} is not managed by the table", contract_addr),
+        });
+    }
+
+    meta.is_managed = false;
+    CONTRACT_METADATA.save(deps.storage, contract_id, &meta)?;
+
+    let (msg, new_admin) = match params.new_admin {
+        Some(admin) => {
+            let admin = deps.api.addr_validate(admin.as_str())?;
+            (
+                WasmMsg::UpdateAdmin {
+                    contract_addr: contract_addr.to_string(),
+                    admin: admin.to_string(),
+                },
+                admin.to_string(),
+            )
+        },
+        None => (
+            WasmMsg::ClearAdmin {
+                contract_addr: contract_addr.to_string(),
+            },
+            String::new(),
+        ),
+    };
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", action),
+            attr("contract", contract_addr),
+            attr("new_admin", new_admin),
+        ])
+        .add_message(msg))
+}
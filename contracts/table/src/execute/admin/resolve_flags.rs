@@ -0,0 +1,125 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{attr, Order, Response, StdResult};
+use cw_storage_plus::{Bound, Deque};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    models::{ContractFlag, FlagResolution},
+    msg::FlagResolutionParams,
+    state::{
+        ensure_allowed_by_acl, load_contract_id, uncount_flag, CONTRACT_FLAG_RESOLUTIONS,
+        CONTRACT_FLAG_WINDOW_STARTS, CONTRACT_OPEN_FLAG_COUNTS, CONTRACT_OPEN_FLAG_WEIGHTS,
+    },
+    util::build_flags_storage_key,
+};
+
+pub const DEFAULT_BATCH_SIZE: u32 = 100;
+pub const MAX_BATCH_SIZE: u32 = 500;
+
+/// Mark a contract's open flags as resolved or, if clearing, delete a batch of
+/// its flag history, newest first.
+pub fn on_execute(
+    ctx: Context,
+    params: FlagResolutionParams,
+) -> Result<Response, ContractError> {
+    let action = "resolve_flags";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/resolve-flags")?;
+
+    let contract_id = load_contract_id(deps.storage, &params.contract)?;
+    let flags_deque_key = build_flags_storage_key(contract_id);
+    let flags: Deque<ContractFlag> = Deque::new(flags_deque_key.as_str());
+    let n_open = CONTRACT_OPEN_FLAG_COUNTS
+        .may_load(deps.storage, contract_id)?
+        .unwrap_or_default();
+
+    if params.clear.unwrap_or(false) {
+        let limit = params
+            .limit
+            .unwrap_or(DEFAULT_BATCH_SIZE)
+            .clamp(1, MAX_BATCH_SIZE);
+
+        // Flags are popped from the back so that the positions of remaining
+        // flags, which resolutions refer to, don't change. Open flags are the
+        // newest, so they're cleared first.
+        let n_total = flags.len(deps.storage)?;
+        let n_cleared = limit.min(n_total);
+        let n_remaining = n_total - n_cleared;
+
+        for position in (n_remaining..n_total).rev() {
+            if let Some(flag) = flags.pop_back(deps.storage)? {
+                uncount_flag(deps.storage, contract_id, position, &flag.sender)?;
+            }
+        }
+
+        if let Some(window_start) =
+            CONTRACT_FLAG_WINDOW_STARTS.may_load(deps.storage, contract_id)?
+        {
+            CONTRACT_FLAG_WINDOW_STARTS.save(
+                deps.storage,
+                contract_id,
+                &window_start.min(n_remaining),
+            )?;
+        }
+
+        for end in CONTRACT_FLAG_RESOLUTIONS
+            .prefix(contract_id)
+            .keys(
+                deps.storage,
+                Some(Bound::Exclusive((n_remaining, PhantomData))),
+                None,
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<u32>>>()?
+        {
+            CONTRACT_FLAG_RESOLUTIONS.remove(deps.storage, (contract_id, end));
+        }
+
+        let n_open_remaining = n_open.saturating_sub(n_cleared);
+        if n_open_remaining > 0 {
+            CONTRACT_OPEN_FLAG_COUNTS.save(deps.storage, contract_id, &n_open_remaining)?;
+        } else {
+            CONTRACT_OPEN_FLAG_COUNTS.remove(deps.storage, contract_id);
+        }
+
+        return Ok(Response::new().add_attributes(vec![
+            attr("action", action),
+            attr("contract", params.contract.to_string()),
+            attr("n_cleared", n_cleared.to_string()),
+            attr("status", if n_remaining == 0 { "done" } else { "clearing" }),
+        ]));
+    }
+
+    if n_open == 0 {
+        return Err(ContractError::ValidationError {
+            reason: format!("contract {} has no open flags", params.contract),
+        });
+    }
+
+    let n_total = flags.len(deps.storage)?;
+
+    // Resolved flags no longer count toward auto-suspension.
+    CONTRACT_OPEN_FLAG_COUNTS.remove(deps.storage, contract_id);
+    CONTRACT_OPEN_FLAG_WEIGHTS.remove(deps.storage, contract_id);
+    CONTRACT_FLAG_WINDOW_STARTS.save(deps.storage, contract_id, &n_total)?;
+    CONTRACT_FLAG_RESOLUTIONS.save(
+        deps.storage,
+        (contract_id, n_total),
+        &FlagResolution {
+            resolved_by: info.sender,
+            note: params.note.clone(),
+            height: env.block.height.into(),
+            time: env.block.time,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("contract", params.contract.to_string()),
+        attr("n_resolved", n_open.to_string()),
+        attr("note", params.note.unwrap_or_default()),
+    ]))
+}
@@ -0,0 +1,274 @@
+use cosmwasm_std::{attr, Order, Response, StdResult, Storage, Uint64};
+
+use crate::{
+    changes::record_change,
+    composite::{load_affected_indices, load_key, save_entry},
+    context::Context,
+    ensure::{ensure_partition_policy, ensure_valid_expiration},
+    error::ContractError,
+    events::TableEvent,
+    models::ChangeKind,
+    msg::{IndexType, RestoreParams},
+    state::{
+        append_group, ensure_allowed_by_acl, ensure_partition_exists, incr_decr_index_size,
+        increment_code_id_size, increment_tag_count, resolve_partition_id, set_expiration,
+        ContractID, PartitionID, ARCHIVED_ADDR_2_ID, CONFIG_STR_MAX_LEN, CONTRACT_ADDR_2_ID,
+        CONTRACT_ARCHIVES, CONTRACT_DYN_METADATA, CONTRACT_EXPIRATIONS, CONTRACT_GROUP_IDS,
+        CONTRACT_INDEX_TYPES, CONTRACT_METADATA, CONTRACT_TAGS, GROUP_METADATA, GROUP_REPLACEMENTS,
+        INDEX_METADATA, IX_CODE_ID, IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY, IX_REV, IX_TAG,
+        IX_UPDATED_AT, IX_UPDATED_BY, PARTITION_SIZES, REL_ADDR_2_ID, REL_ID_2_ADDR, UNIQUE, X,
+    },
+    subscriptions::{build_event_args, build_event_submsgs},
+    util::trim_padding,
+};
+
+use super::rebuild_index::backfill;
+
+/// Restore an archived contract, adding it back to all indices from its
+/// archived metadata, values, tags, relationships and group memberships.
+pub fn on_execute(
+    ctx: Context,
+    params: RestoreParams,
+) -> Result<Response, ContractError> {
+    let action = "restore";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/restore")?;
+
+    let contract_addr = deps.api.addr_validate(params.contract.as_str())?;
+    let contract_id = ARCHIVED_ADDR_2_ID
+        .may_load(deps.storage, &contract_addr)?
+        .ok_or_else(|| ContractError::ContractNotFound {
+            reason: format!("contract {} is not archived", contract_addr),
+        })?;
+
+    let mut meta = CONTRACT_METADATA.load(deps.storage, contract_id)?;
+
+    // Restore to the contract's original partition unless told otherwise.
+    if let Some(selector) = params.partition {
+        meta.partition = resolve_partition_id(deps.storage, selector)?;
+        CONTRACT_METADATA.save(deps.storage, contract_id, &meta)?;
+    }
+
+    let p = meta.partition;
+
+    ensure_partition_exists(deps.storage, p)?;
+    ensure_partition_policy(deps.storage, p, meta.code_id.u64(), &meta.created_by, false)?;
+
+    restore_relationships(deps.storage, contract_id)?;
+    restore_standard_indices(deps.storage, contract_id, p)?;
+    restore_custom_indices(deps.storage, contract_id, p)?;
+    restore_tags(deps.storage, contract_id, p)?;
+    restore_groups(deps.storage, contract_id)?;
+
+    // A contract restored with a past expiration would only be swept again.
+    let prev_expires_at = CONTRACT_EXPIRATIONS.may_load(deps.storage, contract_id)?;
+    let expires_at = match params.expires_at {
+        Some(expires_at) => {
+            ensure_valid_expiration(&env, Some(expires_at))?;
+            Some(expires_at)
+        },
+        None => prev_expires_at.filter(|expires_at| *expires_at > env.block.time),
+    };
+
+    set_expiration(deps.storage, contract_id, expires_at)?;
+
+    PARTITION_SIZES.update(deps.storage, p, |maybe_n| -> Result<_, ContractError> {
+        maybe_n
+            .unwrap_or_default()
+            .checked_add(Uint64::one())
+            .map_err(|e| ContractError::UnexpectedError {
+                reason: e.to_string(),
+            })
+    })?;
+
+    ARCHIVED_ADDR_2_ID.remove(deps.storage, &contract_addr);
+    CONTRACT_ARCHIVES.remove(deps.storage, contract_id);
+    CONTRACT_ADDR_2_ID.save(deps.storage, &contract_addr, &contract_id.into())?;
+
+    record_change(
+        deps.storage,
+        &env,
+        contract_id,
+        &info.sender,
+        ChangeKind::Restore,
+    )?;
+
+    if expires_at != prev_expires_at {
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &info.sender,
+            ChangeKind::Update,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", action),
+            attr("contract_id", contract_id.to_string()),
+            attr("partition", p.to_string()),
+        ])
+        .add_submessages(build_event_submsgs(
+            deps.storage,
+            TableEvent::Restore(build_event_args(
+                &env,
+                &contract_addr,
+                contract_id,
+                &info.sender,
+            )),
+        )?))
+}
+
+/// Rebuild the account side of each of the contract's relationships, failing
+/// if another contract has since taken a unique relationship.
+fn restore_relationships(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+) -> Result<(), ContractError> {
+    for result in REL_ID_2_ADDR
+        .sub_prefix(contract_id)
+        .range(storage, None, None, Order::Ascending)
+        .collect::<Vec<StdResult<_>>>()
+    {
+        let ((rel_name, addr_str), uniqueness) = result?;
+
+        for result in REL_ADDR_2_ID
+            .prefix((addr_str.clone(), rel_name.clone()))
+            .range(storage, None, None, Order::Ascending)
+            .take(1)
+        {
+            let (_, other_uniqueness) = result?;
+            if other_uniqueness == UNIQUE {
+                return Err(ContractError::ValidationError {
+                    reason: format!(
+                        "Relationship {} with {} is unique",
+                        trim_padding(&rel_name),
+                        addr_str
+                    ),
+                });
+            }
+        }
+
+        REL_ADDR_2_ID.save(
+            storage,
+            (addr_str, rel_name, contract_id.to_string()),
+            &uniqueness,
+        )?;
+    }
+    Ok(())
+}
+
+fn restore_standard_indices(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    p: PartitionID,
+) -> Result<(), ContractError> {
+    let meta = CONTRACT_METADATA.load(storage, contract_id)?;
+
+    IX_CONTRACT_ID.save(storage, (p, contract_id, contract_id), &X)?;
+    IX_CODE_ID.save(storage, (p, meta.code_id.into(), contract_id), &X)?;
+    increment_code_id_size(storage, meta.code_id.into())?;
+    IX_CREATED_BY.save(storage, (p, meta.created_by.to_string(), contract_id), &X)?;
+    IX_CREATED_AT.save(storage, (p, meta.created_at.nanos(), contract_id), &X)?;
+
+    if let Some(up_meta) = CONTRACT_DYN_METADATA.may_load(storage, contract_id)? {
+        IX_UPDATED_BY.save(
+            storage,
+            (p, up_meta.updated_by.to_string(), contract_id),
+            &X,
+        )?;
+        IX_UPDATED_AT.save(storage, (p, up_meta.updated_at.nanos(), contract_id), &X)?;
+        IX_REV.save(storage, (p, up_meta.rev.into(), contract_id), &X)?;
+    } else {
+        // Dynamic metadata doesn't exist until first update. in this case,
+        // the initial values are used.
+        IX_UPDATED_BY.save(storage, (p, meta.created_by.to_string(), contract_id), &X)?;
+        IX_UPDATED_AT.save(storage, (p, meta.created_at.nanos(), contract_id), &X)?;
+        IX_REV.save(storage, (p, 1, contract_id), &X)?;
+    }
+
+    Ok(())
+}
+
+/// Re-index the contract's archived values in each custom index that still
+/// exists with the same type, along with any composite indices built on them.
+fn restore_custom_indices(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    p: PartitionID,
+) -> Result<(), ContractError> {
+    let max_str_len = CONFIG_STR_MAX_LEN.load(storage)? as usize;
+    let entries: Vec<(String, IndexType)> = CONTRACT_INDEX_TYPES
+        .prefix(contract_id)
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut index_names: Vec<&String> = Vec::with_capacity(entries.len());
+
+    for (index_name, index_type) in entries.iter() {
+        if let Some(meta) = INDEX_METADATA.may_load(storage, index_name.clone())? {
+            if meta.index_type == *index_type {
+                if backfill(storage, &meta, p, contract_id, max_str_len)? {
+                    incr_decr_index_size(storage, index_name, true)?;
+                }
+                index_names.push(index_name);
+            }
+        }
+    }
+
+    for meta in load_affected_indices(storage, &index_names)?.iter() {
+        if let Some(key) = load_key(storage, contract_id, meta, max_str_len)? {
+            save_entry(storage, meta, p, &key, contract_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn restore_tags(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    p: PartitionID,
+) -> Result<(), ContractError> {
+    for result in CONTRACT_TAGS
+        .prefix(contract_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<Vec<StdResult<String>>>()
+    {
+        let tag = result?;
+        increment_tag_count(storage, p, &tag)?;
+        IX_TAG.save(storage, (p, &tag, contract_id), &X)?;
+    }
+    Ok(())
+}
+
+/// Add the contract back to its groups, dropping memberships in groups that
+/// have since been deleted.
+fn restore_groups(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+) -> Result<(), ContractError> {
+    for group_id in CONTRACT_GROUP_IDS
+        .prefix(contract_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        // Follow groups that were merged away to the group they merged into
+        let mut target = Some(group_id);
+        while let Some(id) = target {
+            if GROUP_METADATA.has(storage, id) {
+                break;
+            }
+            target = GROUP_REPLACEMENTS.may_load(storage, id)?;
+        }
+        if target != Some(group_id) {
+            CONTRACT_GROUP_IDS.remove(storage, (contract_id, group_id));
+        }
+        if let Some(id) = target {
+            append_group(storage, id, contract_id)?;
+        }
+    }
+    Ok(())
+}
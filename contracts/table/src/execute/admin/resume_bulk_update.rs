@@ -0,0 +1,29 @@
+use cosmwasm_std::Response;
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    state::{ensure_allowed_by_acl, BULK_UPDATE_JOB},
+};
+
+use super::bulk_update::process_batch;
+
+/// Apply the bulk update in progress to its next batch of contracts.
+pub fn on_execute(
+    ctx: Context,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let action = "resume_bulk_update";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/bulk-update")?;
+
+    let mut job =
+        BULK_UPDATE_JOB
+            .may_load(deps.storage)?
+            .ok_or_else(|| ContractError::ValidationError {
+                reason: "no bulk update in progress".to_owned(),
+            })?;
+
+    process_batch(deps, &env, &mut job, limit, action)
+}
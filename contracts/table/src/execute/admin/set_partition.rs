@@ -2,19 +2,27 @@ use cosmwasm_std::{Addr, Order, Response, StdResult, Storage, Uint64};
 use cw_storage_plus::Map;
 
 use crate::{
+    aggregates,
+    changes::record_change,
+    composite::move_contract,
     context::Context,
+    ensure::ensure_partition_policy,
     error::ContractError,
+    events::{TableEvent, TableMoveEventArgs},
+    facets,
+    models::ChangeKind,
     msg::{IndexType, PartitionSelector},
     state::{
         decrement_tag_count, ensure_allowed_by_acl, ensure_contract_not_suspended,
-        ensure_partition_exists, increment_tag_count, load_contract_id, resolve_partition_id,
-        ContractID, CustomIndexMap, PartitionID, CONTRACT_DYN_METADATA, CONTRACT_INDEX_TYPES,
-        CONTRACT_METADATA, CONTRACT_TAGS, IX_CODE_ID, IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY,
-        IX_REV, IX_TAG, IX_UPDATED_AT, IX_UPDATED_BY, PARTITION_SIZES, VALUES_BINARY, VALUES_BOOL,
-        VALUES_I32, VALUES_STRING, VALUES_TIME, VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64,
-        VALUES_U8, X,
+        ensure_partition_exists, increment_tag_count, is_case_insensitive, load_contract_id,
+        resolve_partition_id, ContractID, CustomIndexMap, PartitionID, CONFIG_STR_MAX_LEN,
+        CONTRACT_DYN_METADATA, CONTRACT_INDEX_TYPES, CONTRACT_METADATA, CONTRACT_TAGS, IX_CODE_ID,
+        IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY, IX_REV, IX_TAG, IX_UPDATED_AT, IX_UPDATED_BY,
+        PARTITION_SIZES, VALUES_BINARY, VALUES_BOOL, VALUES_I32, VALUES_STRING, VALUES_TIME,
+        VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8, X,
     },
-    util::build_index_storage_key,
+    subscriptions::build_event_submsgs,
+    util::{build_index_storage_key, build_string_index_key},
 };
 
 /// Move the contract to a new partition.
@@ -23,7 +31,7 @@ pub fn on_execute(
     contract_addr: Addr,
     dst_selector: PartitionSelector,
 ) -> Result<Response, ContractError> {
-    let Context { deps, info, .. } = ctx;
+    let Context { deps, info, env } = ctx;
     let action = "partition";
 
     deps.api.addr_validate(contract_addr.as_str())?;
@@ -41,9 +49,20 @@ pub fn on_execute(
 
     let meta = CONTRACT_METADATA.load(deps.storage, contract_id)?;
 
-    if meta.partition != dst_partition {
-        let src_partition = meta.partition;
+    let src_partition = meta.partition;
+
+    if src_partition != dst_partition {
         update_contract_partition(deps.storage, contract_id, src_partition, dst_partition)?;
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &info.sender,
+            ChangeKind::Move {
+                src: src_partition,
+                dst: dst_partition,
+            },
+        )?;
     } else {
         return Err(ContractError::NotAuthorized {
             reason: format!(
@@ -54,7 +73,19 @@ pub fn on_execute(
         });
     }
 
-    Ok(Response::new().add_attribute("action", action))
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_submessages(build_event_submsgs(
+            deps.storage,
+            TableEvent::Move(TableMoveEventArgs {
+                table: env.contract.address,
+                contract: contract_addr,
+                id: contract_id.into(),
+                initiator: info.sender,
+                src: src_partition,
+                dst: dst_partition,
+            }),
+        )?))
 }
 
 pub fn move_tags(
@@ -89,10 +120,26 @@ pub fn update_contract_partition(
     src: PartitionID,
     dst: PartitionID,
 ) -> Result<(), ContractError> {
+    // The dst partition must admit the contract as if it were created there.
+    let meta = CONTRACT_METADATA.load(storage, contract_id)?;
+    ensure_partition_policy(storage, dst, meta.code_id.u64(), &meta.created_by, false)?;
+
     move_standard_indices(storage, contract_id, src, dst)?;
     move_custom_indices(storage, contract_id, src, dst)?;
     move_tags(storage, contract_id, src, dst)?;
 
+    CONTRACT_METADATA.update(
+        storage,
+        contract_id,
+        |maybe_meta| -> Result<_, ContractError> {
+            let mut meta = maybe_meta.ok_or_else(|| ContractError::ContractNotFound {
+                reason: format!("contract {} not found", contract_id),
+            })?;
+            meta.partition = dst;
+            Ok(meta)
+        },
+    )?;
+
     PARTITION_SIZES.update(storage, src, |maybe_n| -> Result<_, ContractError> {
         maybe_n
             .unwrap_or_default()
@@ -102,7 +149,7 @@ pub fn update_contract_partition(
             })
     })?;
 
-    PARTITION_SIZES.update(storage, src, |maybe_n| -> Result<_, ContractError> {
+    PARTITION_SIZES.update(storage, dst, |maybe_n| -> Result<_, ContractError> {
         maybe_n
             .unwrap_or_default()
             .checked_add(Uint64::one())
@@ -150,8 +197,8 @@ fn move_standard_indices(
     } else {
         // Dynamic metadata doesn't exist until first update. in this case,
         // assume existing values are initial values.
-        IX_UPDATED_BY.remove(storage, (src, meta.created_at.to_string(), contract_id));
-        IX_UPDATED_BY.save(storage, (dst, meta.created_at.to_string(), contract_id), &X)?;
+        IX_UPDATED_BY.remove(storage, (src, meta.created_by.to_string(), contract_id));
+        IX_UPDATED_BY.save(storage, (dst, meta.created_by.to_string(), contract_id), &X)?;
         IX_UPDATED_AT.remove(storage, (src, meta.created_at.nanos(), contract_id));
         IX_UPDATED_AT.save(storage, (dst, meta.created_at.nanos(), contract_id), &X)?;
         IX_REV.remove(storage, (src, 1, contract_id));
@@ -173,66 +220,75 @@ pub fn move_custom_indices(
         .filter_map(|r| r.ok())
         .collect();
 
+    let max_str_len = CONFIG_STR_MAX_LEN.load(storage)? as usize;
+    let index_names: Vec<&String> = entries.iter().map(|(name, _)| name).collect();
+
+    move_contract(storage, contract_id, src, dst, &index_names, max_str_len)?;
+    aggregates::move_contract(storage, contract_id, src, dst, &index_names)?;
+    facets::move_contract(storage, contract_id, src, dst, &index_names, max_str_len)?;
+
     for (index_name, index_type) in entries.iter() {
         let index_storage_key = build_index_storage_key(index_name);
         match index_type {
             IndexType::String => {
                 let index: CustomIndexMap<&String> = Map::new(&index_storage_key);
-                let value = VALUES_STRING.load(storage, (contract_id, &index_storage_key))?;
-                index.remove(storage, (src, &value, contract_id));
-                index.save(storage, (dst, &value, contract_id), &X)?;
+                let value = VALUES_STRING.load(storage, (contract_id, index_name))?;
+                let case_insensitive = is_case_insensitive(storage, index_name)?;
+                let key = build_string_index_key(&value, case_insensitive, max_str_len);
+                index.remove(storage, (src, &key, contract_id));
+                index.save(storage, (dst, &key, contract_id), &X)?;
             },
             IndexType::Bool => {
                 let index: CustomIndexMap<u8> = Map::new(&index_storage_key);
-                let value = VALUES_BOOL.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_BOOL.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Timestamp => {
                 let index: CustomIndexMap<u64> = Map::new(&index_storage_key);
-                let value = VALUES_TIME.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_TIME.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.nanos(), contract_id));
                 index.save(storage, (dst, value.nanos(), contract_id), &X)?;
             },
             IndexType::Int32 => {
                 let index: CustomIndexMap<i32> = Map::new(&index_storage_key);
-                let value = VALUES_I32.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_I32.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Uint8 => {
                 let index: CustomIndexMap<u8> = Map::new(&index_storage_key);
-                let value = VALUES_U8.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_U8.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Uint16 => {
                 let index: CustomIndexMap<u16> = Map::new(&index_storage_key);
-                let value = VALUES_U16.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_U16.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Uint32 => {
                 let index: CustomIndexMap<u32> = Map::new(&index_storage_key);
-                let value = VALUES_U32.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_U32.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Uint64 => {
                 let index: CustomIndexMap<u64> = Map::new(&index_storage_key);
-                let value = VALUES_U64.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_U64.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Uint128 => {
                 let index: CustomIndexMap<u128> = Map::new(&index_storage_key);
-                let value = VALUES_U128.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_U128.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.into(), contract_id));
                 index.save(storage, (dst, value.into(), contract_id), &X)?;
             },
             IndexType::Binary => {
                 let index: CustomIndexMap<&[u8]> = Map::new(&index_storage_key);
-                let value = VALUES_BINARY.load(storage, (contract_id, &index_storage_key))?;
+                let value = VALUES_BINARY.load(storage, (contract_id, index_name))?;
                 index.remove(storage, (src, value.as_slice(), contract_id));
                 index.save(storage, (dst, value.as_slice(), contract_id), &X)?;
             },
@@ -0,0 +1,69 @@
+use cosmwasm_std::{attr, Order, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    models::Subscription,
+    msg::SubscriptionParams,
+    state::{ensure_allowed_by_acl, MAX_SUBSCRIBERS, MAX_SUBSCRIBER_GAS_LIMIT, SUBSCRIPTIONS},
+};
+
+/// Register a subscriber for TableEvents or replace its existing subscription.
+pub fn on_execute(
+    ctx: Context,
+    params: SubscriptionParams,
+) -> Result<Response, ContractError> {
+    let action = "subscribe";
+    let Context { deps, info, env } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/subscribe")?;
+
+    let subscriber = deps.api.addr_validate(params.subscriber.as_str())?;
+
+    if params.kinds.is_empty() {
+        return Err(ContractError::ValidationError {
+            reason: "subscription must include at least one event kind".to_owned(),
+        });
+    }
+
+    if params.gas_limit == 0 || params.gas_limit > MAX_SUBSCRIBER_GAS_LIMIT {
+        return Err(ContractError::ValidationError {
+            reason: format!(
+                "subscription gas limit must be between 1 and {}",
+                MAX_SUBSCRIBER_GAS_LIMIT
+            ),
+        });
+    }
+
+    if !SUBSCRIPTIONS.has(deps.storage, &subscriber)
+        && SUBSCRIPTIONS
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .count()
+            >= MAX_SUBSCRIBERS
+    {
+        return Err(ContractError::ValidationError {
+            reason: format!(
+                "table cannot have more than {} subscribers",
+                MAX_SUBSCRIBERS
+            ),
+        });
+    }
+
+    SUBSCRIPTIONS.save(
+        deps.storage,
+        &subscriber,
+        &Subscription {
+            kinds: params.kinds,
+            partitions: params.partitions,
+            groups: params.groups,
+            code_ids: params.code_ids,
+            gas_limit: params.gas_limit,
+            created_at: env.block.time,
+        },
+    )?;
+
+    Ok(
+        Response::new()
+            .add_attributes(vec![attr("action", action), attr("subscriber", subscriber)]),
+    )
+}
@@ -0,0 +1,24 @@
+use cosmwasm_std::{attr, Addr, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    state::{ensure_allowed_by_acl, SUBSCRIPTIONS},
+};
+
+pub fn on_execute(
+    ctx: Context,
+    subscriber: Addr,
+) -> Result<Response, ContractError> {
+    let action = "unsubscribe";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/unsubscribe")?;
+
+    SUBSCRIPTIONS.remove(deps.storage, &subscriber);
+
+    Ok(
+        Response::new()
+            .add_attributes(vec![attr("action", action), attr("subscriber", subscriber)]),
+    )
+}
@@ -3,11 +3,13 @@ use cosmwasm_std::{to_json_binary, Addr, Response, WasmMsg};
 use crate::{
     context::Context,
     error::ContractError,
+    events::TableEvent,
     lifecycle::{LifecycleArgs, LifecycleExecuteMsg, LifecycleExecuteMsgEnvelope},
     state::{
         ensure_allowed_by_acl, CONTRACT_ADDR_2_ID, CONTRACT_SUSPENSIONS,
         CONTRACT_USES_LIFECYCLE_HOOKS,
     },
+    subscriptions::{build_event_args, build_event_submsgs},
 };
 
 /// Load and restore the previous config, provided there is one.
@@ -23,6 +25,15 @@ pub fn on_execute(
     ensure_allowed_by_acl(&deps, &info.sender, "/table/unsuspend")?;
     if let Some(id) = CONTRACT_ADDR_2_ID.may_load(deps.storage, &contract_addr)? {
         CONTRACT_SUSPENSIONS.remove(deps.storage, id.into());
+        resp = resp.add_submessages(build_event_submsgs(
+            deps.storage,
+            TableEvent::Resume(build_event_args(
+                &env,
+                &contract_addr,
+                id.into(),
+                &info.sender,
+            )),
+        )?);
         if CONTRACT_USES_LIFECYCLE_HOOKS
             .may_load(deps.storage, id.into())?
             .unwrap_or_default()
@@ -0,0 +1,47 @@
+use cosmwasm_std::{attr, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    msg::GroupUpdateParams,
+    state::{ensure_allowed_by_acl, GROUP_IX_NAME, GROUP_METADATA, X},
+};
+
+pub fn on_execute(
+    ctx: Context,
+    params: GroupUpdateParams,
+) -> Result<Response, ContractError> {
+    let action = "update_group";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/update-group")?;
+
+    let group_id = params.group;
+    let mut meta = GROUP_METADATA
+        .may_load(deps.storage, group_id)?
+        .ok_or_else(|| ContractError::GroupNotFound {
+            reason: format!("group {} does not exist", group_id),
+        })?;
+
+    if let Some(name) = params.name {
+        if name.is_empty() {
+            return Err(ContractError::ValidationError {
+                reason: "group name cannot be empty".to_owned(),
+            });
+        }
+        GROUP_IX_NAME.remove(deps.storage, (meta.name.clone(), group_id));
+        GROUP_IX_NAME.save(deps.storage, (name.clone(), group_id), &X)?;
+        meta.name = name;
+    }
+
+    if let Some(description) = params.description {
+        meta.description = Some(description);
+    }
+
+    GROUP_METADATA.save(deps.storage, group_id, &meta)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("group", group_id.to_string()),
+    ]))
+}
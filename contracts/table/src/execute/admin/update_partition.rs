@@ -0,0 +1,61 @@
+use cosmwasm_std::{attr, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    msg::PartitionUpdateParams,
+    state::{
+        ensure_allowed_by_acl, ensure_partition_exists, resolve_partition_id, PARTITION_METADATA,
+        PARTITION_NAME_2_ID,
+    },
+};
+
+pub fn on_execute(
+    ctx: Context,
+    params: PartitionUpdateParams,
+) -> Result<Response, ContractError> {
+    let action = "update_partition";
+    let Context { deps, info, .. } = ctx;
+
+    ensure_allowed_by_acl(&deps, &info.sender, "/table/update-partition")?;
+
+    let partition = resolve_partition_id(deps.storage, params.partition)?;
+
+    ensure_partition_exists(deps.storage, partition)?;
+
+    let mut meta = PARTITION_METADATA.load(deps.storage, partition)?;
+
+    // Keep the name -> ID lookup table in sync with the new name.
+    if let Some(name) = params.name {
+        if name.is_empty() {
+            return Err(ContractError::ValidationError {
+                reason: "partition name cannot be empty".to_owned(),
+            });
+        }
+        if name != meta.name {
+            if PARTITION_NAME_2_ID.has(deps.storage, name.clone()) {
+                return Err(ContractError::ValidationError {
+                    reason: format!("partition name '{}' is already taken", name),
+                });
+            }
+            PARTITION_NAME_2_ID.remove(deps.storage, meta.name.clone());
+            PARTITION_NAME_2_ID.save(deps.storage, name.clone(), &partition)?;
+            meta.name = name;
+        }
+    }
+
+    if let Some(description) = params.description {
+        meta.description = Some(description);
+    }
+
+    if let Some(policy) = params.policy {
+        meta.policy = Some(policy);
+    }
+
+    PARTITION_METADATA.save(deps.storage, partition, &meta)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("partition", partition.to_string()),
+    ]))
+}
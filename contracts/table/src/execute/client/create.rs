@@ -1,42 +1,115 @@
 use cosmwasm_std::{
-    attr, to_json_binary, Addr, DepsMut, Env, Event, Reply, Response, StdResult, Storage, SubMsg,
-    Uint64, WasmMsg,
+    attr, to_json_binary, Addr, Coin, DepsMut, Env, Event, Reply, Response, StdResult,
+    Storage, SubMsg, Uint64, WasmMsg,
 };
 
+use gelotto_core::models::token::TokenAmount;
+
+use super::update::update_tags;
 use crate::{
+    changes::record_change,
     context::Context,
-    ensure::ensure_authorized_code_id,
+    ensure::{ensure_authorized_code_id, ensure_partition_policy, ensure_valid_expiration},
     error::ContractError,
+    events::TableEvent,
+    fees::{build_fee_msgs, build_refund_msgs, deduct_native_fee, resolve_creation_fee},
     lifecycle::{LifecycleExecuteMsg, LifecycleExecuteMsgEnvelope, LifecycleSetupArgs},
-    models::{ContractMetadata, ReplyJob},
-    msg::CreationParams,
+    models::{ChangeKind, ContractMetadata, CreationFailure, ReplyJob},
+    msg::{CreationParams, PartitionSelector, TagUpdate, TagUpdates},
     state::{
-        append_group, ensure_allowed_by_acl, ensure_contract_not_suspended,
-        exists_contract_address, load_contract_id, load_next_contract_id, resolve_partition_id,
-        CONTRACT_METADATA, CONTRACT_USES_LIFECYCLE_HOOKS, IX_CODE_ID, IX_CONTRACT_ID,
-        IX_CREATED_AT, IX_CREATED_BY, IX_REV, IX_UPDATED_AT, IX_UPDATED_BY, PARTITION_SIZES,
-        REPLY_JOBS, REPLY_JOB_ID_COUNTER, X,
+        append_group, create_reply_job, ensure_allowed_by_acl, ensure_contract_not_suspended,
+        exists_contract_address, increment_code_id_size, load_contract_id, load_next_contract_id,
+        resolve_partition_id, set_expiration, ContractID, CODE_ID_ALLOWLIST, CONFIG_STR_MAX_LEN,
+        CONTRACT_METADATA, CONTRACT_USES_LIFECYCLE_HOOKS, CREATION_FAILURES, IX_CODE_ID,
+        IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY, IX_REV, IX_UPDATED_AT, IX_UPDATED_BY,
+        PARTITION_SIZES, X,
     },
+    subscriptions::{build_event_args, build_event_submsgs},
 };
 
 pub fn on_execute(ctx: Context, params: CreationParams) -> Result<Response, ContractError> {
     let Context { deps, info, env } = ctx;
     let action = "create";
 
-    ensure_authorized_code_id(deps.storage, params.code_id.into())?;
+    ensure_can_create(&deps, &info.sender)?;
+
+    if params.funds.is_some() {
+        return Err(ContractError::ValidationError {
+            reason: "funds is only used by CreateMany; Create sends the funds received".to_owned(),
+        });
+    }
+
+    // The creation fee, if any, is taken out of the funds sent. Only what
+    // remains is sent with the instantiation.
+    let fee = resolve_creation_fee(deps.storage, &params)?;
+    let funds = match &fee {
+        Some(fee) => deduct_native_fee(info.funds, fee)?,
+        None => info.funds,
+    };
+
+    let (job_id, submsg) =
+        build_create_submsg(deps.storage, &env, &info.sender, params, funds, fee, None)?;
 
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", action),
+            attr("job_id", job_id.to_string()),
+        ])
+        .add_submessage(submsg))
+}
+
+pub fn ensure_can_create(
+    deps: &DepsMut,
+    sender: &Addr,
+) -> Result<(), ContractError> {
     // If sender isn't the contract itself, only allow sender if auth'd by owner
     // address or ACL.
-    if !exists_contract_address(deps.storage, &info.sender) {
-        ensure_allowed_by_acl(&deps, &info.sender, "/table/create")?;
+    if !exists_contract_address(deps.storage, sender) {
+        ensure_allowed_by_acl(deps, sender, "/table/create")?;
     } else {
-        let sender_contract_id = load_contract_id(deps.storage, &info.sender)?;
+        let sender_contract_id = load_contract_id(deps.storage, sender)?;
         ensure_contract_not_suspended(deps.storage, sender_contract_id)?;
     }
+    Ok(())
+}
+
+/// Build the instantiate submsg for a new contract along with the reply job
+/// that registers it once instantiated. The given creation fee, already
+/// received, is paid out once the contract is registered.
+pub fn build_create_submsg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    params: CreationParams,
+    funds: Vec<Coin>,
+    fee: Option<TokenAmount>,
+    best_effort: Option<bool>,
+) -> Result<(u64, SubMsg), ContractError> {
+    ensure_authorized_code_id(storage, params.code_id.into())?;
+    ensure_valid_expiration(env, params.expires_at)?;
+
+    let params = apply_code_id_defaults(storage, params)?;
+    let initiator = &params.initiator.clone().unwrap_or(sender.clone());
+
+    // Check the partition's policy up front. It's checked again on reply, in
+    // case earlier contracts created in the same tx have used up its quotas.
+    if let Some(selector) = params.partition.clone() {
+        let partition = resolve_partition_id(storage, selector)?;
+        let is_child = exists_contract_address(storage, sender);
+        ensure_partition_policy(storage, partition, params.code_id.into(), initiator, is_child)?;
+    }
 
-    let initiator = &params.initiator.unwrap_or(info.sender.clone());
-    let job_id = create_reply_job(deps.storage, &params, initiator)?;
-    let admin: Option<String> = Some(params.admin.unwrap_or(env.contract.address).into());
+    let job_id = create_reply_job(
+        storage,
+        ReplyJob::Create {
+            params: Box::new(params.clone()),
+            initiator: initiator.clone(),
+            best_effort,
+            sender: best_effort.map(|_| sender.clone()),
+            fee,
+        },
+    )?;
+    let admin: Option<String> = Some(params.admin.unwrap_or(env.contract.address.clone()).into());
     // let maybe_table_name = TABLE_INFO.load(deps.storage)?.name;
     let label = params.label.unwrap_or_else(|| {
         format!(
@@ -48,47 +121,59 @@ pub fn on_execute(ctx: Context, params: CreationParams) -> Result<Response, Cont
         .to_owned()
     });
 
-    Ok(Response::new()
-        .add_attributes(vec![
-            attr("action", action),
-            attr("job_id", job_id.to_string()),
-        ])
-        .add_submessage(SubMsg::reply_always(
+    Ok((
+        job_id,
+        SubMsg::reply_always(
             WasmMsg::Instantiate {
                 code_id: params.code_id.into(),
-                msg: params.instantiate_msg.clone(),
-                funds: info.funds,
+                msg: params.instantiate_msg,
+                funds,
                 admin,
                 label,
             },
             job_id,
-        )))
+        ),
+    ))
 }
 
-fn create_reply_job(
-    storage: &mut dyn Storage,
-    msg: &CreationParams,
-    initiator: &Addr,
-) -> Result<u64, ContractError> {
-    let job_id: u64 = REPLY_JOB_ID_COUNTER
-        .update(storage, |n| -> Result<_, ContractError> {
-            Ok(n + Uint64::one())
-        })?
-        .into();
-    let job = ReplyJob::Create {
-        params: msg.clone(),
-        initiator: initiator.clone(),
-    };
-    REPLY_JOBS.save(storage, job_id, &job)?;
-    Ok(job_id)
+/// Fill in any creation params left unset with the defaults registered for
+/// the code ID, if any.
+pub fn apply_code_id_defaults(
+    storage: &dyn Storage,
+    mut params: CreationParams,
+) -> Result<CreationParams, ContractError> {
+    if let Some(defaults) = CODE_ID_ALLOWLIST.may_load(storage, params.code_id.into())? {
+        if params.partition.is_none() {
+            params.partition = defaults.partition.map(PartitionSelector::Id);
+        }
+        if params.use_lifecycle_hooks.is_none() {
+            params.use_lifecycle_hooks = defaults.use_lifecycle_hooks;
+        }
+        if params.groups.is_none() {
+            params.groups = defaults.groups;
+        }
+        if params.tags.is_none() {
+            params.tags = defaults.tags;
+        }
+    }
+    if params.partition.is_none() {
+        return Err(ContractError::ValidationError {
+            reason: format!("partition required for code ID {}", params.code_id),
+        });
+    }
+    Ok(params)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn on_reply(
     deps: DepsMut,
     env: Env,
     reply: Reply,
     params: CreationParams,
     initiator: Addr,
+    best_effort: Option<bool>,
+    sender: Option<Addr>,
+    fee: Option<TokenAmount>,
 ) -> Result<Response, ContractError> {
     let mut resp = Response::new();
 
@@ -101,92 +186,186 @@ pub fn on_reply(
                     .find(|attr| attr.key == "_contract_address")
                 {
                     let contract_addr = Addr::unchecked(attr.value.to_string());
-                    let contract_id = load_next_contract_id(deps.storage, &contract_addr)?;
-                    let p = resolve_partition_id(deps.storage, params.partition)?;
-
-                    // init creation-time contract metadata
-                    let metadata = ContractMetadata {
-                        id: contract_id.into(),
-                        is_managed: params.admin.is_none(),
-                        created_at_height: env.block.height.into(),
-                        created_at: env.block.time,
-                        created_by: initiator.clone(),
-                        code_id: params.code_id.into(),
-                        partition: p,
-                    };
-
-                    CONTRACT_METADATA.save(deps.storage, contract_id, &metadata)?;
-
                     let use_lifecycle_hooks = params.use_lifecycle_hooks.unwrap_or_default();
-
-                    CONTRACT_USES_LIFECYCLE_HOOKS.save(
+                    let is_managed = params.admin.is_none();
+                    let contract_id = register_contract(
                         deps.storage,
-                        contract_id,
-                        &use_lifecycle_hooks,
+                        &env,
+                        &contract_addr,
+                        params,
+                        &initiator,
+                        is_managed,
                     )?;
 
-                    PARTITION_SIZES.update(deps.storage, p, |maybe_n| -> StdResult<_> {
-                        Ok(maybe_n.unwrap_or_default() + Uint64::one())
-                    })?;
-
-                    IX_CONTRACT_ID.save(deps.storage, (p, contract_id, contract_id), &X)?;
-                    IX_CODE_ID.save(deps.storage, (p, params.code_id.into(), contract_id), &X)?;
-                    IX_REV.save(deps.storage, (p, 1, contract_id), &X)?;
-                    IX_CREATED_BY.save(
-                        deps.storage,
-                        (p, initiator.to_string(), contract_id),
-                        &X,
-                    )?;
-                    IX_UPDATED_BY.save(
-                        deps.storage,
-                        (p, initiator.to_string(), contract_id),
-                        &X,
-                    )?;
-                    IX_CREATED_AT.save(
-                        deps.storage,
-                        (p, env.block.time.nanos(), contract_id),
-                        &X,
-                    )?;
-                    IX_UPDATED_AT.save(
-                        deps.storage,
-                        (p, env.block.time.nanos(), contract_id),
-                        &X,
-                    )?;
-
-                    if let Some(group_ids) = params.groups {
-                        for group_id in group_ids.iter() {
-                            append_group(deps.storage, *group_id, contract_id)?;
-                        }
-                    }
-
                     resp = resp.add_event(
                         Event::new("post_create")
                             .add_attribute("contract_address", contract_addr.to_string())
                             .add_attribute("contract_id", contract_id.to_string()),
                     );
 
+                    resp = resp.add_submessages(build_event_submsgs(
+                        deps.storage,
+                        TableEvent::Create(build_event_args(
+                            &env,
+                            &contract_addr,
+                            contract_id,
+                            &initiator,
+                        )),
+                    )?);
+
+                    if let Some(fee) = &fee {
+                        resp = resp.add_messages(build_fee_msgs(deps.storage, fee)?);
+                    }
+
                     if use_lifecycle_hooks {
-                        resp = resp.add_message(WasmMsg::Execute {
-                            contract_addr: contract_addr.into(),
-                            msg: to_json_binary(&LifecycleExecuteMsgEnvelope::Lifecycle(
-                                LifecycleExecuteMsg::Setup(LifecycleSetupArgs {
-                                    table: env.contract.address.clone(),
-                                    initiator,
-                                    id: contract_id.to_string(),
-                                }),
-                            ))?,
-                            funds: vec![],
-                        });
+                        resp = resp.add_message(build_setup_msg(
+                            &env,
+                            &contract_addr,
+                            initiator,
+                            contract_id,
+                        )?);
                     }
                 }
             }
         }
         cosmwasm_std::SubMsgResult::Err(err_reason) => {
-            return Err(ContractError::CreateError {
-                reason: err_reason.into(),
-            });
+            if !best_effort.unwrap_or(false) {
+                return Err(ContractError::CreateError {
+                    reason: err_reason.into(),
+                });
+            }
+
+            // In best-effort mode, record the failure and refund the funds
+            // and fee meant for the failed instantiation instead of reverting.
+            CREATION_FAILURES.save(
+                deps.storage,
+                reply.id,
+                &CreationFailure {
+                    code_id: params.code_id,
+                    initiator,
+                    error: err_reason.clone(),
+                    height: env.block.height.into(),
+                    time: env.block.time,
+                },
+            )?;
+
+            resp = resp.add_event(
+                Event::new("create_failed")
+                    .add_attribute("job_id", reply.id.to_string())
+                    .add_attribute("error", err_reason.to_string()),
+            );
+
+            if let Some(sender) = sender {
+                let funds = params.funds.unwrap_or_default();
+                resp = resp.add_messages(build_refund_msgs(&sender, funds, fee)?);
+            }
         }
     }
 
     Ok(resp)
 }
+
+/// Save a new contract's metadata, initialize its indices and add it to its
+/// groups and tags, returning its new contract ID.
+pub fn register_contract(
+    storage: &mut dyn Storage,
+    env: &Env,
+    contract_addr: &Addr,
+    params: CreationParams,
+    initiator: &Addr,
+    is_managed: bool,
+) -> Result<ContractID, ContractError> {
+    let contract_id = load_next_contract_id(storage, contract_addr)?;
+    let p = resolve_partition_id(
+        storage,
+        params
+            .partition
+            .ok_or_else(|| ContractError::UnexpectedError {
+                reason: "creation job has no partition".to_owned(),
+            })?,
+    )?;
+
+    ensure_partition_policy(storage, p, params.code_id.into(), initiator, false)?;
+
+    // init creation-time contract metadata
+    let metadata = ContractMetadata {
+        id: contract_id.into(),
+        is_managed,
+        created_at_height: env.block.height.into(),
+        created_at: env.block.time,
+        created_by: initiator.clone(),
+        code_id: params.code_id.into(),
+        partition: p,
+    };
+
+    CONTRACT_METADATA.save(storage, contract_id, &metadata)?;
+
+    CONTRACT_USES_LIFECYCLE_HOOKS.save(
+        storage,
+        contract_id,
+        &params.use_lifecycle_hooks.unwrap_or_default(),
+    )?;
+
+    PARTITION_SIZES.update(storage, p, |maybe_n| -> StdResult<_> {
+        Ok(maybe_n.unwrap_or_default() + Uint64::one())
+    })?;
+
+    IX_CONTRACT_ID.save(storage, (p, contract_id, contract_id), &X)?;
+    IX_CODE_ID.save(storage, (p, params.code_id.into(), contract_id), &X)?;
+    increment_code_id_size(storage, params.code_id.into())?;
+    IX_REV.save(storage, (p, 1, contract_id), &X)?;
+    IX_CREATED_BY.save(storage, (p, initiator.to_string(), contract_id), &X)?;
+    IX_UPDATED_BY.save(storage, (p, initiator.to_string(), contract_id), &X)?;
+    IX_CREATED_AT.save(storage, (p, env.block.time.nanos(), contract_id), &X)?;
+    IX_UPDATED_AT.save(storage, (p, env.block.time.nanos(), contract_id), &X)?;
+
+    set_expiration(storage, contract_id, params.expires_at)?;
+
+    if let Some(group_ids) = params.groups {
+        for group_id in group_ids.iter() {
+            append_group(storage, *group_id, contract_id)?;
+        }
+    }
+
+    record_change(storage, env, contract_id, initiator, ChangeKind::Create)?;
+
+    if let Some(tags) = params.tags {
+        let max_str_len = CONFIG_STR_MAX_LEN.load(storage)? as usize;
+        update_tags(
+            storage,
+            p,
+            contract_id,
+            TagUpdates {
+                remove: None,
+                add: Some(
+                    tags.into_iter()
+                        .map(|text| TagUpdate { text, unique: None })
+                        .collect(),
+                ),
+            },
+            max_str_len,
+        )?;
+        record_change(storage, env, contract_id, initiator, ChangeKind::Tags)?;
+    }
+
+    Ok(contract_id)
+}
+
+pub fn build_setup_msg(
+    env: &Env,
+    contract_addr: &Addr,
+    initiator: Addr,
+    contract_id: ContractID,
+) -> Result<WasmMsg, ContractError> {
+    Ok(WasmMsg::Execute {
+        contract_addr: contract_addr.into(),
+        msg: to_json_binary(&LifecycleExecuteMsgEnvelope::Lifecycle(
+            LifecycleExecuteMsg::Setup(LifecycleSetupArgs {
+                table: env.contract.address.clone(),
+                initiator,
+                id: contract_id.to_string(),
+            }),
+        ))?,
+        funds: vec![],
+    })
+}
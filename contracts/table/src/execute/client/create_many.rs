@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{attr, Coin, Response, Uint128};
+use gelotto_core::models::token::{Token, TokenAmount};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    fees::resolve_creation_fee,
+    msg::{BatchCreationParams, CreationParams},
+};
+
+use super::create::{build_create_submsg, ensure_can_create};
+
+pub const MAX_BATCH_SIZE: usize = 50;
+
+/// Create multiple contracts, each with its own reply job. In best-effort
+/// mode, failed instantiations are recorded instead of reverting the batch.
+pub fn on_execute(
+    ctx: Context,
+    params: BatchCreationParams,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let action = "create_many";
+
+    ensure_can_create(&deps, &info.sender)?;
+
+    if params.entries.is_empty() || params.entries.len() > MAX_BATCH_SIZE {
+        return Err(ContractError::ValidationError {
+            reason: format!(
+                "batch must contain between 1 and {} entries",
+                MAX_BATCH_SIZE
+            ),
+        });
+    }
+
+    let mut fees: Vec<Option<TokenAmount>> = Vec::with_capacity(params.entries.len());
+    for entry in params.entries.iter() {
+        fees.push(resolve_creation_fee(deps.storage, entry)?);
+    }
+
+    ensure_funds_match(&info.funds, &params.entries, &fees)?;
+
+    let best_effort = params.best_effort.unwrap_or(false);
+    let mut resp = Response::new();
+    let mut job_ids: Vec<String> = Vec::with_capacity(params.entries.len());
+
+    for (entry, fee) in params.entries.into_iter().zip(fees) {
+        let funds = entry.funds.clone().unwrap_or_default();
+        let (job_id, submsg) = build_create_submsg(
+            deps.storage,
+            &env,
+            &info.sender,
+            entry,
+            funds,
+            fee,
+            Some(best_effort),
+        )?;
+        job_ids.push(job_id.to_string());
+        resp = resp.add_submessage(submsg);
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", action),
+        attr("job_ids", job_ids.join(",")),
+    ]))
+}
+
+/// Ensure that the funds sent are exactly the sum of each entry's funds and
+/// creation fee.
+fn ensure_funds_match(
+    funds: &[Coin],
+    entries: &[CreationParams],
+    fees: &[Option<TokenAmount>],
+) -> Result<(), ContractError> {
+    let mut required: BTreeMap<String, Uint128> = BTreeMap::new();
+    let fee_coins = fees
+        .iter()
+        .flatten()
+        .map(|fee| match &fee.token {
+            Token::Denom(denom) => Ok(Coin::new(fee.amount.u128(), denom)),
+            // CW20 tokens can only be received one creation at a time.
+            Token::Address(_) => Err(ContractError::InsufficientFee {
+                reason: format!(
+                    "creation fee of {} {} must be paid by sending CW20 tokens",
+                    fee.amount,
+                    fee.token.to_key()
+                ),
+            }),
+        })
+        .collect::<Result<Vec<Coin>, ContractError>>()?;
+
+    for coin in entries
+        .iter()
+        .filter_map(|e| e.funds.as_ref())
+        .flatten()
+        .chain(fee_coins.iter())
+    {
+        let total = required.entry(coin.denom.clone()).or_default();
+        *total = total
+            .checked_add(coin.amount)
+            .map_err(|e| ContractError::ValidationError {
+                reason: e.to_string(),
+            })?;
+    }
+
+    let mut received: BTreeMap<String, Uint128> = BTreeMap::new();
+    for coin in funds.iter() {
+        *received.entry(coin.denom.clone()).or_default() += coin.amount;
+    }
+
+    required.retain(|_, amount| !amount.is_zero());
+    received.retain(|_, amount| !amount.is_zero());
+
+    if required != received {
+        return Err(ContractError::ValidationError {
+            reason: "funds must equal the sum of each entry's funds and creation fee".to_owned(),
+        });
+    }
+
+    Ok(())
+}
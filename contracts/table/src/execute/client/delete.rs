@@ -1,27 +1,39 @@
-use std::marker::PhantomData;
-
-use cosmwasm_std::{to_json_binary, Addr, Order, Response, StdResult, Storage, Uint64, WasmMsg};
-use cw_storage_plus::{Bound, Deque, Map};
+use cosmwasm_std::{
+    to_json_binary, Addr, Env, Order, Response, StdResult, Storage, SubMsg, Uint64, WasmMsg,
+};
+use cw_storage_plus::{Deque, Map};
 
 use crate::{
+    aggregates,
+    changes::record_change,
+    composite::remove_contract,
     context::Context,
     error::ContractError,
+    events::TableEvent,
+    facets,
     lifecycle::{LifecycleArgs, LifecycleExecuteMsg, LifecycleExecuteMsgEnvelope},
-    models::ContractFlag,
+    models::{ChangeKind, ContractArchive, ContractFlag},
     msg::IndexType,
     state::{
-        ensure_allowed_by_acl, ensure_contract_not_suspended, incr_decr_index_size,
-        load_contract_id, remove_from_group, ContractID, CustomIndexMap, CONTRACT_ADDR_2_ID,
-        CONTRACT_DYN_METADATA, CONTRACT_GROUP_IDS, CONTRACT_ID_2_ADDR, CONTRACT_INDEX_TYPES,
-        CONTRACT_METADATA, CONTRACT_SUSPENSIONS, CONTRACT_TAGS, CONTRACT_USES_LIFECYCLE_HOOKS,
-        IX_CODE_ID, IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY, IX_REV, IX_TAG, IX_UPDATED_AT,
+        decrement_code_id_size, ensure_allowed_by_acl, ensure_contract_not_suspended,
+        incr_decr_index_size, is_case_insensitive, load_contract_id, remove_from_group,
+        set_expiration, uncount_flag, ContractID, CustomIndexMap, ARCHIVED_ADDR_2_ID,
+        CONFIG_ARCHIVE_ON_DELETE, CONFIG_STR_MAX_LEN, CONTRACT_ADDR_2_ID, CONTRACT_ARCHIVES,
+        CONTRACT_DYN_METADATA, CONTRACT_EXPIRATIONS, CONTRACT_FLAG_RESOLUTIONS,
+        CONTRACT_FLAG_WINDOW_STARTS, CONTRACT_GROUP_IDS, CONTRACT_ID_2_ADDR, CONTRACT_INDEX_TYPES,
+        CONTRACT_METADATA, CONTRACT_OPEN_FLAG_COUNTS, CONTRACT_OPEN_FLAG_WEIGHTS,
+        CONTRACT_SUSPENSIONS, CONTRACT_TAGS, CONTRACT_USES_LIFECYCLE_HOOKS, IX_CODE_ID,
+        IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY, IX_EXPIRES_AT, IX_REV, IX_TAG, IX_UPDATED_AT,
         IX_UPDATED_BY, PARTITION_SIZES, PARTITION_TAG_COUNTS, REL_ADDR_2_ID, REL_ID_2_ADDR,
         VALUES_BINARY, VALUES_BOOL, VALUES_I32, VALUES_STRING, VALUES_TIME, VALUES_U128,
-        VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8,
+        VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8, X,
     },
-    util::build_index_storage_key,
+    subscriptions::{build_event_args, build_event_submsgs},
+    util::{build_flags_storage_key, build_index_storage_key, build_string_index_key},
 };
 
+use super::sweep::MAX_TEARDOWN_GAS_LIMIT;
+
 // Replace the existing config in its entirety.
 pub fn on_execute(
     ctx: Context,
@@ -33,7 +45,6 @@ pub fn on_execute(
     deps.api.addr_validate(contract_addr.as_str())?;
 
     let contract_id = load_contract_id(deps.storage, &contract_addr)?;
-    let mut resp = Response::new().add_attribute("action", action);
 
     // If sender isn't the contract itself, only allow sender if auth'd by owner
     // address or ACL.
@@ -43,31 +54,146 @@ pub fn on_execute(
         ensure_contract_not_suspended(deps.storage, contract_id)?;
     };
 
+    Ok(delete_contract(
+        deps.storage,
+        &env,
+        &info.sender,
+        &contract_addr,
+        contract_id,
+        None,
+    )?
+    .add_attribute("action", action))
+}
+
+/// Remove a contract from the table, sending it the Teardown lifecycle hook if
+/// it uses lifecycle hooks and notifying subscribers of its deletion. Given a
+/// `teardown_reply_id`, an error in the Teardown hook is sent to that reply
+/// instead of reverting the deletion.
+pub fn delete_contract(
+    storage: &mut dyn Storage,
+    env: &Env,
+    initiator: &Addr,
+    contract_addr: &Addr,
+    contract_id: ContractID,
+    teardown_reply_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut resp = Response::new();
+
     if CONTRACT_USES_LIFECYCLE_HOOKS
-        .may_load(deps.storage, contract_id)?
+        .may_load(storage, contract_id)?
         .unwrap_or_default()
     {
-        resp = resp.add_message(WasmMsg::Execute {
+        let teardown_msg = WasmMsg::Execute {
             contract_addr: contract_addr.clone().into(),
             msg: to_json_binary(&LifecycleExecuteMsgEnvelope::Lifecycle(
                 LifecycleExecuteMsg::Teardown(LifecycleArgs {
                     table: env.contract.address.clone(),
-                    initiator: info.sender.clone(),
+                    initiator: initiator.clone(),
                 }),
             ))?,
             funds: vec![],
-        });
+        };
+        resp = match teardown_reply_id {
+            Some(reply_id) => resp.add_submessage(
+                SubMsg::reply_on_error(teardown_msg, reply_id)
+                    .with_gas_limit(MAX_TEARDOWN_GAS_LIMIT),
+            ),
+            None => resp.add_message(teardown_msg),
+        };
     }
 
-    delete_from_indices(deps.storage, contract_id)?;
-    delete_from_tags(deps.storage, contract_id)?;
-    delete_from_relationships(deps.storage, contract_id)?;
-    delete_from_partition(deps.storage, &contract_addr, contract_id)?;
-    delete_from_groups(deps.storage, contract_id)?;
+    // Build events while the contract's metadata and groups still exist.
+    resp = resp.add_submessages(build_event_submsgs(
+        storage,
+        TableEvent::Delete(build_event_args(env, contract_addr, contract_id, initiator)),
+    )?);
+
+    let is_archive = CONFIG_ARCHIVE_ON_DELETE
+        .may_load(storage)?
+        .unwrap_or_default();
+
+    record_change(
+        storage,
+        env,
+        contract_id,
+        initiator,
+        if is_archive {
+            ChangeKind::Archive
+        } else {
+            ChangeKind::Delete
+        },
+    )?;
+
+    if is_archive {
+        archive_contract(storage, env, initiator, contract_addr, contract_id)?;
+    } else {
+        delete_from_indices(storage, contract_id, false)?;
+        delete_from_tags(storage, contract_id)?;
+        delete_from_relationships(storage, contract_id, false)?;
+        delete_from_partition(storage, contract_addr, contract_id)?;
+        delete_from_groups(storage, contract_id)?;
+    }
 
     Ok(resp)
 }
 
+/// Remove a contract from all indices, keeping its metadata, values, tags,
+/// relationships and group memberships so that it can be restored.
+fn archive_contract(
+    storage: &mut dyn Storage,
+    env: &Env,
+    initiator: &Addr,
+    contract_addr: &Addr,
+    id: ContractID,
+) -> Result<(), ContractError> {
+    delete_from_indices(storage, id, true)?;
+    delete_from_tags(storage, id)?;
+    delete_from_relationships(storage, id, true)?;
+
+    for group_id in CONTRACT_GROUP_IDS
+        .prefix(id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        // Keep the membership itself for when the contract is restored.
+        remove_from_group(storage, group_id, id)?;
+        CONTRACT_GROUP_IDS.save(storage, (id, group_id), &X)?;
+    }
+
+    if let Some(expires_at) = CONTRACT_EXPIRATIONS.may_load(storage, id)? {
+        IX_EXPIRES_AT.remove(storage, (expires_at.nanos(), id));
+    }
+
+    let meta = CONTRACT_METADATA.load(storage, id)?;
+
+    PARTITION_SIZES.update(
+        storage,
+        meta.partition,
+        |maybe_n| -> Result<_, ContractError> {
+            maybe_n
+                .unwrap_or_default()
+                .checked_sub(Uint64::one())
+                .map_err(|e| ContractError::UnexpectedError {
+                    reason: e.to_string(),
+                })
+        },
+    )?;
+
+    CONTRACT_ADDR_2_ID.remove(storage, contract_addr);
+    ARCHIVED_ADDR_2_ID.save(storage, contract_addr, &id)?;
+    CONTRACT_ARCHIVES.save(
+        storage,
+        id,
+        &ContractArchive {
+            archived_at: env.block.time,
+            archived_at_height: env.block.height.into(),
+            archived_by: initiator.clone(),
+        },
+    )?;
+
+    Ok(())
+}
+
 fn delete_from_groups(
     storage: &mut dyn Storage,
     contract_id: ContractID,
@@ -101,6 +227,9 @@ fn delete_from_partition(
     // Clear suspension flags
     CONTRACT_SUSPENSIONS.remove(storage, id);
 
+    // Clear expiration time
+    set_expiration(storage, id, None)?;
+
     // Decrement parition size
     PARTITION_SIZES.update(
         storage,
@@ -115,14 +244,29 @@ fn delete_from_partition(
         },
     )?;
 
-    // Clear ContractFlags
-    let flags_deque_key = format!("_flags_{}", id);
+    // Clear ContractFlags and their resolutions
+    CONTRACT_OPEN_FLAG_COUNTS.remove(storage, id);
+
+    for end in CONTRACT_FLAG_RESOLUTIONS
+        .prefix(id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<u32>>>()?
+    {
+        CONTRACT_FLAG_RESOLUTIONS.remove(storage, (id, end));
+    }
+
+    let flags_deque_key = build_flags_storage_key(id);
     let flags: Deque<ContractFlag> = Deque::new(flags_deque_key.as_str());
 
-    for _ in 0..flags.len(storage)? {
-        flags.pop_front(storage)?;
+    for position in 0..flags.len(storage)? {
+        if let Some(flag) = flags.pop_front(storage)? {
+            uncount_flag(storage, id, position, &flag.sender)?;
+        }
     }
 
+    CONTRACT_OPEN_FLAG_WEIGHTS.remove(storage, id);
+    CONTRACT_FLAG_WINDOW_STARTS.remove(storage, id);
+
     Ok(())
 }
 
@@ -172,9 +316,12 @@ fn delete_from_tags(
     Ok(())
 }
 
+/// Remove a contract from all built-in and custom indices. Unless archiving the
+/// contract, its custom index values are removed as well.
 fn delete_from_indices(
     storage: &mut dyn Storage,
     id: ContractID,
+    is_archive: bool,
 ) -> Result<(), ContractError> {
     let meta = CONTRACT_METADATA.load(storage, id)?;
     let p = meta.partition;
@@ -182,6 +329,7 @@ fn delete_from_indices(
     // Remove from main metadata indices
     IX_CONTRACT_ID.remove(storage, (p, id, id));
     IX_CODE_ID.remove(storage, (p, meta.code_id.into(), id));
+    decrement_code_id_size(storage, meta.code_id.into())?;
     IX_CREATED_AT.remove(storage, (p, meta.created_at.nanos(), id));
     IX_CREATED_BY.remove(storage, (p, meta.created_by.to_string(), id));
 
@@ -190,8 +338,28 @@ fn delete_from_indices(
         IX_UPDATED_AT.remove(storage, (p, up_meta.updated_at.nanos(), id));
         IX_UPDATED_BY.remove(storage, (p, up_meta.updated_by.to_string(), id));
         IX_REV.remove(storage, (p, up_meta.rev.into(), id));
+    } else {
+        // Dynamic metadata doesn't exist until first update, so the "update"
+        // indices still hold the contract's initial values.
+        IX_UPDATED_AT.remove(storage, (p, meta.created_at.nanos(), id));
+        IX_UPDATED_BY.remove(storage, (p, meta.created_by.to_string(), id));
+        IX_REV.remove(storage, (p, 1, id));
     }
 
+    // Remove from composite indices, running aggregates and facet counts,
+    // which are built from the custom index values.
+    let index_names = CONTRACT_INDEX_TYPES
+        .prefix(id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    let max_str_len = CONFIG_STR_MAX_LEN.load(storage)? as usize;
+
+    let index_names: Vec<&String> = index_names.iter().collect();
+
+    remove_contract(storage, p, id, &index_names, max_str_len)?;
+    aggregates::remove_contract(storage, p, id, &index_names)?;
+    facets::remove_contract(storage, p, id, &index_names, max_str_len)?;
+
     // Remove from custom indices
     for result in CONTRACT_INDEX_TYPES
         .prefix(id)
@@ -200,111 +368,161 @@ fn delete_from_indices(
     {
         let (index_name, index_type) = result?;
 
-        CONTRACT_INDEX_TYPES.remove(storage, (id, &index_name));
-
-        incr_decr_index_size(storage, &index_name, false)?;
-
         let index_storage_key = build_index_storage_key(&index_name);
 
         match index_type {
             IndexType::String => {
                 if let Some(v) = VALUES_STRING.may_load(storage, (id, &index_name))? {
-                    VALUES_STRING.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<&String> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, &v, id));
+                    let case_insensitive = is_case_insensitive(storage, &index_name)?;
+                    let value_key = build_string_index_key(&v, case_insensitive, max_str_len);
+                    let key = (p, &value_key, id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Bool => {
                 if let Some(v) = VALUES_BOOL.may_load(storage, (id, &index_name))? {
-                    VALUES_BOOL.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u8> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, if v { 1 } else { 0 }, id));
+                    let key = (p, if v { 1 } else { 0 }, id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Timestamp => {
                 if let Some(v) = VALUES_TIME.may_load(storage, (id, &index_name))? {
-                    VALUES_TIME.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u64> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v.nanos(), id));
+                    let key = (p, v.nanos(), id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Int32 => {
                 if let Some(v) = VALUES_I32.may_load(storage, (id, &index_name))? {
-                    VALUES_I32.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<i32> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v, id));
+                    let key = (p, v, id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Uint8 => {
                 if let Some(v) = VALUES_U8.may_load(storage, (id, &index_name))? {
-                    VALUES_U8.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u8> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v, id));
+                    let key = (p, v, id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Uint16 => {
                 if let Some(v) = VALUES_U16.may_load(storage, (id, &index_name))? {
-                    VALUES_U16.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u16> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v, id));
+                    let key = (p, v, id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Uint32 => {
                 if let Some(v) = VALUES_U32.may_load(storage, (id, &index_name))? {
-                    VALUES_U32.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u32> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v, id));
+                    let key = (p, v, id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Uint64 => {
                 if let Some(v) = VALUES_U64.may_load(storage, (id, &index_name))? {
-                    VALUES_U64.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u64> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v.u64(), id));
+                    let key = (p, v.u64(), id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Uint128 => {
                 if let Some(v) = VALUES_U128.may_load(storage, (id, &index_name))? {
-                    VALUES_U128.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<u128> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, v.u128(), id));
+                    let key = (p, v.u128(), id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
             IndexType::Binary => {
                 if let Some(v) = VALUES_BINARY.may_load(storage, (id, &index_name))? {
-                    VALUES_BINARY.remove(storage, (id, &index_name));
                     let index: CustomIndexMap<&[u8]> = Map::new(&index_storage_key);
-                    index.remove(storage, (p, &v.as_slice(), id));
+                    let key = (p, v.as_slice(), id);
+                    if index.has(storage, key) {
+                        index.remove(storage, key);
+                        incr_decr_index_size(storage, &index_name, false)?;
+                    }
                 }
             },
         }
+
+        // Archived contracts keep their values for when they're restored.
+        if !is_archive {
+            CONTRACT_INDEX_TYPES.remove(storage, (id, &index_name));
+            remove_value(storage, id, &index_name, &index_type);
+        }
     }
 
     Ok(())
 }
 
+fn remove_value(
+    storage: &mut dyn Storage,
+    id: ContractID,
+    index_name: &String,
+    index_type: &IndexType,
+) {
+    let key = (id, index_name);
+    match index_type {
+        IndexType::String => VALUES_STRING.remove(storage, key),
+        IndexType::Bool => VALUES_BOOL.remove(storage, key),
+        IndexType::Timestamp => VALUES_TIME.remove(storage, key),
+        IndexType::Int32 => VALUES_I32.remove(storage, key),
+        IndexType::Uint8 => VALUES_U8.remove(storage, key),
+        IndexType::Uint16 => VALUES_U16.remove(storage, key),
+        IndexType::Uint32 => VALUES_U32.remove(storage, key),
+        IndexType::Uint64 => VALUES_U64.remove(storage, key),
+        IndexType::Uint128 => VALUES_U128.remove(storage, key),
+        IndexType::Binary => VALUES_BINARY.remove(storage, key),
+    }
+}
+
 fn delete_from_relationships(
     storage: &mut dyn Storage,
     id: ContractID,
+    is_archive: bool,
 ) -> Result<(), ContractError> {
     for result in REL_ID_2_ADDR
-        .keys(
-            storage,
-            Some(Bound::Inclusive((
-                (id, "".to_owned(), "".to_owned()),
-                PhantomData,
-            ))),
-            None,
-            Order::Ascending,
-        )
+        .sub_prefix(id)
+        .keys(storage, None, None, Order::Ascending)
         .collect::<Vec<StdResult<_>>>()
     {
-        let (contract_id, rel_name, account_addr) = result?;
+        let (rel_name, account_addr) = result?;
 
-        REL_ID_2_ADDR.remove(
-            storage,
-            (contract_id, rel_name.clone(), account_addr.clone()),
-        );
+        // Archived contracts keep their own side of each relationship, which
+        // is enough to rebuild the other side when they're restored.
+        if !is_archive {
+            REL_ID_2_ADDR.remove(storage, (id, rel_name.clone(), account_addr.clone()));
+        }
         REL_ADDR_2_ID.remove(storage, (account_addr, rel_name, id.to_string()));
     }
 
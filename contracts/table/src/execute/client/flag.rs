@@ -1,18 +1,27 @@
-use cosmwasm_std::{attr, to_json_binary, Response, WasmMsg};
+use cosmwasm_std::{attr, to_json_binary, Addr, Response, Storage, Timestamp, WasmMsg};
 use cw_storage_plus::Deque;
 
 use crate::{
     context::Context,
     error::ContractError,
+    events::TableEvent,
     lifecycle::{LifecycleArgs, LifecycleExecuteMsg, LifecycleExecuteMsgEnvelope},
     models::ContractFlag,
-    msg::FlagParams,
+    msg::{AutoSuspensionConfig, FlagParams},
     state::{
-        ensure_allowed_by_acl, ensure_contract_not_suspended, load_contract_id,
-        CONTRACT_SUSPENSIONS, CONTRACT_USES_LIFECYCLE_HOOKS,
+        ensure_allowed_by_acl, ensure_contract_not_suspended, load_contract_id, ContractID,
+        CONFIG_AUTO_SUSPENSION, CONTRACT_FLAGGERS, CONTRACT_FLAG_WINDOW_STARTS,
+        CONTRACT_OPEN_FLAG_COUNTS, CONTRACT_OPEN_FLAG_WEIGHTS, CONTRACT_SUSPENSIONS,
+        CONTRACT_USES_LIFECYCLE_HOOKS,
     },
+    subscriptions::{build_event_args, build_event_submsgs},
+    util::build_flags_storage_key,
 };
 
+// Max number of flags made before the auto-suspension window that are removed
+// from a contract's open flag weight per call.
+pub const MAX_EXPIRED_FLAGS: u32 = 20;
+
 pub fn on_execute(
     ctx: Context,
     params: FlagParams,
@@ -22,27 +31,55 @@ pub fn on_execute(
 
     let contract_addr = deps.api.addr_validate(params.contract.as_str())?;
     let contract_id = load_contract_id(deps.storage, &contract_addr)?;
+    let auto_suspension = CONFIG_AUTO_SUSPENSION.may_load(deps.storage)?;
+    let suspend = params.suspend.unwrap_or(false);
 
     // If sender isn't the contract itself, only allow sender if auth'd by owner
-    // address or ACL.
+    // address or ACL. When auto-suspension is enabled, anyone may flag, but
+    // only an authorized sender may suspend the contract outright.
     if contract_addr != info.sender {
-        ensure_allowed_by_acl(&deps, &info.sender, "/table/flag")?;
+        if suspend || auto_suspension.is_none() {
+            ensure_allowed_by_acl(&deps, &info.sender, "/table/flag")?;
+        }
     } else {
         ensure_contract_not_suspended(deps.storage, contract_id)?;
     };
 
-    let flags_deque_key = format!("_flags_{}", contract_id);
+    let flags_deque_key = build_flags_storage_key(contract_id);
     let flags: Deque<ContractFlag> = Deque::new(flags_deque_key.as_str());
     let mut resp = Response::new().add_attributes(vec![attr("action", action)]);
+    let mut is_auto_suspended = false;
+
+    if let Some(config) = &auto_suspension {
+        // Flags on a suspended contract would only add to a count that no
+        // longer matters.
+        ensure_contract_not_suspended(deps.storage, contract_id)?;
 
-    if params.suspend.unwrap_or(false) {
+        let (total_weight, is_window_current) = count_flag(
+            deps.storage,
+            contract_id,
+            &flags,
+            config,
+            env.block.time,
+            &info.sender,
+            params.code,
+            suspend,
+        )?;
+
+        // The threshold is only checked once every flag made before the window
+        // has been removed from the total, so that expired flags never count.
+        is_auto_suspended = is_window_current && total_weight >= config.threshold as u64;
+    }
+
+    if suspend || is_auto_suspended {
+        resp = resp.add_attribute("suspended", "true");
         CONTRACT_SUSPENSIONS.save(deps.storage, contract_id, &true)?;
         if CONTRACT_USES_LIFECYCLE_HOOKS
             .may_load(deps.storage, contract_id.into())?
             .unwrap_or_default()
         {
             resp = resp.add_message(WasmMsg::Execute {
-                contract_addr: contract_addr.into(),
+                contract_addr: contract_addr.clone().into(),
                 msg: to_json_binary(&LifecycleExecuteMsgEnvelope::Lifecycle(
                     LifecycleExecuteMsg::Suspend(LifecycleArgs {
                         table: env.contract.address.clone(),
@@ -54,6 +91,8 @@ pub fn on_execute(
         }
     }
 
+    let event_args = build_event_args(&env, &contract_addr, contract_id, &info.sender);
+
     flags.push_back(
         deps.storage,
         &ContractFlag {
@@ -65,5 +104,115 @@ pub fn on_execute(
         },
     )?;
 
+    CONTRACT_OPEN_FLAG_COUNTS.update(
+        deps.storage,
+        contract_id,
+        |maybe_n| -> Result<_, ContractError> { Ok(maybe_n.unwrap_or_default() + 1) },
+    )?;
+
+    resp = resp.add_submessages(build_event_submsgs(
+        deps.storage,
+        TableEvent::Flag(event_args.clone()),
+    )?);
+
+    if suspend || is_auto_suspended {
+        resp = resp.add_submessages(build_event_submsgs(
+            deps.storage,
+            TableEvent::Suspend(event_args),
+        )?);
+    }
+
     Ok(resp)
 }
+
+/// Add the sender's flag to the contract's running open flag weight, after
+/// removing a bounded number of flags made before the configured window from
+/// it. Returns the new weight and whether every such flag has been removed.
+#[allow(clippy::too_many_arguments)]
+fn count_flag(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    flags: &Deque<ContractFlag>,
+    config: &AutoSuspensionConfig,
+    time: Timestamp,
+    sender: &Addr,
+    code: Option<u32>,
+    suspend: bool,
+) -> Result<(u64, bool), ContractError> {
+    let n_total = flags.len(storage)?;
+    let n_open = CONTRACT_OPEN_FLAG_COUNTS
+        .may_load(storage, contract_id)?
+        .unwrap_or_default();
+    let min_time = config
+        .window
+        .map(|secs| {
+            Timestamp::from_nanos(
+                time.nanos()
+                    .saturating_sub(secs.saturating_mul(1_000_000_000)),
+            )
+        })
+        .unwrap_or_default();
+
+    let mut window_start = CONTRACT_FLAG_WINDOW_STARTS
+        .may_load(storage, contract_id)?
+        .unwrap_or(n_total.saturating_sub(n_open));
+    let mut total = CONTRACT_OPEN_FLAG_WEIGHTS
+        .may_load(storage, contract_id)?
+        .unwrap_or_default();
+    let mut is_window_current = true;
+    let mut n_expired: u32 = 0;
+
+    // Flags are in chronological order, so expired flags are at the front of
+    // the window.
+    while let Some(flag) = flags.get(storage, window_start)? {
+        if flag.time >= min_time {
+            break;
+        }
+        if n_expired == MAX_EXPIRED_FLAGS {
+            is_window_current = false;
+            break;
+        }
+        if let Some((position, weight)) =
+            CONTRACT_FLAGGERS.may_load(storage, (contract_id, &flag.sender))?
+        {
+            if position == window_start {
+                CONTRACT_FLAGGERS.remove(storage, (contract_id, &flag.sender));
+                total = total.saturating_sub(weight as u64);
+            }
+        }
+        window_start += 1;
+        n_expired += 1;
+    }
+
+    // Each sender may have only one flag counting toward the threshold. A
+    // sender's expired flag that hasn't been removed yet is replaced.
+    let mut is_already_counted = false;
+    if let Some((position, weight)) = CONTRACT_FLAGGERS.may_load(storage, (contract_id, sender))? {
+        if position >= window_start {
+            let is_expired = flags
+                .get(storage, position)?
+                .map(|flag| flag.time < min_time)
+                .unwrap_or(true);
+            if is_expired {
+                total = total.saturating_sub(weight as u64);
+            } else if suspend {
+                is_already_counted = true;
+            } else {
+                return Err(ContractError::ValidationError {
+                    reason: format!("{} has already flagged this contract", sender),
+                });
+            }
+        }
+    }
+
+    if !is_already_counted {
+        let weight = config.get_weight(code);
+        CONTRACT_FLAGGERS.save(storage, (contract_id, sender), &(n_total, weight))?;
+        total += weight as u64;
+    }
+
+    CONTRACT_FLAG_WINDOW_STARTS.save(storage, contract_id, &window_start)?;
+    CONTRACT_OPEN_FLAG_WEIGHTS.save(storage, contract_id, &total)?;
+
+    Ok((total, is_window_current))
+}
@@ -1,4 +1,8 @@
 pub mod create;
+pub mod create_many;
 pub mod delete;
 pub mod flag;
+pub mod receive;
+pub mod sweep;
+pub mod sync_admin;
 pub mod update;
@@ -0,0 +1,54 @@
+use cosmwasm_std::{attr, from_json, Response};
+use cw20::Cw20ReceiveMsg;
+use gelotto_core::models::token::Token;
+
+use crate::{context::Context, error::ContractError, fees::resolve_creation_fee, msg::ReceiveMsg};
+
+use super::create::{build_create_submsg, ensure_can_create};
+
+/// Handle CW20 tokens sent to pay a creation fee. The CW20 sender is treated
+/// as the sender of the creation.
+pub fn on_execute(
+    ctx: Context,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let Context { deps, info, env } = ctx;
+    let cw20_addr = info.sender;
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+
+    match from_json(&cw20_msg.msg)? {
+        ReceiveMsg::Create(params) => {
+            let action = "create";
+
+            ensure_can_create(&deps, &sender)?;
+
+            let fee = resolve_creation_fee(deps.storage, &params)?.ok_or_else(|| {
+                ContractError::ValidationError {
+                    reason: "no creation fee to pay".to_owned(),
+                }
+            })?;
+
+            if fee.token != Token::Address(cw20_addr.clone()) || fee.amount != cw20_msg.amount {
+                return Err(ContractError::InsufficientFee {
+                    reason: format!(
+                        "creation fee is {} {}, received {} {}",
+                        fee.amount,
+                        fee.token.to_key(),
+                        cw20_msg.amount,
+                        cw20_addr
+                    ),
+                });
+            }
+
+            let (job_id, submsg) =
+                build_create_submsg(deps.storage, &env, &sender, params, vec![], Some(fee), None)?;
+
+            Ok(Response::new()
+                .add_attributes(vec![
+                    attr("action", action),
+                    attr("job_id", job_id.to_string()),
+                ])
+                .add_submessage(submsg))
+        },
+    }
+}
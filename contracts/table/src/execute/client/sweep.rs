@@ -0,0 +1,83 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{attr, Order, Response, StdResult};
+use cw_storage_plus::Bound;
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    msg::SweepParams,
+    state::{load_contract_addr, ContractID, IX_EXPIRES_AT},
+};
+
+use super::delete::delete_contract;
+
+pub const DEFAULT_BATCH_SIZE: u32 = 20;
+pub const MAX_BATCH_SIZE: u32 = 100;
+
+// Reply ID of Teardown hooks sent to swept contracts. Reply job IDs count up
+// from 1, so this never collides with one.
+pub const TEARDOWN_REPLY_ID: u64 = u64::MAX;
+
+// Max gas a swept contract may use to handle its Teardown hook. A reply can't
+// catch a hook running out of the transaction's gas, only out of its own limit.
+pub const MAX_TEARDOWN_GAS_LIMIT: u64 = 1_000_000;
+
+/// Delete a batch of expired contracts, earliest expiration first. Anyone may
+/// sweep, since only contracts past their expiration time are deleted. A
+/// contract whose Teardown hook fails, or runs out of its capped gas, is still
+/// deleted, so that it can't block the sweep.
+pub fn on_execute(
+    ctx: Context,
+    params: SweepParams,
+) -> Result<Response, ContractError> {
+    let action = "sweep";
+    let Context { deps, env, info } = ctx;
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE) as usize;
+
+    let contract_ids: Vec<ContractID> = IX_EXPIRES_AT
+        .keys(
+            deps.storage,
+            None,
+            Some(Bound::Inclusive((
+                (env.block.time.nanos(), ContractID::MAX),
+                PhantomData,
+            ))),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|r| r.map(|(_, id)| id))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut resp = Response::new();
+
+    for contract_id in contract_ids.iter() {
+        let contract_addr = load_contract_addr(deps.storage, *contract_id)?;
+        let deleted = delete_contract(
+            deps.storage,
+            &env,
+            &info.sender,
+            &contract_addr,
+            *contract_id,
+            Some(TEARDOWN_REPLY_ID),
+        )?;
+        resp = resp.add_submessages(deleted.messages);
+    }
+
+    Ok(resp.add_attributes(vec![
+        attr("action", action),
+        attr("deleted", contract_ids.len().to_string()),
+        attr(
+            "status",
+            if contract_ids.len() < limit {
+                "done"
+            } else {
+                "sweeping"
+            },
+        ),
+    ]))
+}
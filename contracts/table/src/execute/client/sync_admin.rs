@@ -0,0 +1,42 @@
+use cosmwasm_std::{attr, Addr, Response};
+
+use crate::{
+    context::Context,
+    error::ContractError,
+    state::{load_contract_id, CONTRACT_METADATA},
+};
+
+/// Set whether a contract is managed by the table from its actual wasm admin.
+/// To hand management to the table, a contract's admin updates the admin to
+/// the table and then executes this. Since it only reflects the chain's
+/// contract info, anyone may execute it.
+pub fn on_execute(
+    ctx: Context,
+    contract_addr: Addr,
+) -> Result<Response, ContractError> {
+    let action = "sync_admin";
+    let Context { deps, env, .. } = ctx;
+
+    let contract_addr = deps.api.addr_validate(contract_addr.as_str())?;
+    let contract_id = load_contract_id(deps.storage, &contract_addr)?;
+    let contract_info = deps.querier.query_wasm_contract_info(&contract_addr)?;
+    let is_managed = contract_info.admin == Some(env.contract.address.to_string());
+
+    CONTRACT_METADATA.update(
+        deps.storage,
+        contract_id,
+        |maybe_meta| -> Result<_, ContractError> {
+            let mut meta = maybe_meta.ok_or_else(|| ContractError::ContractNotFound {
+                reason: format!("contract {} has no metadata", contract_addr),
+            })?;
+            meta.is_managed = is_managed;
+            Ok(meta)
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("contract", contract_addr),
+        attr("is_managed", is_managed.to_string()),
+    ]))
+}
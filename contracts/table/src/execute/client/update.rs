@@ -1,17 +1,25 @@
 use crate::{
+    aggregates::{load_aggregated_indices, load_indexed_value, update_aggregate},
+    changes::record_change,
+    composite::{load_affected_indices, load_key, remove_entry, save_entry},
     context::Context,
+    ensure::ensure_valid_expiration,
     error::ContractError,
-    models::DynamicContractMetadata,
+    events::TableEvent,
+    facets::{load_facet_value, load_faceted_indices, update_facet},
+    models::{ChangeKind, DynamicContractMetadata},
     msg::{IndexType, KeyValue, Relationship, RelationshipUpdates, TagUpdates, UpdateParams},
     state::{
         decrement_tag_count, ensure_allowed_by_acl, ensure_contract_not_suspended,
-        incr_decr_index_size, increment_tag_count, load_contract_id, ContractID, CustomIndexMap,
-        PartitionID, CONFIG_STR_MAX_LEN, CONTRACT_DYN_METADATA, CONTRACT_INDEX_TYPES,
-        CONTRACT_METADATA, CONTRACT_TAGS, IX_REV, IX_TAG, IX_UPDATED_AT, IX_UPDATED_BY, NOT_UNIQUE,
-        REL_ADDR_2_ID, REL_ID_2_ADDR, UNIQUE, VALUES_BINARY, VALUES_BOOL, VALUES_I32,
-        VALUES_STRING, VALUES_TIME, VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8, X,
+        incr_decr_index_size, increment_tag_count, is_case_insensitive, load_contract_id,
+        set_expiration, ContractID, CustomIndexMap, PartitionID, CONFIG_STR_MAX_LEN,
+        CONTRACT_DYN_METADATA, CONTRACT_INDEX_TYPES, CONTRACT_METADATA, CONTRACT_TAGS, IX_REV,
+        IX_TAG, IX_UPDATED_AT, IX_UPDATED_BY, NOT_UNIQUE, REL_ADDR_2_ID, REL_ID_2_ADDR, UNIQUE,
+        VALUES_BINARY, VALUES_BOOL, VALUES_I32, VALUES_STRING, VALUES_TIME, VALUES_U128,
+        VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8, X,
     },
-    util::{build_index_storage_key, pad, trim_padding},
+    subscriptions::{build_event_args, build_event_submsgs},
+    util::{build_index_storage_key, build_string_index_key, pad, trim_padding},
 };
 use cosmwasm_std::{attr, Addr, Binary, Env, Order, Response, Storage, Timestamp, Uint128, Uint64};
 use cw_storage_plus::Map;
@@ -36,6 +44,7 @@ pub fn on_execute(
 
     let initiator = params.initiator;
     let contract_id = load_contract_id(deps.storage, &contract_addr)?;
+    let event_args = build_event_args(&env, &contract_addr, contract_id, &initiator);
 
     ensure_contract_not_suspended(deps.storage, contract_id)?;
 
@@ -52,6 +61,13 @@ pub fn on_execute(
             index_updates,
             max_str_len,
         )?;
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &initiator,
+            ChangeKind::Update,
+        )?;
     }
 
     // Update tags
@@ -63,17 +79,49 @@ pub fn on_execute(
             tag_updates,
             max_str_len,
         )?;
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &initiator,
+            ChangeKind::Tags,
+        )?;
     }
 
     // Update relationships
     if let Some(rel_updates) = params.relationships.clone() {
         update_relationships(deps.storage, contract_id, rel_updates, max_str_len)?;
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &initiator,
+            ChangeKind::Relationships,
+        )?;
     }
 
-    Ok(Response::new().add_attributes(vec![attr("action", action)]))
+    // Update expiration time
+    if let Some(expires_at) = params.expires_at {
+        ensure_valid_expiration(&env, Some(expires_at))?;
+        set_expiration(deps.storage, contract_id, Some(expires_at))?;
+        record_change(
+            deps.storage,
+            &env,
+            contract_id,
+            &initiator,
+            ChangeKind::Update,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attributes(vec![attr("action", action)])
+        .add_submessages(build_event_submsgs(
+            deps.storage,
+            TableEvent::Update(event_args),
+        )?))
 }
 
-fn upsert_metadata(
+pub fn upsert_metadata(
     storage: &mut dyn Storage,
     env: &Env,
     partition: PartitionID,
@@ -123,7 +171,7 @@ fn upsert_metadata(
     Ok(())
 }
 
-fn update_tags(
+pub fn update_tags(
     storage: &mut dyn Storage,
     partition: PartitionID,
     contract_id: ContractID,
@@ -277,13 +325,45 @@ fn remove_relationship(
     Ok(())
 }
 
-fn update_indices(
+pub fn update_indices(
     storage: &mut dyn Storage,
     partition: PartitionID,
     contract_id: ContractID,
     index_updates: Vec<KeyValue>,
     max_str_len: usize,
 ) -> Result<(), ContractError> {
+    // Composite indices with a column among the updated keys must be re-keyed
+    // once all new values are in place, so get their existing keys up front.
+    let column_names: Vec<&String> = index_updates.iter().map(get_key_name).collect();
+    let composite_indices = load_affected_indices(storage, &column_names)?;
+    let mut prev_composite_keys: Vec<Option<Vec<u8>>> = Vec::with_capacity(composite_indices.len());
+
+    for meta in composite_indices.iter() {
+        prev_composite_keys.push(load_key(storage, contract_id, meta, max_str_len)?);
+    }
+
+    // Likewise, running aggregates are adjusted by the change in each value.
+    let aggregated_indices = load_aggregated_indices(storage, &column_names)?;
+    let mut prev_aggregated_values = Vec::with_capacity(aggregated_indices.len());
+
+    for meta in aggregated_indices.iter() {
+        prev_aggregated_values.push(load_indexed_value(storage, meta, partition, contract_id)?);
+    }
+
+    // ...and facet counts by the change in each value.
+    let faceted_indices = load_faceted_indices(storage, &column_names)?;
+    let mut prev_facet_values = Vec::with_capacity(faceted_indices.len());
+
+    for meta in faceted_indices.iter() {
+        prev_facet_values.push(load_facet_value(
+            storage,
+            meta,
+            partition,
+            contract_id,
+            max_str_len,
+        )?);
+    }
+
     // Update each index for the given KeyValue. If the given value is None, use
     // this as a signal to remove the existing entry, if any, from the index.
     for value in index_updates.iter() {
@@ -320,9 +400,48 @@ fn update_indices(
             },
         }
     }
+
+    // Re-key each affected composite index entry.
+    for (meta, prev_key) in composite_indices.iter().zip(prev_composite_keys) {
+        let key = load_key(storage, contract_id, meta, max_str_len)?;
+        if key != prev_key {
+            if let Some(prev_key) = prev_key {
+                remove_entry(storage, meta, partition, &prev_key, contract_id)?;
+            }
+            if let Some(key) = key {
+                save_entry(storage, meta, partition, &key, contract_id)?;
+            }
+        }
+    }
+
+    for (meta, prev_value) in aggregated_indices.iter().zip(prev_aggregated_values) {
+        let value = load_indexed_value(storage, meta, partition, contract_id)?;
+        update_aggregate(storage, &meta.name, partition, prev_value, value)?;
+    }
+
+    for (meta, prev_value) in faceted_indices.iter().zip(prev_facet_values) {
+        let value = load_facet_value(storage, meta, partition, contract_id, max_str_len)?;
+        update_facet(storage, &meta.name, partition, prev_value, value)?;
+    }
+
     Ok(())
 }
 
+pub fn get_key_name(value: &KeyValue) -> &String {
+    match value {
+        KeyValue::String(key, _)
+        | KeyValue::Bool(key, _)
+        | KeyValue::Timestamp(key, _)
+        | KeyValue::Int32(key, _)
+        | KeyValue::Uint8(key, _)
+        | KeyValue::Uint16(key, _)
+        | KeyValue::Uint32(key, _)
+        | KeyValue::Uint64(key, _)
+        | KeyValue::Uint128(key, _)
+        | KeyValue::Binary(key, _) => key,
+    }
+}
+
 fn update_string_index(
     storage: &mut dyn Storage,
     partition: PartitionID,
@@ -334,11 +453,16 @@ fn update_string_index(
     let index_storage_key = build_index_storage_key(index_name);
     let index: CustomIndexMap<&String> = Map::new(&index_storage_key);
     let indexed_value_map = VALUES_STRING;
+    let case_insensitive = is_case_insensitive(storage, index_name)?;
 
     if let Some(new_val) = maybe_value {
         let new_val = &pad(&new_val, max_str_len);
-        let index_key = (partition, new_val, contract_id);
+        let new_key = &build_string_index_key(new_val, case_insensitive, max_str_len);
+        let index_key = (partition, new_key, contract_id);
         if index.has(storage, index_key) {
+            // The indexed key is unchanged, but the original value may still
+            // differ in case if the index is case-insensitive.
+            indexed_value_map.save(storage, (contract_id, index_name), new_val)?;
             return Ok(());
         }
 
@@ -353,20 +477,22 @@ fn update_string_index(
         )?;
 
         if let Some(old_val) = maybe_old_val {
-            index.remove(storage, (partition, &old_val, contract_id));
+            let old_key = build_string_index_key(&old_val, case_insensitive, max_str_len);
+            index.remove(storage, (partition, &old_key, contract_id));
         }
 
-        index.save(storage, (partition, new_val, contract_id), &X)?;
+        index.save(storage, (partition, new_key, contract_id), &X)?;
         if !CONTRACT_INDEX_TYPES.has(storage, (contract_id, index_name)) {
             CONTRACT_INDEX_TYPES.save(storage, (contract_id, index_name), &IndexType::String)?;
         }
         incr_decr_index_size(storage, index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, index_name))? {
-        let index_key = (partition, &old_val, contract_id);
+        let old_key = build_string_index_key(&old_val, case_insensitive, max_str_len);
+        let index_key = (partition, &old_key, contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, index_name));
             incr_decr_index_size(storage, index_name, false)?;
         }
     }
@@ -414,10 +540,10 @@ fn update_bool_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, if old_val { 1u8 } else { 0u8 }, contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -466,10 +592,10 @@ fn update_timestamp_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val.nanos(), contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, (partition, old_val.nanos(), contract_id));
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -512,10 +638,10 @@ fn update_u8_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val, contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -560,10 +686,10 @@ fn update_u16_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val, contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -606,10 +732,10 @@ fn update_i32_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val, contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -652,10 +778,10 @@ fn update_u32_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val, contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -698,10 +824,10 @@ fn update_u64_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val.u64(), contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -746,10 +872,10 @@ fn update_u128_index(
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val.u128(), contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
@@ -789,15 +915,15 @@ fn update_binary_index(
 
         index.save(storage, (partition, new_val.as_slice(), contract_id), &X)?;
         if !CONTRACT_INDEX_TYPES.has(storage, (contract_id, &index_name)) {
-            CONTRACT_INDEX_TYPES.save(storage, (contract_id, &index_name), &IndexType::Uint128)?;
+            CONTRACT_INDEX_TYPES.save(storage, (contract_id, &index_name), &IndexType::Binary)?;
         }
         incr_decr_index_size(storage, &index_name, true)?;
     } else if let Some(old_val) = indexed_value_map.may_load(storage, (contract_id, &index_name))? {
         let index_key = (partition, old_val.as_slice(), contract_id);
+        indexed_value_map.remove(storage, (contract_id, index_name));
+        CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
         if index.has(storage, index_key) {
             index.remove(storage, index_key);
-            indexed_value_map.remove(storage, (contract_id, index_name));
-            CONTRACT_INDEX_TYPES.remove(storage, (contract_id, &index_name));
             incr_decr_index_size(storage, &index_name, false)?;
         }
     }
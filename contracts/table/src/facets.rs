@@ -0,0 +1,256 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Binary, Int256, Order, StdResult, Storage};
+
+use crate::{
+    aggregates::load_indexed_value,
+    error::ContractError,
+    msg::{IndexMetadata, IndexType},
+    state::{
+        has_index_entry, ContractID, PartitionID, INDEX_FACET_COUNTS, INDEX_METADATA,
+        VALUES_BINARY, VALUES_BOOL, VALUES_STRING,
+    },
+    util::{build_index_storage_key, build_string_index_key, trim_padding},
+};
+
+// Facet counts hold the number of contracts in each partition with each
+// distinct value of a custom index created with facets enabled. Values are
+// keyed like in the custom index itself, so that numeric facets sort by value:
+// strings as indexed (lowercased if the index is case-insensitive) but without
+// padding, bools as a byte, numbers and timestamps (in nanoseconds) as
+// big-endian integers of the index type's width and binary as is.
+
+/// Load the metadata of each of the given custom indices that keeps facet
+/// counts.
+pub fn load_faceted_indices(
+    storage: &dyn Storage,
+    index_names: &[&String],
+) -> Result<Vec<IndexMetadata>, ContractError> {
+    let mut indices: Vec<IndexMetadata> = Vec::with_capacity(index_names.len());
+    for index_name in index_names.iter() {
+        if let Some(meta) = INDEX_METADATA.may_load(storage, (*index_name).clone())? {
+            if meta.facet.unwrap_or(false) && !indices.iter().any(|m| m.name == meta.name) {
+                indices.push(meta);
+            }
+        }
+    }
+    Ok(indices)
+}
+
+/// Load the facet key of a contract in a custom index, provided that it has
+/// an entry in the index within the given partition.
+pub fn load_facet_value(
+    storage: &dyn Storage,
+    meta: &IndexMetadata,
+    partition: PartitionID,
+    contract_id: ContractID,
+    max_str_len: usize,
+) -> Result<Option<Vec<u8>>, ContractError> {
+    let storage_key = &build_index_storage_key(&meta.name);
+    let key = (contract_id, &meta.name);
+    Ok(match meta.index_type {
+        IndexType::String => match VALUES_STRING.may_load(storage, key)? {
+            Some(v) => {
+                let case_insensitive = meta.case_insensitive.unwrap_or(false);
+                let k = build_string_index_key(&v, case_insensitive, max_str_len);
+                has_index_entry(storage, storage_key, (partition, &k, contract_id))
+                    .then(|| trim_padding(&k).into_bytes())
+            },
+            None => None,
+        },
+        IndexType::Bool => match VALUES_BOOL.may_load(storage, key)? {
+            Some(v) => has_index_entry(storage, storage_key, (partition, v as u8, contract_id))
+                .then(|| vec![v as u8]),
+            None => None,
+        },
+        IndexType::Binary => match VALUES_BINARY.may_load(storage, key)? {
+            Some(v) => {
+                has_index_entry(storage, storage_key, (partition, v.as_slice(), contract_id))
+                    .then(|| v.to_vec())
+            },
+            None => None,
+        },
+        _ => load_indexed_value(storage, meta, partition, contract_id)?
+            .map(|v| encode_numeric_key(&meta.index_type, v)),
+    })
+}
+
+/// Width in bytes of a numeric index type's keys and whether it's signed.
+fn numeric_key_width(index_type: &IndexType) -> (usize, bool) {
+    match index_type {
+        IndexType::Int32 => (4, true),
+        IndexType::Uint8 => (1, false),
+        IndexType::Uint16 => (2, false),
+        IndexType::Uint32 => (4, false),
+        IndexType::Timestamp | IndexType::Uint64 => (8, false),
+        _ => (16, false),
+    }
+}
+
+/// Encode a numeric value the way its index type is encoded in index keys:
+/// big-endian, with the sign bit flipped for signed types.
+fn encode_numeric_key(
+    index_type: &IndexType,
+    value: Int256,
+) -> Vec<u8> {
+    let (width, is_signed) = numeric_key_width(index_type);
+    let mut key = value.to_be_bytes()[32 - width..].to_vec();
+    if is_signed {
+        key[0] ^= 0x80;
+    }
+    key
+}
+
+/// Encode a facet value, as returned by the Facets query, as its facet key.
+pub fn encode_facet_value(
+    meta: &IndexMetadata,
+    value: &str,
+) -> Result<Vec<u8>, ContractError> {
+    let invalid = || ContractError::ValidationError {
+        reason: format!("invalid value for index {}: {}", meta.name, value),
+    };
+    Ok(match meta.index_type {
+        IndexType::String => value.as_bytes().to_vec(),
+        IndexType::Bool => vec![bool::from_str(value).map_err(|_| invalid())? as u8],
+        IndexType::Binary => Binary::from_base64(value).map_err(|_| invalid())?.to_vec(),
+        _ => encode_numeric_key(
+            &meta.index_type,
+            Int256::from_str(value).map_err(|_| invalid())?,
+        ),
+    })
+}
+
+/// Decode a facet key into the value returned by the Facets query: strings as
+/// indexed, timestamps in nanoseconds and binary as base64.
+pub fn decode_facet_value(
+    meta: &IndexMetadata,
+    key: &[u8],
+) -> Result<String, ContractError> {
+    let invalid = || ContractError::UnexpectedError {
+        reason: format!("invalid facet key in index {}", meta.name),
+    };
+    Ok(match meta.index_type {
+        IndexType::String => String::from_utf8(key.to_vec()).map_err(|_| invalid())?,
+        IndexType::Bool => (key.first() == Some(&1)).to_string(),
+        IndexType::Binary => Binary::from(key).to_base64(),
+        IndexType::Int32 => {
+            let mut bytes: [u8; 4] = key.try_into().map_err(|_| invalid())?;
+            bytes[0] ^= 0x80;
+            i32::from_be_bytes(bytes).to_string()
+        },
+        _ => {
+            if key.len() > 16 {
+                return Err(invalid());
+            }
+            let mut bytes = [0u8; 16];
+            bytes[16 - key.len()..].copy_from_slice(key);
+            u128::from_be_bytes(bytes).to_string()
+        },
+    })
+}
+
+/// Apply a change in a contract's facet value to the partition's counts.
+pub fn update_facet(
+    storage: &mut dyn Storage,
+    index_name: &String,
+    partition: PartitionID,
+    prev_value: Option<Vec<u8>>,
+    value: Option<Vec<u8>>,
+) -> Result<(), ContractError> {
+    if prev_value == value {
+        return Ok(());
+    }
+    if let Some(v) = prev_value {
+        let key = (index_name, partition, v.as_slice());
+        match INDEX_FACET_COUNTS
+            .may_load(storage, key)?
+            .unwrap_or_default()
+        {
+            0 | 1 => INDEX_FACET_COUNTS.remove(storage, key),
+            n => INDEX_FACET_COUNTS.save(storage, key, &(n - 1))?,
+        }
+    }
+    if let Some(v) = value {
+        INDEX_FACET_COUNTS.update(
+            storage,
+            (index_name, partition, v.as_slice()),
+            |n| -> Result<_, ContractError> {
+                n.unwrap_or_default()
+                    .checked_add(1)
+                    .ok_or_else(|| ContractError::UnexpectedError {
+                        reason: format!("overflow incrementing facet count in {}", index_name),
+                    })
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Remove a contract's values from the facet counts of its partition. This
+/// must be called before its custom index entries are removed.
+pub fn remove_contract(
+    storage: &mut dyn Storage,
+    partition: PartitionID,
+    contract_id: ContractID,
+    index_names: &[&String],
+    max_str_len: usize,
+) -> Result<(), ContractError> {
+    for meta in load_faceted_indices(storage, index_names)?.iter() {
+        let value = load_facet_value(storage, meta, partition, contract_id, max_str_len)?;
+        update_facet(storage, &meta.name, partition, value, None)?;
+    }
+    Ok(())
+}
+
+/// Move a contract's values between partition facet counts. This must be
+/// called before its custom index entries are moved.
+pub fn move_contract(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    src: PartitionID,
+    dst: PartitionID,
+    index_names: &[&String],
+    max_str_len: usize,
+) -> Result<(), ContractError> {
+    for meta in load_faceted_indices(storage, index_names)?.iter() {
+        let value = load_facet_value(storage, meta, src, contract_id, max_str_len)?;
+        update_facet(storage, &meta.name, src, value.clone(), None)?;
+        update_facet(storage, &meta.name, dst, None, value)?;
+    }
+    Ok(())
+}
+
+/// Remove the facet counts of a deleted index from every partition.
+pub fn clear_index(
+    storage: &mut dyn Storage,
+    index_name: &String,
+) -> Result<(), ContractError> {
+    for (partition, value) in INDEX_FACET_COUNTS
+        .sub_prefix(index_name)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(PartitionID, Vec<u8>)>>>()?
+    {
+        INDEX_FACET_COUNTS.remove(storage, (index_name, partition, value.as_slice()));
+    }
+    Ok(())
+}
+
+/// Remove the facet counts of a deleted partition from every index.
+pub fn clear_partition(
+    storage: &mut dyn Storage,
+    partition: PartitionID,
+) -> Result<(), ContractError> {
+    for index_name in INDEX_METADATA
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?
+    {
+        for value in INDEX_FACET_COUNTS
+            .prefix((&index_name, partition))
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<Vec<u8>>>>()?
+        {
+            INDEX_FACET_COUNTS.remove(storage, (&index_name, partition, value.as_slice()));
+        }
+    }
+    Ok(())
+}
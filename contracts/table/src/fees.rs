@@ -0,0 +1,165 @@
+use cosmwasm_std::{to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Storage, Uint128, WasmMsg};
+use cw20::Cw20ExecuteMsg;
+use gelotto_core::models::token::{Token, TokenAmount};
+
+use crate::{
+    error::ContractError,
+    execute::client::create::apply_code_id_defaults,
+    msg::CreationParams,
+    state::{resolve_partition_id, CODE_ID_ALLOWLIST, CONFIG_CREATION_FEES, PARTITION_METADATA},
+};
+
+/// Get the fee for creating a contract with the given params, if any. The code
+/// ID's fee takes precedence over the partition's, then the table's.
+pub fn resolve_creation_fee(
+    storage: &dyn Storage,
+    params: &CreationParams,
+) -> Result<Option<TokenAmount>, ContractError> {
+    let config = match CONFIG_CREATION_FEES.may_load(storage)? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    let code_id = params.code_id.u64();
+
+    if let Some(fee) = CODE_ID_ALLOWLIST
+        .may_load(storage, code_id)?
+        .and_then(|meta| meta.creation_fee)
+    {
+        return Ok(Some(fee));
+    }
+
+    let params = apply_code_id_defaults(storage, params.clone())?;
+
+    if let Some(selector) = params.partition {
+        let partition = resolve_partition_id(storage, selector)?;
+        if let Some(fee) = PARTITION_METADATA
+            .may_load(storage, partition)?
+            .and_then(|meta| meta.policy)
+            .and_then(|policy| policy.creation_fee)
+        {
+            return Ok(Some(fee));
+        }
+    }
+
+    Ok(config.fee)
+}
+
+/// Deduct a native fee from the given funds, returning the remaining funds.
+pub fn deduct_native_fee(
+    funds: Vec<Coin>,
+    fee: &TokenAmount,
+) -> Result<Vec<Coin>, ContractError> {
+    let denom = fee
+        .token
+        .get_denom()
+        .ok_or_else(|| ContractError::InsufficientFee {
+            reason: format!(
+                "creation fee of {} {} must be paid by sending CW20 tokens",
+                fee.amount,
+                fee.token.to_key()
+            ),
+        })?;
+
+    let mut remaining = funds;
+    let coin = remaining
+        .iter_mut()
+        .find(|c| c.denom == denom && c.amount >= fee.amount)
+        .ok_or_else(|| ContractError::InsufficientFee {
+            reason: format!("creation fee of {}{} not paid", fee.amount, denom),
+        })?;
+
+    coin.amount -= fee.amount;
+    remaining.retain(|c| !c.amount.is_zero());
+
+    Ok(remaining)
+}
+
+/// Build msgs paying a fee to the configured recipients, split in proportion
+/// to their weights. Any remainder from rounding goes to the first recipient.
+pub fn build_fee_msgs(
+    storage: &dyn Storage,
+    fee: &TokenAmount,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let config = CONFIG_CREATION_FEES.load(storage)?;
+    let total_weight: u128 = config.recipients.iter().map(|r| r.weight as u128).sum();
+
+    let mut amounts: Vec<Uint128> = config
+        .recipients
+        .iter()
+        .map(|r| fee.amount.multiply_ratio(r.weight as u128, total_weight))
+        .collect();
+
+    let total_paid: Uint128 = amounts.iter().copied().sum();
+    if let Some(first) = amounts.first_mut() {
+        *first += fee.amount - total_paid;
+    }
+
+    let mut msgs: Vec<CosmosMsg> = Vec::with_capacity(amounts.len());
+
+    for (recipient, amount) in config.recipients.iter().zip(amounts) {
+        if amount.is_zero() {
+            continue;
+        }
+        msgs.push(match &fee.token {
+            Token::Denom(denom) => BankMsg::Send {
+                to_address: recipient.address.to_string(),
+                amount: vec![Coin::new(amount.u128(), denom)],
+            }
+            .into(),
+            Token::Address(cw20_addr) => WasmMsg::Execute {
+                contract_addr: cw20_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.address.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        });
+    }
+
+    Ok(msgs)
+}
+
+/// Build msgs refunding the funds and fee received for a failed creation.
+pub fn build_refund_msgs(
+    recipient: &Addr,
+    funds: Vec<Coin>,
+    fee: Option<TokenAmount>,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let mut coins: Vec<Coin> = funds.into_iter().filter(|c| !c.amount.is_zero()).collect();
+    let mut msgs: Vec<CosmosMsg> = Vec::with_capacity(2);
+
+    if let Some(TokenAmount { token, amount }) = fee.filter(|fee| !fee.amount.is_zero()) {
+        match token {
+            Token::Denom(denom) => match coins.iter_mut().find(|c| c.denom == denom) {
+                Some(coin) => coin.amount += amount,
+                None => coins.push(Coin::new(amount.u128(), denom)),
+            },
+            Token::Address(cw20_addr) => msgs.push(
+                WasmMsg::Execute {
+                    contract_addr: cw20_addr.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: recipient.to_string(),
+                        amount,
+                    })?,
+                    funds: vec![],
+                }
+                .into(),
+            ),
+        }
+    }
+
+    if !coins.is_empty() {
+        msgs.push(
+            BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: coins,
+            }
+            .into(),
+        );
+    }
+
+    Ok(msgs)
+}
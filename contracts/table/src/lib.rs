@@ -1,13 +1,24 @@
+#[cfg(not(feature = "library"))]
+mod aggregates;
+#[cfg(not(feature = "library"))]
+mod changes;
 #[cfg(feature = "library")]
 pub mod client;
+#[cfg(not(feature = "library"))]
+mod composite;
 mod context;
 #[cfg(not(feature = "library"))]
 pub mod contract;
 #[cfg(not(feature = "library"))]
 mod ensure;
 mod error;
+pub mod events;
 #[cfg(not(feature = "library"))]
 pub mod execute;
+#[cfg(not(feature = "library"))]
+mod facets;
+#[cfg(not(feature = "library"))]
+mod fees;
 pub mod lifecycle;
 pub mod models;
 pub mod msg;
@@ -15,4 +26,5 @@ pub mod msg;
 pub mod query;
 pub mod state;
 #[cfg(not(feature = "library"))]
-mod util;
+mod subscriptions;
+pub mod util;
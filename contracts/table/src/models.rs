@@ -1,8 +1,10 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Timestamp, Uint64};
+use cosmwasm_std::{Addr, Int256, Timestamp, Uint64};
+use gelotto_core::models::token::TokenAmount;
 
 use crate::{
-    msg::CreationParams,
+    events::TableEventKind,
+    msg::{BulkGroupUpdates, BulkUpdateSelector, CreationParams, KeyValue, TagUpdates},
     state::{GroupID, PartitionID},
 };
 
@@ -15,6 +17,14 @@ pub struct ContractFlag {
     pub time: Timestamp,
 }
 
+#[cw_serde]
+pub struct FlagResolution {
+    pub resolved_by: Addr,
+    pub note: Option<String>,
+    pub height: Uint64,
+    pub time: Timestamp,
+}
+
 #[cw_serde]
 pub struct ContractMetadata {
     pub id: Uint64,
@@ -43,11 +53,48 @@ pub struct ContractMetadataView {
     pub partition: PartitionID,
     pub created_at: Timestamp,
     pub updated_at: Option<Timestamp>,
+    pub expires_at: Option<Timestamp>,
     pub rev: Option<Uint64>,
     pub is_suspended: bool,
     pub details: Option<ContractMetadataViewDetails>,
 }
 
+/// A change to a contract recorded in the change log.
+#[cw_serde]
+pub struct Change {
+    pub seq: Uint64,
+    pub kind: ChangeKind,
+    pub contract: Addr,
+    pub id: Uint64,
+    /// The contract's partition as of the change.
+    pub partition: PartitionID,
+    pub initiator: Addr,
+    pub time: Timestamp,
+    pub height: Uint64,
+}
+
+#[cw_serde]
+pub enum ChangeKind {
+    Create,
+    Update,
+    Tags,
+    Relationships,
+    Groups,
+    Move { src: PartitionID, dst: PartitionID },
+    Delete,
+    Archive,
+    Restore,
+}
+
+/// Metadata of an archived contract, which is no longer indexed but can be
+/// restored.
+#[cw_serde]
+pub struct ContractArchive {
+    pub archived_at: Timestamp,
+    pub archived_at_height: Uint64,
+    pub archived_by: Addr,
+}
+
 #[cw_serde]
 pub struct DynamicContractMetadata {
     pub rev: Uint64,
@@ -59,11 +106,66 @@ pub struct DynamicContractMetadata {
 #[cw_serde]
 pub enum ReplyJob {
     Create {
-        params: CreationParams,
+        params: Box<CreationParams>,
         initiator: Addr,
+        best_effort: Option<bool>,
+        sender: Option<Addr>,
+        fee: Option<TokenAmount>,
+    },
+    Migrate {
+        contract_id: Uint64,
+        code_id: Uint64,
     },
 }
 
+#[cw_serde]
+pub struct MigrationFailure {
+    pub code_id: Uint64,
+    pub error: String,
+    pub height: Uint64,
+    pub time: Timestamp,
+}
+
+/// An in-progress bulk update, whose selector holds the position of the next
+/// batch of contracts to update.
+#[cw_serde]
+pub struct BulkUpdateJob {
+    pub selector: BulkUpdateSelector,
+    pub initiator: Addr,
+    pub values: Option<Vec<KeyValue>>,
+    pub tags: Option<TagUpdates>,
+    pub groups: Option<BulkGroupUpdates>,
+    pub n_updated: Uint64,
+    pub created_by: Addr,
+    pub created_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct Subscription {
+    pub kinds: Vec<TableEventKind>,
+    pub partitions: Option<Vec<PartitionID>>,
+    pub groups: Option<Vec<GroupID>>,
+    pub code_ids: Option<Vec<Uint64>>,
+    pub gas_limit: u64,
+    pub created_at: Timestamp,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct IndexAggregate {
+    pub count: Uint64,
+    pub sum: Int256,
+}
+
+#[cw_serde]
+pub struct CreationFailure {
+    pub code_id: Uint64,
+    pub initiator: Addr,
+    pub error: String,
+    pub height: Uint64,
+    pub time: Timestamp,
+}
+
 #[cw_serde]
 pub enum Details {
     Basic,
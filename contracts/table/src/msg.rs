@@ -1,10 +1,17 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, Binary, Timestamp, Uint128, Uint64};
-use gelotto_core::models::owner::Owner;
+use cosmwasm_std::{
+    Addr, Api, Binary, Coin, Int256, SignedDecimal256, Timestamp, Uint128, Uint64,
+};
+use cw20::Cw20ReceiveMsg;
+use gelotto_core::models::{owner::Owner, token::TokenAmount};
 
 use crate::{
     error::ContractError,
-    models::{ContractMetadataView, Details},
+    events::TableEventKind,
+    models::{
+        Change, ContractArchive, ContractFlag, ContractMetadataView, CreationFailure, Details,
+        FlagResolution,
+    },
     state::{GroupID, PartitionID},
 };
 
@@ -17,29 +24,53 @@ pub struct InstantiateMsg {
     pub partitions: Option<Vec<PartitionCreationParams>>,
     pub groups: Option<Vec<GroupCreationParams>>,
     pub indices: Option<Vec<IndexCreationParams>>,
+    pub composite_indices: Option<Vec<CompositeIndexCreationParams>>,
 }
 
 #[cw_serde]
 pub enum AdminMsg {
     CreateGroup(GroupCreationParams),
+    UpdateGroup(GroupUpdateParams),
+    DeleteGroup(GroupDeletionParams),
+    MergeGroups(GroupMergeParams),
     CreatePartition(PartitionCreationParams),
+    UpdatePartition(PartitionUpdateParams),
+    DeletePartition(PartitionSelector),
+    MovePartition(PartitionMoveParams),
     CreateIndex(IndexCreationParams),
+    CreateCompositeIndex(CompositeIndexCreationParams),
     UpdateInfo(TableInfo),
     SetPartition(Addr, PartitionSelector),
     AssignGroups(GroupUpdates),
     UpdateConfig(Config),
     RevertConfig(),
     Unsuspend(Addr),
+    Restore(RestoreParams),
     DeleteIndex(String),
+    RebuildIndex(IndexRebuildParams),
+    ResolveFlags(FlagResolutionParams),
+    AllowCodeId(CodeIdAllowanceParams),
+    DisallowCodeId(Uint64),
+    Adopt(AdoptionParams),
+    BulkUpdate(Box<BulkUpdateParams>),
+    ResumeBulkUpdate(Option<u32>),
+    CancelBulkUpdate(),
+    MigrateContracts(MigrationParams),
+    ReleaseAdmin(AdminReleaseParams),
+    Subscribe(SubscriptionParams),
+    Unsubscribe(Addr),
     SetOwner(Owner),
 }
 
 #[cw_serde]
 pub enum ClientMsg {
     Create(CreationParams),
+    CreateMany(BatchCreationParams),
     Update(UpdateParams),
     Delete(Addr),
     Flag(FlagParams),
+    SyncAdmin(Addr),
+    Sweep(SweepParams),
 }
 
 #[cw_serde]
@@ -49,6 +80,87 @@ pub struct GroupUpdates {
     pub add: Option<Vec<GroupID>>,
 }
 
+/// Updates to apply to every contract matched by the selector. Contracts are
+/// updated in batches, with the first batch processed immediately and the
+/// rest through ResumeBulkUpdate.
+#[cw_serde]
+pub struct BulkUpdateParams {
+    pub selector: BulkUpdateSelector,
+    pub initiator: Option<Addr>,
+    pub values: Option<Vec<KeyValue>>,
+    pub tags: Option<TagUpdates>,
+    pub groups: Option<BulkGroupUpdates>,
+    pub limit: Option<u32>,
+}
+
+/// Contracts targeted by a bulk update. Query-based selectors are paginated
+/// using their own cursor, ignoring limit, details and select. A Range can't
+/// select by an index whose keys the update's values would change.
+#[cw_serde]
+pub enum BulkUpdateSelector {
+    Addresses(Vec<Addr>),
+    Tag(TagQueryParams),
+    Group(GroupQueryParams),
+    Range(RangeQueryParams),
+}
+
+/// Migrate a page of managed contracts to a new code ID. When more contracts
+/// remain, the response's cursor attribute resumes from where this one stopped.
+#[cw_serde]
+pub struct MigrationParams {
+    pub contracts: MigrationSelector,
+    pub code_id: Uint64,
+    pub msg: Binary,
+    pub cursor: Option<Uint64>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub enum MigrationSelector {
+    CodeId(Uint64),
+    Group(GroupID),
+    Addresses(Vec<Addr>),
+}
+
+/// Give up the table's wasm admin rights over a managed contract, either
+/// transferring them to a new admin or clearing the admin altogether.
+#[cw_serde]
+pub struct AdminReleaseParams {
+    pub contract: Addr,
+    pub new_admin: Option<Addr>,
+}
+
+/// Register a contract to receive TableEvents of the given kinds. Each filter
+/// that is set must match a contract for its events to be sent.
+#[cw_serde]
+pub struct SubscriptionParams {
+    pub subscriber: Addr,
+    pub kinds: Vec<TableEventKind>,
+    pub partitions: Option<Vec<PartitionID>>,
+    pub groups: Option<Vec<GroupID>>,
+    pub code_ids: Option<Vec<Uint64>>,
+    /// Gas the subscriber may use to handle each event, up to 1,000,000.
+    pub gas_limit: u64,
+}
+
+#[cw_serde]
+pub struct BulkGroupUpdates {
+    pub remove: Option<Vec<GroupID>>,
+    pub add: Option<Vec<GroupID>>,
+}
+
+#[cw_serde]
+pub struct FlagResolutionParams {
+    pub contract: Addr,
+    pub note: Option<String>,
+    /// Delete the contract's flag history instead of marking its open flags
+    /// as resolved.
+    pub clear: Option<bool>,
+    /// Max number of flags to delete when clearing, newest first. Clearing
+    /// resumes with the next call until no flags remain.
+    pub limit: Option<u32>,
+}
+
 #[cw_serde]
 pub struct FlagParams {
     pub contract: Addr,
@@ -61,23 +173,39 @@ pub struct FlagParams {
 pub enum ExecuteMsg {
     Admin(AdminMsg),
     Client(ClientMsg),
+    Receive(Cw20ReceiveMsg),
+}
+
+/// Hook msgs sent with CW20 tokens, which pay the creation fee.
+#[cw_serde]
+pub enum ReceiveMsg {
+    Create(CreationParams),
 }
 
 #[cw_serde]
 pub enum ContractsQueryMsg {
     Range(RangeQueryParams),
     WithTag(TagQueryParams),
+    WithTagMatch(TagMatchQueryParams),
     InGroup(GroupQueryParams),
     ByAddresses(AddressesQueryParams),
     RelatedTo(RelationshipQueryParams),
+    Where(WhereQueryParams),
 }
 
 #[cw_serde]
 pub enum TableQueryMsg {
     Indices(TableIndicesQueryParams),
+    CompositeIndices(TableIndicesQueryParams),
     Partitions(TablePartitionsQueryParams),
     Groups(TableGroupsQueryParams),
     Tags(TableTagsQueryParams),
+    Flagged(TableFlaggedQueryParams),
+    CodeIds(TableCodeIdsQueryParams),
+    Aggregate(TableAggregateQueryParams),
+    Facets(TableFacetsQueryParams),
+    Changes(TableChangesQueryParams),
+    CreationFailures(TableCreationFailuresQueryParams),
 }
 
 #[cw_serde]
@@ -86,6 +214,9 @@ pub enum ContractQueryMsg {
     Groups(ContractGroupsQueryParams),
     Tags(ContractTagsQueryParams),
     IsRelatedTo(ContractIsRelatedToParams),
+    Values(ContractValuesQueryParams),
+    Flags(ContractFlagsQueryParams),
+    Archived(ContractArchivedQueryParams),
 }
 
 #[cw_serde]
@@ -109,6 +240,12 @@ pub struct IndicesResponse {
     pub cursor: Option<String>,
 }
 
+#[cw_serde]
+pub struct CompositeIndicesResponse {
+    pub indices: Vec<CompositeIndexMetadata>,
+    pub cursor: Option<String>,
+}
+
 #[cw_serde]
 pub struct ContractIsRelatedToResponse {
     pub is_related: bool,
@@ -132,12 +269,35 @@ pub struct TagCount {
     pub count: u32,
 }
 
+#[cw_serde]
+pub struct FacetsResponse {
+    pub facets: Vec<FacetCount>,
+    pub cursor: Option<String>,
+}
+
+#[cw_serde]
+pub struct ChangesResponse {
+    pub changes: Vec<Change>,
+    pub cursor: Option<Uint64>,
+    /// Sequence number of the latest change. Clients are in sync once they've
+    /// read up to it. If the first change returned doesn't directly follow
+    /// since_seq, the changes in between were pruned.
+    pub latest_seq: Uint64,
+}
+
+#[cw_serde]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u32,
+}
+
 #[cw_serde]
 pub struct PartitionView {
     pub id: PartitionID,
     pub size: Uint64,
     pub description: Option<String>,
     pub name: String,
+    pub policy: Option<PartitionPolicy>,
 }
 
 #[cw_serde]
@@ -150,6 +310,7 @@ pub struct PartitionsResponse {
 pub struct ContractRecord {
     pub address: Addr,
     pub meta: Option<ContractMetadataView>,
+    pub values: Option<Vec<KeyValue>>,
 }
 
 #[cw_serde]
@@ -170,12 +331,24 @@ pub struct ContractsByTagResponse {
     pub cursor: Option<Uint64>,
 }
 
+#[cw_serde]
+pub struct ContractsByTagMatchResponse {
+    pub contracts: Vec<ContractRecord>,
+    pub cursor: Option<Cursor>,
+}
+
 #[cw_serde]
 pub struct ContractsByGroupResponse {
     pub contracts: Vec<ContractRecord>,
     pub cursor: Option<Uint64>,
 }
 
+#[cw_serde]
+pub struct ContractsWhereResponse {
+    pub contracts: Vec<ContractRecord>,
+    pub cursor: Option<WhereCursor>,
+}
+
 #[cw_serde]
 pub struct FullRelationship {
     pub contract: Addr,
@@ -220,6 +393,82 @@ pub struct ContractGroupsResponse {
     pub cursor: Option<GroupID>,
 }
 
+#[cw_serde]
+pub struct ContractFlagRecord {
+    pub position: u32,
+    pub flag: ContractFlag,
+    pub resolution: Option<FlagResolution>,
+}
+
+#[cw_serde]
+pub struct ContractFlagsResponse {
+    pub flags: Vec<ContractFlagRecord>,
+    pub n_open: u32,
+    pub cursor: Option<u32>,
+}
+
+#[cw_serde]
+pub struct FlaggedContract {
+    pub address: Addr,
+    pub n_open: u32,
+    pub n_total: u32,
+}
+
+#[cw_serde]
+pub struct CodeIdView {
+    pub code_id: Uint64,
+    pub metadata: CodeIdMetadata,
+    pub size: Uint64,
+}
+
+#[cw_serde]
+pub struct CodeIdsResponse {
+    pub code_ids: Vec<CodeIdView>,
+    pub cursor: Option<Uint64>,
+}
+
+#[cw_serde]
+pub struct CreationFailureView {
+    pub job_id: Uint64,
+    pub failure: CreationFailure,
+}
+
+#[cw_serde]
+pub struct CreationFailuresResponse {
+    pub failures: Vec<CreationFailureView>,
+    pub cursor: Option<Uint64>,
+}
+
+#[cw_serde]
+pub struct AggregateResponse {
+    pub count: Uint64,
+    pub sum: Int256,
+    pub min: Option<IndexValue>,
+    pub max: Option<IndexValue>,
+    pub avg: Option<SignedDecimal256>,
+}
+
+#[cw_serde]
+pub struct FlaggedResponse {
+    pub contracts: Vec<FlaggedContract>,
+    pub cursor: Option<Uint64>,
+}
+
+/// An archived contract's record, as it was when archived. Its tags,
+/// relationships, groups, values and flags can be paginated through the other
+/// contract queries.
+#[cw_serde]
+pub struct ContractArchivedResponse {
+    pub record: ContractRecord,
+    pub archive: ContractArchive,
+}
+
+#[cw_serde]
+pub struct ContractValuesResponse {
+    pub values: Vec<KeyValue>,
+    pub cursor: Option<String>,
+}
+
 #[cw_serde]
 pub struct ContractTagsResponse {
     pub tags: Vec<String>,
@@ -240,11 +489,39 @@ pub struct CreationParams {
     pub admin: Option<Addr>,
     // Internal contract params
     pub initiator: Option<Addr>,
-    pub partition: PartitionSelector,
+    pub partition: Option<PartitionSelector>,
     pub label: Option<String>,
     pub groups: Option<Vec<GroupID>>,
     pub tags: Option<Vec<String>>,
     pub use_lifecycle_hooks: Option<bool>,
+    /// Funds to send with the instantiation when part of a CreateMany batch.
+    /// Create always sends all funds received, so it must be left unset.
+    pub funds: Option<Vec<Coin>>,
+    /// Time after which the contract is excluded from queries by default and
+    /// can be swept from the table.
+    pub expires_at: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct BatchCreationParams {
+    pub entries: Vec<CreationParams>,
+    /// Record failed instantiations instead of reverting the whole batch.
+    pub best_effort: Option<bool>,
+}
+
+/// Params for registering a contract instantiated outside of the table. Unset
+/// params default to those registered for the contract's code ID, if any.
+#[cw_serde]
+pub struct AdoptionParams {
+    pub contract: Addr,
+    pub code_id: Uint64,
+    pub initiator: Option<Addr>,
+    pub partition: Option<PartitionSelector>,
+    pub groups: Option<Vec<GroupID>>,
+    pub tags: Option<Vec<String>>,
+    pub use_lifecycle_hooks: Option<bool>,
+    /// Execute the contract's Setup lifecycle hook, if it uses hooks.
+    pub setup: Option<bool>,
 }
 
 #[cw_serde]
@@ -260,6 +537,26 @@ pub struct UpdateParams {
     pub values: Option<Vec<KeyValue>>,
     pub tags: Option<TagUpdates>,
     pub relationships: Option<RelationshipUpdates>,
+    /// Set a new expiration time, replacing any existing one.
+    pub expires_at: Option<Timestamp>,
+}
+
+/// Restore an archived contract, re-indexing it in its original partition or
+/// the given one.
+#[cw_serde]
+pub struct RestoreParams {
+    pub contract: Addr,
+    pub partition: Option<PartitionSelector>,
+    /// New expiration time. Otherwise the contract keeps its expiration time
+    /// unless it has already passed, in which case it's cleared.
+    pub expires_at: Option<Timestamp>,
+}
+
+/// Delete up to `limit` expired contracts, in order of expiration. Contracts
+/// are archived instead if the table archives on delete.
+#[cw_serde]
+pub struct SweepParams {
+    pub limit: Option<u32>,
 }
 
 #[cw_serde]
@@ -323,6 +620,7 @@ pub enum RangeSelector {
     Uint64(String),
     Uint128(String),
     Binary(String),
+    Composite(String),
 }
 
 #[cw_serde]
@@ -350,6 +648,25 @@ pub struct PartitionMetadata {
     pub name: String,
     pub description: Option<String>,
     pub created_at: Timestamp,
+    pub policy: Option<PartitionPolicy>,
+}
+
+/// Limits on which contracts can be created in a partition, and by whom. Each
+/// limit applies only when set.
+#[cw_serde]
+#[derive(Default)]
+pub struct PartitionPolicy {
+    /// Max number of contracts in the partition.
+    pub max_size: Option<Uint64>,
+    /// Code IDs that can be instantiated in the partition.
+    pub code_ids: Option<Vec<Uint64>>,
+    /// Max number of contracts in the partition with the same initiator.
+    pub max_per_initiator: Option<u32>,
+    /// Can contracts already in the table create contracts in the partition?
+    /// Defaults to true.
+    pub allow_child_creation: Option<bool>,
+    /// Fee for creating a contract in the partition.
+    pub creation_fee: Option<TokenAmount>,
 }
 
 #[cw_serde]
@@ -363,6 +680,36 @@ pub struct IndexMetadata {
     pub index_type: IndexType,
     pub name: String,
     pub size: Uint64,
+    pub case_insensitive: Option<bool>,
+    pub status: Option<IndexStatus>,
+    pub aggregate: Option<bool>,
+    pub facet: Option<bool>,
+}
+
+#[cw_serde]
+pub enum IndexStatus {
+    Ready,
+    /// The index is being rebuilt from stored values. The cursor is the last
+    /// contract ID scanned so far.
+    Building {
+        cursor: Option<Uint64>,
+        scanned: Uint64,
+    },
+}
+
+#[cw_serde]
+pub struct CompositeIndexColumn {
+    pub name: String,
+    pub index_type: IndexType,
+    pub case_insensitive: Option<bool>,
+}
+
+#[cw_serde]
+pub struct CompositeIndexMetadata {
+    pub name: String,
+    pub columns: Vec<CompositeIndexColumn>,
+    pub size: Uint64,
+    pub status: Option<IndexStatus>,
 }
 #[cw_serde]
 pub struct TagUpdate {
@@ -388,6 +735,58 @@ pub struct Config {
     pub owner: Owner,
     pub code_id_allowlist_enabled: bool,
     pub max_str_len: u16,
+    pub auto_suspension: Option<AutoSuspensionConfig>,
+    pub creation_fees: Option<CreationFeeConfig>,
+    /// Archive deleted contracts instead of erasing them, so that they can be
+    /// restored later.
+    pub archive_on_delete: Option<bool>,
+    /// Limits on the changes kept in the change log. All are kept if not set.
+    pub change_retention: Option<ChangeRetention>,
+}
+
+/// Changes are pruned from the change log, oldest first, once either limit is
+/// exceeded. Pruning happens a few changes at a time as new ones are recorded.
+#[cw_serde]
+pub struct ChangeRetention {
+    /// Max age of a change, in seconds.
+    pub max_age: Option<u64>,
+    /// Max number of changes.
+    pub max_size: Option<Uint64>,
+}
+
+/// Fees charged for creating a contract. The fee set for the contract's code
+/// ID takes precedence over its partition's, which takes precedence over the
+/// table-wide fee. No fees are charged unless this config is set.
+#[cw_serde]
+pub struct CreationFeeConfig {
+    /// Table-wide fee.
+    pub fee: Option<TokenAmount>,
+    /// Fees are split among recipients in proportion to their weights.
+    pub recipients: Vec<FeeRecipient>,
+}
+
+#[cw_serde]
+pub struct FeeRecipient {
+    pub address: Addr,
+    pub weight: u32,
+}
+
+/// Community moderation settings. When set, anyone may flag a contract, and a
+/// contract is suspended once the combined weight of its open flags, counting
+/// at most one flag per sender, reaches the threshold.
+#[cw_serde]
+pub struct AutoSuspensionConfig {
+    pub threshold: u32,
+    /// Only flags made within this many seconds count toward the threshold.
+    pub window: Option<u64>,
+    /// Weights of flags by reason code. Other flags have a weight of 1.
+    pub weights: Option<Vec<FlagCodeWeight>>,
+}
+
+#[cw_serde]
+pub struct FlagCodeWeight {
+    pub code: u32,
+    pub weight: u32,
 }
 
 #[cw_serde]
@@ -399,6 +798,33 @@ pub struct GroupMetadata {
     pub size: Uint64,
 }
 
+/// Creation params for adding a code ID to the allowlist. Each default applies
+/// to contracts created from the code ID that don't specify their own value.
+#[cw_serde]
+pub struct CodeIdAllowanceParams {
+    pub code_id: Uint64,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub use_lifecycle_hooks: Option<bool>,
+    pub partition: Option<PartitionSelector>,
+    pub groups: Option<Vec<GroupID>>,
+    pub tags: Option<Vec<String>>,
+    pub creation_fee: Option<TokenAmount>,
+}
+
+#[cw_serde]
+pub struct CodeIdMetadata {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub use_lifecycle_hooks: Option<bool>,
+    pub partition: Option<PartitionID>,
+    pub groups: Option<Vec<GroupID>>,
+    pub tags: Option<Vec<String>>,
+    pub creation_fee: Option<TokenAmount>,
+    pub created_by: Addr,
+    pub created_at: Timestamp,
+}
+
 #[cw_serde]
 pub struct GroupMetadataView {
     pub id: GroupID,
@@ -414,11 +840,112 @@ pub struct GroupCreationParams {
     pub description: Option<String>,
 }
 
+#[cw_serde]
+pub struct GroupUpdateParams {
+    pub group: GroupID,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Remove up to `limit` contracts from a group per call. The group itself is
+/// removed once it's empty, so the same message is resent until done.
+#[cw_serde]
+pub struct GroupDeletionParams {
+    pub group: GroupID,
+    pub limit: Option<u32>,
+}
+
+/// Move up to `limit` contracts from the src group into the dst group per
+/// call. The src group is removed once it's empty.
+#[cw_serde]
+pub struct GroupMergeParams {
+    pub src: GroupID,
+    pub dst: GroupID,
+    pub limit: Option<u32>,
+}
+
+impl IndexType {
+    /// Can values of this type be summed and averaged?
+    pub fn is_numeric(&self) -> bool {
+        !matches!(self, IndexType::String | IndexType::Bool | IndexType::Binary)
+    }
+}
+
 impl Config {
     pub fn validate(&self, api: &dyn Api) -> Result<(), ContractError> {
         api.addr_validate(self.owner.to_addr().as_str())?;
+        if let Some(auto_suspension) = &self.auto_suspension {
+            auto_suspension.validate()?;
+        }
+        if let Some(creation_fees) = &self.creation_fees {
+            creation_fees.validate(api)?;
+        }
+        if let Some(change_retention) = &self.change_retention {
+            change_retention.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl ChangeRetention {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if self.max_age == Some(0) || self.max_size == Some(Uint64::zero()) {
+            return Err(ContractError::ValidationError {
+                reason: "change retention limits must be greater than 0".to_owned(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl CreationFeeConfig {
+    pub fn validate(&self, api: &dyn Api) -> Result<(), ContractError> {
+        if self.recipients.is_empty() {
+            return Err(ContractError::ValidationError {
+                reason: "creation fees require at least one recipient".to_owned(),
+            });
+        }
+        for recipient in self.recipients.iter() {
+            api.addr_validate(recipient.address.as_str())?;
+            if recipient.weight == 0 {
+                return Err(ContractError::ValidationError {
+                    reason: format!("fee recipient {} must have a weight", recipient.address),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AutoSuspensionConfig {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if self.threshold == 0 {
+            return Err(ContractError::ValidationError {
+                reason: "auto-suspension threshold must be greater than 0".to_owned(),
+            });
+        }
+        if let Some(weights) = &self.weights {
+            for (i, w) in weights.iter().enumerate() {
+                if weights[..i].iter().any(|prev| prev.code == w.code) {
+                    return Err(ContractError::ValidationError {
+                        reason: format!("duplicate weight for flag code {}", w.code),
+                    });
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Get the weight of a flag with the given reason code.
+    pub fn get_weight(&self, code: Option<u32>) -> u32 {
+        code.and_then(|code| {
+            self.weights
+                .as_ref()
+                .and_then(|weights| weights.iter().find(|w| w.code == code))
+                .map(|w| w.weight)
+        })
+        .unwrap_or(1)
+    }
 }
 
 #[cw_serde]
@@ -436,6 +963,33 @@ pub struct ContractGroupsQueryParams {
     pub partition: PartitionID,
 }
 
+#[cw_serde]
+pub struct ContractFlagsQueryParams {
+    pub contract: Addr,
+    /// Only return flags that haven't been resolved.
+    pub open: Option<bool>,
+    pub cursor: Option<u32>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct ContractArchivedQueryParams {
+    pub contract: Addr,
+    /// Names of custom indices whose values to include in the record.
+    pub select: Option<Vec<String>>,
+}
+
+#[cw_serde]
+pub struct ContractValuesQueryParams {
+    pub contract: Addr,
+    /// Only return values for these custom indices.
+    pub names: Option<Vec<String>>,
+    pub cursor: Option<String>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+}
+
 #[cw_serde]
 pub struct ContractTagsQueryParams {
     pub contract: Addr,
@@ -462,7 +1016,14 @@ pub struct GroupQueryParams {
     pub desc: Option<bool>,
     pub limit: Option<u32>,
     pub partition: PartitionID,
+    /// Groups span partitions, so members in every partition are returned
+    /// unless only some partitions are given.
+    pub partitions: Option<PartitionSet>,
     pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
 }
 
 #[cw_serde]
@@ -473,6 +1034,10 @@ pub struct AddressesQueryParams {
     pub limit: Option<u32>,
     pub partition: PartitionID,
     pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
 }
 
 #[cw_serde]
@@ -483,7 +1048,80 @@ pub struct TagQueryParams {
     pub desc: Option<bool>,
     pub limit: Option<u32>,
     pub partition: PartitionID,
+    pub partitions: Option<PartitionSet>,
     pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
+}
+
+/// Like TagQueryParams, but matching tags equal to, between or starting with
+/// the given target.
+#[cw_serde]
+pub struct TagMatchQueryParams {
+    pub tag: Target,
+    pub cursor: Option<Cursor>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+    pub partition: PartitionID,
+    pub partitions: Option<PartitionSet>,
+    pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
+}
+
+#[cw_serde]
+pub struct TableCodeIdsQueryParams {
+    pub cursor: Option<Uint64>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct TableCreationFailuresQueryParams {
+    /// Job ID of the last failure in the previous page.
+    pub cursor: Option<Uint64>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct TableAggregateQueryParams {
+    /// Name of a numeric custom index.
+    pub index: String,
+    /// Partition to aggregate, or all partitions if None.
+    pub partition: Option<PartitionID>,
+    /// Inclusive bounds on the values to aggregate. Timestamps are given in
+    /// nanoseconds.
+    pub range: Option<Range>,
+}
+
+#[cw_serde]
+pub struct TableFlaggedQueryParams {
+    pub cursor: Option<Uint64>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct TableChangesQueryParams {
+    /// Return changes after this sequence number, or from the earliest one
+    /// retained if not set.
+    pub since_seq: Option<Uint64>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct TableFacetsQueryParams {
+    /// Name of a custom index created with facets enabled.
+    pub index: String,
+    pub partition: PartitionID,
+    pub cursor: Option<String>,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
 }
 
 #[cw_serde]
@@ -535,33 +1173,138 @@ pub struct RelationshipQueryParams {
     pub limit: Option<u32>,
     pub partition: PartitionID,
     pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
 }
 
 #[cw_serde]
 pub struct PartitionCreationParams {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub policy: Option<PartitionPolicy>,
+}
+
+#[cw_serde]
+pub struct PartitionUpdateParams {
+    pub partition: PartitionSelector,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Replaces the partition's existing policy, if any.
+    pub policy: Option<PartitionPolicy>,
+}
+
+/// Move up to `limit` contracts from the src partition to the dst partition
+/// per call, so the same message is resent until the src partition is empty.
+#[cw_serde]
+pub struct PartitionMoveParams {
+    pub src: PartitionSelector,
+    pub dst: PartitionSelector,
+    pub limit: Option<u32>,
 }
 
 #[cw_serde]
 pub struct IndexCreationParams {
     pub index_type: IndexType,
     pub name: String,
+    /// String indices only. Values are indexed and queried in lowercase, while
+    /// the original value is kept for display.
+    pub case_insensitive: Option<bool>,
+    /// Numeric indices only. Keep a running count and sum of each partition's
+    /// values, so that aggregate queries needn't scan the index.
+    pub aggregate: Option<bool>,
+    /// Keep a count of each partition's contracts per distinct value. Meant
+    /// for low-cardinality indices.
+    pub facet: Option<bool>,
+}
+
+#[cw_serde]
+pub struct IndexRebuildParams {
+    pub name: String,
+    /// Max number of contracts to scan in this batch.
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct CompositeIndexCreationParams {
+    pub name: String,
+    pub columns: Vec<String>,
 }
 
 #[cw_serde]
 pub enum Target {
     Equals(String),
     Between(Range),
+    StartsWith(String),
+}
+
+/// Partitions to query instead of a single partition. Results from each
+/// partition are merged in index order, and the returned cursor resumes every
+/// partition from the last entry returned.
+#[cw_serde]
+pub enum PartitionSet {
+    All,
+    Only(Vec<PartitionID>),
 }
 
 #[cw_serde]
 pub struct RangeQueryParams {
     pub index: RangeSelector,
     pub partition: PartitionID,
+    pub partitions: Option<PartitionSet>,
+    pub prefix: Option<Vec<String>>,
     pub params: Target,
     pub desc: Option<bool>,
     pub limit: Option<u32>,
     pub cursor: Option<Cursor>,
     pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
+}
+
+#[cw_serde]
+pub struct RangeFilter {
+    pub index: RangeSelector,
+    pub prefix: Option<Vec<String>>,
+    pub params: Target,
+}
+
+#[cw_serde]
+pub struct RelationshipFilter {
+    pub name: String,
+    pub address: Option<Addr>,
+}
+
+#[cw_serde]
+pub enum Expression {
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+    Not(Box<Expression>),
+    Range(RangeFilter),
+    Tag(String),
+    Group(GroupID),
+    RelatedTo(RelationshipFilter),
+}
+
+#[cw_serde]
+pub struct WhereCursor {
+    pub driver: Option<u32>,
+    pub key: Cursor,
+}
+
+#[cw_serde]
+pub struct WhereQueryParams {
+    pub partition: PartitionID,
+    pub filter: Expression,
+    pub desc: Option<bool>,
+    pub limit: Option<u32>,
+    pub cursor: Option<WhereCursor>,
+    pub details: Option<Details>,
+    /// Names of custom indices whose values to include in each record.
+    pub select: Option<Vec<String>>,
+    /// Include expired contracts, which are excluded by default.
+    pub include_expired: Option<bool>,
 }
@@ -0,0 +1,27 @@
+use crate::error::ContractError;
+use crate::models::Details;
+use crate::msg::{ContractArchivedQueryParams, ContractArchivedResponse};
+use crate::state::{load_one_contract_record, ARCHIVED_ADDR_2_ID, CONTRACT_ARCHIVES};
+use cosmwasm_std::Deps;
+
+/// Load an archived contract's record, with full metadata.
+pub fn query_archived(
+    deps: Deps,
+    params: ContractArchivedQueryParams,
+) -> Result<ContractArchivedResponse, ContractError> {
+    let contract_id = ARCHIVED_ADDR_2_ID
+        .may_load(deps.storage, &params.contract)?
+        .ok_or_else(|| ContractError::ContractNotFound {
+            reason: format!("contract {} is not archived", params.contract),
+        })?;
+
+    Ok(ContractArchivedResponse {
+        record: load_one_contract_record(
+            deps.storage,
+            contract_id,
+            Some(Details::Full),
+            params.select.as_deref(),
+        )?,
+        archive: CONTRACT_ARCHIVES.load(deps.storage, contract_id)?,
+    })
+}
@@ -0,0 +1,84 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::models::ContractFlag;
+use crate::msg::{ContractFlagRecord, ContractFlagsQueryParams, ContractFlagsResponse};
+use crate::state::{load_any_contract_id, CONTRACT_FLAG_RESOLUTIONS, CONTRACT_OPEN_FLAG_COUNTS};
+use crate::util::build_flags_storage_key;
+use cosmwasm_std::{Deps, Order};
+use cw_storage_plus::{Bound, Deque};
+
+/// Paginate over a contract's flags by position, oldest first, along with the
+/// resolution of each resolved flag.
+pub fn query_flags(
+    deps: Deps,
+    params: ContractFlagsQueryParams,
+) -> Result<ContractFlagsResponse, ContractError> {
+    let contract_id = load_any_contract_id(deps.storage, &params.contract)?;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
+    let desc = params.desc.unwrap_or(false);
+
+    let flags_deque_key = build_flags_storage_key(contract_id);
+    let flags: Deque<ContractFlag> = Deque::new(flags_deque_key.as_str());
+    let n_total = flags.len(deps.storage)?;
+    let n_open = CONTRACT_OPEN_FLAG_COUNTS
+        .may_load(deps.storage, contract_id)?
+        .unwrap_or_default();
+
+    // Open flags are the last n_open flags in the Deque.
+    let first_position = if params.open.unwrap_or(false) {
+        n_total.saturating_sub(n_open)
+    } else {
+        0
+    };
+
+    let positions: Vec<u32> = if desc {
+        let stop = params.cursor.unwrap_or(n_total).min(n_total);
+        (first_position..stop).rev().take(limit).collect()
+    } else {
+        let start = params
+            .cursor
+            .map(|n| n.saturating_add(1))
+            .unwrap_or_default()
+            .max(first_position);
+        (start..n_total).take(limit).collect()
+    };
+
+    let mut records: Vec<ContractFlagRecord> = Vec::with_capacity(positions.len());
+
+    for position in positions.iter() {
+        if let Some(flag) = flags.get(deps.storage, *position)? {
+            // A flag is resolved by the first resolution made after it.
+            let resolution = match CONTRACT_FLAG_RESOLUTIONS
+                .prefix(contract_id)
+                .range(
+                    deps.storage,
+                    Some(Bound::Exclusive((*position, PhantomData))),
+                    None,
+                    Order::Ascending,
+                )
+                .next()
+            {
+                Some(result) => Some(result?.1),
+                None => None,
+            };
+            records.push(ContractFlagRecord {
+                position: *position,
+                flag,
+                resolution,
+            });
+        }
+    }
+
+    let cursor = if records.len() == limit {
+        records.last().map(|r| r.position)
+    } else {
+        None
+    };
+
+    Ok(ContractFlagsResponse {
+        flags: records,
+        n_open,
+        cursor,
+    })
+}
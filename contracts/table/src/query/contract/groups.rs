@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use crate::error::ContractError;
 use crate::msg::{ContractGroupsQueryParams, ContractGroupsResponse, GroupMetadataView};
-use crate::state::{load_contract_id, GroupID, CONTRACT_GROUP_IDS, GROUP_METADATA};
+use crate::state::{load_any_contract_id, GroupID, CONTRACT_GROUP_IDS, GROUP_METADATA};
 use cosmwasm_std::{Deps, Order};
 use cw_storage_plus::Bound;
 
@@ -10,7 +10,7 @@ pub fn query_groups(
     deps: Deps,
     params: ContractGroupsQueryParams,
 ) -> Result<ContractGroupsResponse, ContractError> {
-    let contract_id = load_contract_id(deps.storage, &params.contract)?;
+    let contract_id = load_any_contract_id(deps.storage, &params.contract)?;
     let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
     let desc = params.desc.unwrap_or(false);
     let order = if desc {
@@ -77,7 +77,7 @@ pub fn _query_groups(
 
     // Get min, max range bounds. For paginating in descending order, we swap
     // their positions in the range call.
-    let contract_id = load_contract_id(deps.storage, &params.contract)?;
+    let contract_id = load_any_contract_id(deps.storage, &params.contract)?;
 
     let mut max = None;
     let mut min = params
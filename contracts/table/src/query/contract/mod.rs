@@ -1,9 +1,15 @@
+mod archived;
+mod flags;
 mod groups;
 mod is_related_to;
 mod relationships;
 mod tags;
+mod values;
 
+pub use archived::query_archived as archived;
+pub use flags::query_flags as flags;
 pub use groups::query_groups as groups;
 pub use is_related_to::is_related_to;
 pub use relationships::query_relationships as relationships;
 pub use tags::query_tags as tags;
+pub use values::query_values as values;
@@ -5,7 +5,7 @@ use crate::error::ContractError;
 use crate::msg::{
     ContractRelationshipsQueryParams, ContractRelationshipsResponse, RelationshipAddresses,
 };
-use crate::state::{load_contract_id, CONFIG_STR_MAX_LEN, REL_ID_2_ADDR};
+use crate::state::{load_any_contract_id, CONFIG_STR_MAX_LEN, REL_ID_2_ADDR};
 use crate::util::{pad, trim_padding};
 use cosmwasm_std::{Addr, Deps, Order};
 use cw_storage_plus::Bound;
@@ -14,7 +14,7 @@ pub fn query_relationships(
     deps: Deps,
     params: ContractRelationshipsQueryParams,
 ) -> Result<ContractRelationshipsResponse, ContractError> {
-    let contract_id = load_contract_id(deps.storage, &params.contract)?;
+    let contract_id = load_any_contract_id(deps.storage, &params.contract)?;
     let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
     let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
     let desc = params.desc.unwrap_or(false);
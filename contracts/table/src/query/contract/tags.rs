@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use crate::error::ContractError;
 use crate::msg::{ContractTagsQueryParams, ContractTagsResponse};
-use crate::state::{load_contract_id, CONTRACT_TAGS};
+use crate::state::{load_any_contract_id, CONTRACT_TAGS};
 use crate::util::trim_padding;
 use cosmwasm_std::{Deps, Order};
 use cw_storage_plus::Bound;
@@ -11,7 +11,7 @@ pub fn query_tags(
     deps: Deps,
     params: ContractTagsQueryParams,
 ) -> Result<ContractTagsResponse, ContractError> {
-    let contract_id = load_contract_id(deps.storage, &params.contract)?;
+    let contract_id = load_any_contract_id(deps.storage, &params.contract)?;
     let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
     let desc = params.desc.unwrap_or(false);
     let order = if desc {
@@ -0,0 +1,91 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::msg::{ContractValuesQueryParams, ContractValuesResponse, KeyValue};
+use crate::state::{load_any_contract_id, load_key_value, CONTRACT_INDEX_TYPES};
+use cosmwasm_std::{Deps, Order};
+use cw_storage_plus::Bound;
+
+/// Paginate over a contract's custom index values, ordered by index name.
+pub fn query_values(
+    deps: Deps,
+    params: ContractValuesQueryParams,
+) -> Result<ContractValuesResponse, ContractError> {
+    let contract_id = load_any_contract_id(deps.storage, &params.contract)?;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
+    let desc = params.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+    let (min, max) = match order {
+        Order::Ascending => (
+            params
+                .cursor
+                .as_ref()
+                .map(|name| Bound::Exclusive((name, PhantomData))),
+            None,
+        ),
+        Order::Descending => (
+            None,
+            params
+                .cursor
+                .as_ref()
+                .map(|name| Bound::Exclusive((name, PhantomData))),
+        ),
+    };
+
+    let mut cursor: Option<String> = None;
+    let mut values: Vec<KeyValue> = Vec::with_capacity(4);
+
+    // Look up each of the given index names directly, in the same order as
+    // when paginating over all of the contract's values.
+    if let Some(mut names) = params.names {
+        names.sort_unstable();
+        names.dedup();
+        if desc {
+            names.reverse();
+        }
+        for index_name in names.into_iter().filter(|name| match &params.cursor {
+            Some(c) if desc => name < c,
+            Some(c) => name > c,
+            None => true,
+        }) {
+            if let Some(index_type) =
+                CONTRACT_INDEX_TYPES.may_load(deps.storage, (contract_id, &index_name))?
+            {
+                values.push(load_key_value(
+                    deps.storage,
+                    contract_id,
+                    &index_name,
+                    &index_type,
+                )?);
+                if values.len() == limit {
+                    cursor = Some(index_name);
+                    break;
+                }
+            }
+        }
+        return Ok(ContractValuesResponse { values, cursor });
+    }
+
+    for result in CONTRACT_INDEX_TYPES
+        .prefix(contract_id)
+        .range(deps.storage, min, max, order)
+    {
+        let (index_name, index_type) = result?;
+        values.push(load_key_value(
+            deps.storage,
+            contract_id,
+            &index_name,
+            &index_type,
+        )?);
+        if values.len() == limit {
+            cursor = Some(index_name);
+            break;
+        }
+    }
+
+    Ok(ContractValuesResponse { values, cursor })
+}
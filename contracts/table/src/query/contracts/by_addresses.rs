@@ -1,11 +1,12 @@
 use crate::error::ContractError;
 use crate::msg::{AddressesQueryParams, ContractRecord, ContractsByAddressResponse};
-use crate::state::{load_contract_id, load_one_contract_record};
-use cosmwasm_std::Deps;
+use crate::state::{is_expired, load_contract_id, load_one_contract_record};
+use cosmwasm_std::{Deps, Env};
 
 /// Paginate over contracts by address
 pub fn by_addresses(
     deps: Deps,
+    env: Env,
     params: &mut AddressesQueryParams,
 ) -> Result<ContractsByAddressResponse, ContractError> {
     if params.contracts.is_empty() {
@@ -19,6 +20,7 @@ pub fn by_addresses(
     let i_start = params.cursor.unwrap_or(0).clamp(0, limit as u32 - 1) as usize;
     let i_stop = (i_start + limit).min(params.contracts.len() - 1);
     let desc = params.desc.unwrap_or(false);
+    let include_expired = params.include_expired.unwrap_or(false);
 
     if desc {
         params.contracts.reverse();
@@ -29,10 +31,14 @@ pub fn by_addresses(
     for i in i_start..i_stop {
         let contract_addr = &params.contracts[i];
         let contract_id = load_contract_id(deps.storage, contract_addr)?;
+        if !include_expired && is_expired(deps.storage, contract_id, env.block.time)? {
+            continue;
+        }
         contracts.push(load_one_contract_record(
             deps.storage,
             contract_id,
             params.details.clone(),
+            params.select.as_deref(),
         )?);
     }
 
@@ -1,19 +1,28 @@
 use std::marker::PhantomData;
 
 use crate::error::ContractError;
-use crate::msg::{ContractRecord, ContractsByGroupResponse, GroupQueryParams};
-use crate::state::{load_contract_records, ContractID, IX_GROUP};
-use cosmwasm_std::{Deps, Order, Uint64};
+use crate::msg::{ContractRecord, ContractsByGroupResponse, GroupQueryParams, PartitionSet};
+use crate::state::{
+    is_expired, load_contract_records, resolve_query_partitions, ContractID, CONTRACT_METADATA,
+    IX_GROUP,
+};
+use cosmwasm_std::{Deps, Env, Order, Uint64};
 use cw_storage_plus::Bound;
 
+// Max number of group members read per query. When reached, the query returns
+// whatever it has matched so far, along with a cursor for resuming the scan.
+pub const MAX_SCAN: usize = 500;
+
 /// Paginate the contracts in a given group.
 pub fn in_group(
     deps: Deps,
+    env: Env,
     params: GroupQueryParams,
 ) -> Result<ContractsByGroupResponse, ContractError> {
     let group_id = params.group;
     let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
     let desc = params.desc.unwrap_or(false);
+    let include_expired = params.include_expired.unwrap_or(false);
     let order = if desc {
         Order::Descending
     } else {
@@ -34,25 +43,59 @@ pub fn in_group(
         ),
     };
 
+    // Groups span partitions, so all members are returned by default. Members
+    // outside of the given partitions are skipped.
+    let partitions = match &params.partitions {
+        Some(PartitionSet::Only(..)) => Some(resolve_query_partitions(
+            deps.storage,
+            params.partition,
+            &params.partitions,
+        )?),
+        _ => None,
+    };
+
     let mut contract_ids: Vec<ContractID> = vec![];
     let mut cursor: Option<Uint64> = None;
+    let mut last_scanned: Option<ContractID> = None;
+    let mut n_scanned: usize = 0;
 
     // Read one page of the group's contract ID's
     for maybe_contract_id in IX_GROUP
         .prefix(group_id)
         .keys(deps.storage, min, max, order)
+        .take(MAX_SCAN)
     {
-        contract_ids.push(maybe_contract_id?);
+        let contract_id = maybe_contract_id?;
+        last_scanned = Some(contract_id);
+        n_scanned += 1;
+        if let Some(partitions) = &partitions {
+            let partition = CONTRACT_METADATA.load(deps.storage, contract_id)?.partition;
+            if !partitions.contains(&partition) {
+                continue;
+            }
+        }
+        if !include_expired && is_expired(deps.storage, contract_id, env.block.time)? {
+            continue;
+        }
+        contract_ids.push(contract_id);
+        if contract_ids.len() == limit {
+            break;
+        }
     }
 
-    // Get cursor needed for next page
-    if contract_ids.len() == limit {
-        cursor = Some(Uint64::from(contract_ids.last().unwrap().clone()))
+    // Get cursor needed for next page, which resumes from the last member
+    // scanned when the scan limit is reached before the page is full.
+    if contract_ids.len() == limit || n_scanned == MAX_SCAN {
+        cursor = last_scanned.map(Uint64::from);
     }
 
     // Load contract records from IDs
-    let contracts: Vec<ContractRecord> =
-        load_contract_records(deps.storage, &contract_ids, params.details)?;
+    let contracts: Vec<ContractRecord> = load_contract_records(
+        deps.storage,
+        &contract_ids,
+        params.details,
+        params.select.as_deref(),
+    )?;
 
     Ok(ContractsByGroupResponse { contracts, cursor })
 }
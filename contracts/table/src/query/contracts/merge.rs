@@ -0,0 +1,70 @@
+use cosmwasm_std::{Order, StdResult};
+
+use crate::error::ContractError;
+use crate::state::{ContractID, PartitionID};
+
+/// An iterator over one partition's index entries, in index order.
+pub type PartitionEntries<'a, D> =
+    Box<dyn Iterator<Item = StdResult<(PartitionID, D, ContractID)>> + 'a>;
+
+/// Read up to `limit` entries from the given per-partition iterators, merged
+/// into a single sequence ordered by (value, contract ID). Since contract IDs
+/// are unique across partitions, the last entry returned is enough to resume
+/// every partition's iterator on the next page.
+pub fn merge_entries<D: Ord>(
+    mut iters: Vec<PartitionEntries<D>>,
+    order: Order,
+    limit: usize,
+) -> Result<Vec<(PartitionID, D, ContractID)>, ContractError> {
+    let mut entries = Vec::with_capacity(limit);
+
+    // With only one partition, there's nothing to merge.
+    if iters.len() == 1 {
+        for result in iters.remove(0).take(limit) {
+            entries.push(result?);
+        }
+        return Ok(entries);
+    }
+
+    let mut heads: Vec<Option<(PartitionID, D, ContractID)>> = Vec::with_capacity(iters.len());
+
+    for iter in iters.iter_mut() {
+        heads.push(iter.next().transpose()?);
+    }
+
+    while entries.len() < limit {
+        // Find the partition whose next entry comes first in the given order.
+        let mut best: Option<usize> = None;
+
+        for (i, head) in heads.iter().enumerate() {
+            if let Some((_, value, id)) = head {
+                let is_first = match best.and_then(|j| heads[j].as_ref()) {
+                    Some((_, best_value, best_id)) => {
+                        let ordering = (value, id).cmp(&(best_value, best_id));
+                        match order {
+                            Order::Ascending => ordering.is_lt(),
+                            Order::Descending => ordering.is_gt(),
+                        }
+                    },
+                    None => true,
+                };
+                if is_first {
+                    best = Some(i);
+                }
+            }
+        }
+
+        let i = match best {
+            Some(i) => i,
+            None => break,
+        };
+
+        if let Some(entry) = heads[i].take() {
+            entries.push(entry);
+        }
+
+        heads[i] = iters[i].next().transpose()?;
+    }
+
+    Ok(entries)
+}
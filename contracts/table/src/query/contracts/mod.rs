@@ -1,11 +1,14 @@
 mod by_addresses;
 mod in_group;
+mod merge;
 mod range;
 mod related_to;
+mod select_where;
 mod with_tag;
 
 pub use by_addresses::by_addresses;
 pub use in_group::in_group;
 pub use range::range;
 pub use related_to::related_to;
-pub use with_tag::with_tag;
+pub use select_where::select_where;
+pub use with_tag::{with_tag, with_tag_match};
@@ -1,43 +1,76 @@
 use std::marker::PhantomData;
 use std::str::FromStr;
 
+use crate::composite::build_range_keys;
 use crate::msg::{ContractsRangeResponse, Cursor, RangeSelector, Target};
 use crate::state::{
-    load_contract_records, ContractID, CustomIndexMap, PartitionID, IX_CODE_ID, IX_CONTRACT_ID,
-    IX_CREATED_AT, IX_CREATED_BY, IX_REV, IX_UPDATED_AT, IX_UPDATED_BY,
+    exclude_expired, is_case_insensitive, load_contract_records, resolve_query_partitions,
+    CompositeIndexMap, ContractID, CustomIndexMap, PartitionID, COMPOSITE_INDEX_METADATA,
+    CONFIG_STR_MAX_LEN, IX_CODE_ID, IX_CONTRACT_ID, IX_CREATED_AT, IX_CREATED_BY, IX_REV,
+    IX_UPDATED_AT, IX_UPDATED_BY,
+};
+use crate::util::{
+    build_composite_index_storage_key, build_index_storage_key, build_string_index_key,
+    increment_prefix, pad, parse, parse_bool,
 };
-use crate::util::{build_index_storage_key, pad, parse, parse_bool};
 use crate::{error::ContractError, msg::RangeQueryParams};
-use cosmwasm_std::{Api, Binary, Deps, Order, StdResult, Storage, Uint64};
+
+use super::merge::{merge_entries, PartitionEntries};
+use cosmwasm_std::{Api, Binary, Deps, Env, Order, Storage, Uint64};
 use cw_storage_plus::{Bound, KeyDeserialize, Map, Prefixer, PrimaryKey};
 
 pub fn range(
     deps: Deps,
+    env: Env,
     query: RangeQueryParams,
 ) -> Result<ContractsRangeResponse, ContractError> {
-    // let limit = query.limit.unwrap_or(20).clamp(1, 200) as usize;
-    // let desc = query.desc.unwrap_or(false);
     let details = query.details.clone();
+    let select = query.select.clone();
+    let include_expired = query.include_expired;
 
     // Find matching contract ID's
-    let (ids, cursor) = match query.params.clone() {
-        Target::Equals(value) => {
-            get_contract_ids(deps.api, deps.storage, query, Some(value), None, true)
-        },
+    let entries = load_entries(deps, query)?;
+    let cursor = entries.last().map(|(_, cursor)| cursor.clone());
+    let ids: Vec<ContractID> = entries.into_iter().map(|(id, _)| id).collect();
+    let ids = exclude_expired(deps.storage, ids, env.block.time, include_expired)?;
+
+    // Convert contract ID's to Addrs
+    let contracts = load_contract_records(deps.storage, &ids, details, select.as_deref())?;
+
+    Ok(ContractsRangeResponse { contracts, cursor })
+}
+
+/// Read one page of (contract ID, cursor) entries from the index selected by
+/// the given query, where each cursor points at the entry it's paired with.
+pub(crate) fn load_entries(
+    deps: Deps,
+    query: RangeQueryParams,
+) -> Result<Vec<(ContractID, Cursor)>, ContractError> {
+    let partitions = resolve_query_partitions(deps.storage, query.partition, &query.partitions)?;
+
+    match query.params.clone() {
+        Target::Equals(value) => get_contract_ids(
+            deps.api,
+            deps.storage,
+            query,
+            &partitions,
+            Some(value),
+            None,
+            true,
+        ),
         Target::Between(range) => get_contract_ids(
             deps.api,
             deps.storage,
             query,
+            &partitions,
             range.start,
             range.stop,
             false,
         ),
-    }?;
-
-    // Convert contract ID's to Addrs
-    let contracts = load_contract_records(deps.storage, &ids, details)?;
-
-    Ok(ContractsRangeResponse { contracts, cursor })
+        Target::StartsWith(prefix) => {
+            get_contract_ids_by_prefix(deps.storage, query, &partitions, prefix)
+        },
+    }
 }
 
 fn build_bounds<'a, T>(
@@ -92,6 +125,28 @@ where
     })
 }
 
+/// Bound on the first possible key of a partition, given the smallest value.
+fn partition_start<'a, T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize>(
+    partition: PartitionID,
+    min_value: T,
+) -> Bound<'a, (PartitionID, T, u64)> {
+    Bound::Inclusive(((partition, min_value, u64::MIN), PhantomData))
+}
+
+/// Bound just past the last possible key of a partition, for value types with
+/// no largest value. The last partition needs no bound.
+fn partition_end<'a, T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize>(
+    partition: PartitionID,
+    min_value: T,
+) -> Option<Bound<'a, (PartitionID, T, u64)>> {
+    partition
+        .checked_add(1)
+        .map(|next| Bound::Exclusive(((next, min_value, u64::MIN), PhantomData)))
+}
+
+/// Build bounds for a range over a partition of a String-valued index. Open
+/// ends of the range are bounded by the partition itself, so that iterators
+/// over different partitions never overlap.
 fn build_range_bounds_str<'a>(
     order: Order,
     partition: PartitionID,
@@ -114,13 +169,13 @@ fn build_range_bounds_str<'a>(
                 } else if let Some(v) = range_start_value {
                     Some(Bound::Inclusive(((partition, v, u64::MIN), PhantomData)))
                 } else {
-                    None
+                    Some(partition_start(partition, String::new()))
                 },
                 // max
                 if let Some(v) = range_stop_value {
                     Some(Bound::Inclusive(((partition, v, u64::MAX), PhantomData)))
                 } else {
-                    None
+                    partition_end(partition, String::new())
                 },
             )
         },
@@ -130,7 +185,7 @@ fn build_range_bounds_str<'a>(
                 if let Some(v) = range_start_value {
                     Some(Bound::Exclusive(((partition, v, u64::MIN), PhantomData)))
                 } else {
-                    None
+                    Some(partition_start(partition, String::new()))
                 },
                 // max
                 if let Some((p, v_str, id)) = maybe_cursor {
@@ -138,7 +193,7 @@ fn build_range_bounds_str<'a>(
                 } else if let Some(v) = range_stop_value {
                     Some(Bound::Inclusive(((partition, v, u64::MAX), PhantomData)))
                 } else {
-                    None
+                    partition_end(partition, String::new())
                 },
             )
         },
@@ -220,33 +275,44 @@ fn build_start_stop_values_binary<'a>(
     Ok((start_value, stop_value))
 }
 
-fn page<'a, D>(
-    iter: Box<dyn Iterator<Item = StdResult<(PartitionID, D, ContractID)>> + 'a>,
+/// Read one page of entries from the given partitions of an index, merged in
+/// index order. The query's cursor is applied to each partition's iterator.
+fn page<'a, D: Ord>(
+    partitions: &[PartitionID],
+    cursor: &Option<Cursor>,
+    order: Order,
     limit: usize,
+    load_partition: &dyn Fn(
+        PartitionID,
+        Option<Cursor>,
+    ) -> Result<PartitionEntries<'a, D>, ContractError>,
     to_string: &dyn Fn(&D) -> String,
-) -> Result<(Vec<ContractID>, Option<Cursor>), ContractError> {
-    let limit = limit as usize;
-    let mut contract_ids = Vec::with_capacity(limit);
-    let mut cursor: Option<Cursor> = None;
-
-    for item in iter.take(limit) {
-        let (partition, value, contract_id) = item?;
-        cursor = Some((partition, to_string(&value), Uint64::from(contract_id)));
-        contract_ids.push(contract_id);
+) -> Result<Vec<(ContractID, Cursor)>, ContractError> {
+    let mut iters = Vec::with_capacity(partitions.len());
+
+    for partition in partitions.iter() {
+        let partition_cursor = cursor.clone().map(|(_, v, id)| (*partition, v, id));
+        iters.push(load_partition(*partition, partition_cursor)?);
     }
 
-    Ok((contract_ids, cursor))
+    Ok(merge_entries(iters, order, limit)?
+        .into_iter()
+        .map(|(partition, value, contract_id)| {
+            let cursor = (partition, to_string(&value), Uint64::from(contract_id));
+            (contract_id, cursor)
+        })
+        .collect())
 }
 
 fn get_contract_ids(
     _api: &dyn Api,
     store: &dyn Storage,
     query: RangeQueryParams,
+    partitions: &[PartitionID],
     raw_start: Option<String>,
     raw_stop: Option<String>,
     exact: bool,
-) -> Result<(Vec<u64>, Option<Cursor>), ContractError> {
-    let partition = query.partition;
+) -> Result<Vec<(ContractID, Cursor)>, ContractError> {
     let limit = query.limit.unwrap_or(20).clamp(1, 200) as usize;
     let desc = query.desc.unwrap_or(false);
     let order = if desc {
@@ -254,245 +320,554 @@ fn get_contract_ids(
     } else {
         Order::Ascending
     };
+    let cursor = &query.cursor;
 
     Ok(match &query.index {
         RangeSelector::Id => {
-            let index = IX_CONTRACT_ID;
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(IX_CONTRACT_ID.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::CodeId => {
-            let index = IX_CODE_ID;
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(IX_CODE_ID.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Rev => {
-            let index = IX_REV;
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(IX_REV.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::CreatedAt => {
-            let index = IX_CREATED_AT;
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(IX_CREATED_AT.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::UpdatedAt => {
-            let index = IX_UPDATED_AT;
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(IX_UPDATED_AT.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::CreatedBy => {
-            let index = IX_CREATED_BY;
             let (start, stop) = build_start_stop_values_str(raw_start, raw_stop, exact, None)?;
-            let (min, max) = build_range_bounds_str(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) =
+                        build_range_bounds_str(order, p, start.clone(), stop.clone(), cursor)?;
+                    Ok(IX_CREATED_BY.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::UpdatedBy => {
-            let index = IX_UPDATED_BY;
             let (start, stop) = build_start_stop_values_str(raw_start, raw_stop, exact, None)?;
-            let (min, max) = build_range_bounds_str(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) =
+                        build_range_bounds_str(order, p, start.clone(), stop.clone(), cursor)?;
+                    Ok(IX_UPDATED_BY.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::String(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<String> = Map::new(&storage_key);
-            let (start, stop) = build_start_stop_values_str(raw_start, raw_stop, exact, None)?;
-            let (min, max) = build_range_bounds_str(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            let case_insensitive = is_case_insensitive(store, index_name)?;
+            let max_str_len = CONFIG_STR_MAX_LEN.load(store)? as usize;
+            let to_key = |s: String| build_string_index_key(&s, case_insensitive, max_str_len);
+            let (start, stop) = build_start_stop_values_str(
+                raw_start.map(&to_key),
+                raw_stop.map(&to_key),
+                exact,
+                None,
+            )?;
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) =
+                        build_range_bounds_str(order, p, start.clone(), stop.clone(), cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Bool(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u8> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u8::MIN, raw_stop, u8::MAX, exact, &parse_bool)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Timestamp(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u64> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Int32(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<i32> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, i32::MIN, raw_stop, i32::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Uint8(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u8> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u8::MIN, raw_stop, u8::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Uint16(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u16> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u16::MIN, raw_stop, u16::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Uint32(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u32> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u32::MIN, raw_stop, u32::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Uint64(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u64> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u64::MIN, raw_stop, u64::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Uint128(index_name) => {
             let storage_key = build_index_storage_key(index_name);
             let index: CustomIndexMap<u128> = Map::new(&storage_key);
             let (start, stop) =
                 build_start_stop(raw_start, u128::MIN, raw_stop, u128::MAX, exact, &parse)?;
-            let (min, max) = build_bounds(order, partition, start, stop, query.cursor)?;
-            page(index.keys(store, min, max, order), limit, &|x| {
-                x.to_string()
-            })?
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let (min, max) = build_bounds(order, p, start, stop, cursor)?;
+                    Ok(index.keys(store, min, max, order))
+                },
+                &|x| x.to_string(),
+            )?
         },
         RangeSelector::Binary(index_name) => {
             let storage_key = build_index_storage_key(index_name);
-            let index: CustomIndexMap<&[u8]> = Map::new(&storage_key);
             let (start, stop) = build_start_stop_values_binary(raw_start, raw_stop, exact)?;
-
-            #[allow(unused_assignments)]
-            let mut start_vec: Vec<u8> = vec![];
-
-            #[allow(unused_assignments)]
-            let mut stop_vec: Vec<u8> = vec![];
-
-            let (min, max) = match order {
-                Order::Ascending => {
-                    (
-                        // min
-                        if let Some((p, v_str, id)) = query.cursor {
-                            start_vec = Binary::from_base64(&v_str)?.to_vec();
-                            Some(Bound::Exclusive((
-                                (p, start_vec.as_slice(), id.u64()),
-                                PhantomData,
-                            )))
-                        } else if let Some(v) = start {
-                            start_vec = v;
-                            Some(Bound::Inclusive((
-                                (partition, start_vec.as_slice(), u64::MIN),
-                                PhantomData,
-                            )))
-                        } else {
-                            None
-                        },
-                        // max
-                        if let Some(v) = stop {
-                            stop_vec = v;
-                            Some(Bound::Exclusive((
-                                (partition, stop_vec.as_slice(), u64::MAX),
-                                PhantomData,
-                            )))
-                        } else {
-                            None
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let index: CustomIndexMap<&[u8]> = Map::new(&storage_key);
+                    let cursor = match cursor {
+                        Some((p, v_str, id)) => {
+                            Some((p, Binary::from_base64(&v_str)?.to_vec(), id.u64()))
                         },
-                    )
+                        None => None,
+                    };
+                    let start_bound = start.as_ref().map(|v| (p, v.as_slice(), u64::MIN));
+                    let stop_bound = stop.as_ref().map(|v| (p, v.as_slice(), u64::MAX));
+                    let cursor_bound = cursor.as_ref().map(|(p, v, id)| (*p, v.as_slice(), *id));
+                    // Open ends of the range are bounded by the partition.
+                    let (min, max) = match order {
+                        Order::Ascending => (
+                            cursor_bound
+                                .map(|k| Bound::Exclusive((k, PhantomData)))
+                                .or(start_bound.map(|k| Bound::Inclusive((k, PhantomData))))
+                                .or(Some(partition_start(p, &[][..]))),
+                            stop_bound
+                                .map(|k| Bound::Exclusive((k, PhantomData)))
+                                .or(partition_end(p, &[][..])),
+                        ),
+                        Order::Descending => (
+                            start_bound
+                                .map(|k| Bound::Exclusive((k, PhantomData)))
+                                .or(Some(partition_start(p, &[][..]))),
+                            cursor_bound
+                                .map(|k| Bound::Exclusive((k, PhantomData)))
+                                .or(stop_bound.map(|k| Bound::Inclusive((k, PhantomData))))
+                                .or(partition_end(p, &[][..])),
+                        ),
+                    };
+                    Ok(index.keys(store, min, max, order))
                 },
-                Order::Descending => {
-                    (
-                        // min
-                        if let Some(v) = start {
-                            start_vec = v;
-                            Some(Bound::Exclusive((
-                                (partition, start_vec.as_slice(), u64::MIN),
-                                PhantomData,
-                            )))
-                        } else {
-                            None
+                &|x| Binary::from(x.as_slice()).to_base64(),
+            )?
+        },
+        RangeSelector::Composite(index_name) => {
+            let meta = COMPOSITE_INDEX_METADATA
+                .may_load(store, index_name.clone())?
+                .ok_or_else(|| ContractError::ValidationError {
+                    reason: format!("composite index {} does not exist", index_name),
+                })?;
+            let max_str_len = CONFIG_STR_MAX_LEN.load(store)? as usize;
+            let prefix = query.prefix.clone().unwrap_or_default();
+            let raw_stop = if exact { raw_start.clone() } else { raw_stop };
+            let (start, stop) = build_range_keys(&meta, &prefix, raw_start, raw_stop, max_str_len)?;
+            let storage_key = build_composite_index_storage_key(index_name);
+
+            // Composite keys are paginated by their base64-encoded bytes.
+            page(
+                partitions,
+                cursor,
+                order,
+                limit,
+                &|p, cursor| {
+                    let index: CompositeIndexMap = Map::new(&storage_key);
+                    let cursor_key = match cursor {
+                        Some((p, v_str, id)) => {
+                            Some((p, Binary::from_base64(&v_str)?.to_vec(), id.u64()))
                         },
-                        // max
-                        if let Some((p, v_str, id)) = query.cursor {
-                            stop_vec = Binary::from_base64(&v_str)?.to_vec();
-                            Some(Bound::Exclusive((
-                                (p, stop_vec.as_slice(), id.u64()),
-                                PhantomData,
-                            )))
-                        } else if let Some(v) = stop {
-                            stop_vec = v;
-                            Some(Bound::Inclusive((
-                                (partition, stop_vec.as_slice(), u64::MAX),
-                                PhantomData,
-                            )))
-                        } else {
-                            None
+                        None => None,
+                    };
+                    let min_bound =
+                        Bound::Inclusive(((p, start.as_slice(), u64::MIN), PhantomData));
+                    let max_bound = Bound::Inclusive(((p, stop.as_slice(), u64::MAX), PhantomData));
+                    let cursor_bound = cursor_key.as_ref().map(|(p, key, id)| {
+                        Bound::Exclusive(((*p, key.as_slice(), *id), PhantomData))
+                    });
+                    let (min, max) = match order {
+                        Order::Ascending => {
+                            (Some(cursor_bound.unwrap_or(min_bound)), Some(max_bound))
                         },
-                    )
+                        Order::Descending => {
+                            (Some(min_bound), Some(cursor_bound.unwrap_or(max_bound)))
+                        },
+                    };
+                    Ok(index.keys(store, min, max, order))
                 },
-            };
-
-            // index.prefix_range(
-            //     storage,
-            //     Some(PrefixBound::Exclusive((
-            //         (partition, "".as_bytes()),
-            //         PhantomData,
-            //     ))),
-            //     None,
-            //     order,
-            // );
-
-            page(index.keys(store, min, max, order), limit, &|x| {
-                Binary::from(x.as_slice()).to_base64()
-            })?
+                &|x| Binary::from(x.as_slice()).to_base64(),
+            )?
         },
     })
 }
+
+/// Page through the entries of a custom String index whose values start with
+/// the given prefix.
+fn get_contract_ids_by_prefix(
+    store: &dyn Storage,
+    query: RangeQueryParams,
+    partitions: &[PartitionID],
+    prefix: String,
+) -> Result<Vec<(ContractID, Cursor)>, ContractError> {
+    let index_name = match &query.index {
+        RangeSelector::String(index_name) => index_name,
+        _ => {
+            return Err(ContractError::ValidationError {
+                reason: "StartsWith only applies to string indices".to_owned(),
+            })
+        },
+    };
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 200) as usize;
+    let desc = query.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+
+    let max_str_len = CONFIG_STR_MAX_LEN.load(store)? as usize;
+    let prefix = if is_case_insensitive(store, index_name)? {
+        prefix.to_lowercase()
+    } else {
+        prefix
+    };
+
+    // Values are padded to the max string length, so every value starting with
+    // the prefix lies between the padded prefix and its padded successor.
+    let start = pad(&prefix, max_str_len);
+    let stop = increment_prefix(&prefix).map(|stop| pad(&stop, max_str_len));
+    let storage_key = build_index_storage_key(index_name);
+
+    page(
+        partitions,
+        &query.cursor,
+        order,
+        limit,
+        &|p, cursor| {
+            let index: CustomIndexMap<String> = Map::new(&storage_key);
+            let start_bound = Bound::Inclusive(((start.clone(), u64::MIN), PhantomData));
+            let stop_bound = stop
+                .clone()
+                .map(|stop| Bound::Exclusive(((stop, u64::MIN), PhantomData)));
+            let cursor_bound =
+                cursor.map(|(_, value, id)| Bound::Exclusive(((value, id.u64()), PhantomData)));
+            let (min, max) = match order {
+                Order::Ascending => (Some(cursor_bound.unwrap_or(start_bound)), stop_bound),
+                Order::Descending => (Some(start_bound), cursor_bound.or(stop_bound)),
+            };
+            let prefix = prefix.clone();
+            Ok(Box::new(
+                index
+                    .sub_prefix(p)
+                    .keys(store, min, max, order)
+                    .take_while(move |result| match result {
+                        Ok((value, _)) => value.starts_with(prefix.as_str()),
+                        Err(_) => true,
+                    })
+                    .map(move |result| result.map(|(value, id)| (p, value, id))),
+            ))
+        },
+        &|x| x.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies, MockApi, MockQuerier, MockStorage},
+        Binary, OwnedDeps, Uint64,
+    };
+    use cw_storage_plus::Map;
+
+    use super::load_entries;
+    use crate::{
+        msg::{Cursor, PartitionSet, Range, RangeQueryParams, RangeSelector, Target},
+        state::{CustomIndexMap, IX_CREATED_BY, X},
+        util::build_index_storage_key,
+    };
+
+    fn query(
+        index: RangeSelector,
+        desc: bool,
+        cursor: Option<Cursor>,
+    ) -> RangeQueryParams {
+        RangeQueryParams {
+            index,
+            partition: 1,
+            partitions: Some(PartitionSet::Only(vec![1, 2])),
+            prefix: None,
+            params: Target::Between(Range {
+                start: None,
+                stop: None,
+            }),
+            desc: Some(desc),
+            limit: Some(3),
+            cursor,
+            details: None,
+            select: None,
+            include_expired: None,
+        }
+    }
+
+    /// Page through the whole index, returning contract IDs in order.
+    fn load_all(
+        deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        index: RangeSelector,
+        desc: bool,
+    ) -> Vec<u64> {
+        let mut ids: Vec<u64> = vec![];
+        let mut cursor: Option<Cursor> = None;
+        for _ in 0..10 {
+            let entries = load_entries(deps.as_ref(), query(index.clone(), desc, cursor)).unwrap();
+            ids.extend(entries.iter().map(|(id, _)| *id));
+            if entries.len() < 3 {
+                return ids;
+            }
+            cursor = entries.last().map(|(_, cursor)| cursor.clone());
+        }
+        panic!("paging did not end");
+    }
+
+    #[test]
+    fn merges_partitions_of_an_open_ended_string_range() {
+        let mut deps = mock_dependencies();
+        let storage = deps.as_mut().storage;
+
+        // Partition 3 isn't queried, so none of its contracts may be returned.
+        for (partition, addr, id) in [
+            (1, "addr_a", 1),
+            (2, "addr_b", 2),
+            (1, "addr_c", 3),
+            (2, "addr_d", 4),
+            (3, "addr_e", 5),
+        ] {
+            IX_CREATED_BY
+                .save(storage, (partition, addr.to_owned(), id), &X)
+                .unwrap();
+        }
+
+        assert_eq!(
+            load_all(&deps, RangeSelector::CreatedBy, false),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(
+            load_all(&deps, RangeSelector::CreatedBy, true),
+            vec![4, 3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn merges_partitions_of_an_open_ended_binary_range() {
+        let mut deps = mock_dependencies();
+        let storage = deps.as_mut().storage;
+        let storage_key = build_index_storage_key(&"key".to_owned());
+        let index: CustomIndexMap<&[u8]> = Map::new(&storage_key);
+
+        for (partition, value, id) in [
+            (0, &[0u8][..], 5),
+            (1, &[1u8][..], 1),
+            (2, &[2u8][..], 2),
+            (1, &[3u8][..], 3),
+            (2, &[4u8][..], 4),
+        ] {
+            index.save(storage, (partition, value, id), &X).unwrap();
+        }
+
+        let selector = RangeSelector::Binary("key".to_owned());
+        assert_eq!(load_all(&deps, selector.clone(), false), vec![1, 2, 3, 4]);
+        assert_eq!(load_all(&deps, selector, true), vec![4, 3, 2, 1]);
+
+        // Cursors hold the last value seen, in base64.
+        let entries = load_entries(
+            deps.as_ref(),
+            query(RangeSelector::Binary("key".to_owned()), false, None),
+        )
+        .unwrap();
+        assert_eq!(
+            entries.last().map(|(_, cursor)| cursor.clone()),
+            Some((1, Binary::from(&[3u8][..]).to_base64(), Uint64::from(3u64)))
+        );
+    }
+}
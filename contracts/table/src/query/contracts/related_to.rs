@@ -6,10 +6,10 @@ use crate::msg::{
     Range, ReadRelationshipResponse, RelatedContract, RelationshipMetadata, RelationshipQueryParams,
 };
 use crate::state::{
-    load_one_contract_record, ContractID, CONFIG_STR_MAX_LEN, REL_ADDR_2_ID, UNIQUE,
+    is_expired, load_one_contract_record, ContractID, CONFIG_STR_MAX_LEN, REL_ADDR_2_ID, UNIQUE,
 };
 use crate::util::{pad, parse, trim_padding};
-use cosmwasm_std::{Deps, Order};
+use cosmwasm_std::{Deps, Env, Order};
 use cw_storage_plus::Bound;
 
 /// Paginate over lists of relationships between contracts in the table and
@@ -22,18 +22,27 @@ use cw_storage_plus::Bound;
 /// relationships to a given arbitrary address.
 pub fn related_to(
     deps: Deps,
+    env: Env,
     params: RelationshipQueryParams,
 ) -> Result<ReadRelationshipResponse, ContractError> {
     let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
     let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
     let desc = params.desc.unwrap_or(false);
+    let include_expired = params.include_expired.unwrap_or(false);
     let order = if desc {
         Order::Descending
     } else {
         Order::Ascending
     };
 
+    // When matching relationship names by prefix, iteration stops at the first
+    // name that doesn't start with it.
+    let mut name_prefix: Option<String> = None;
+
     let (start_name, stop_name) = if params.cursor.is_some() {
+        if let Some(crate::msg::Target::StartsWith(prefix)) = &params.name {
+            name_prefix = Some(prefix.clone());
+        }
         (String::default(), String::default())
     } else {
         match params.name {
@@ -42,6 +51,10 @@ pub fn related_to(
                 crate::msg::Target::Between(Range { start, stop }) => {
                     (start.unwrap_or_default(), stop.unwrap_or_default())
                 },
+                crate::msg::Target::StartsWith(prefix) => {
+                    name_prefix = Some(prefix.clone());
+                    (prefix, String::new())
+                },
             },
             None => (String::default(), String::default()),
         }
@@ -133,15 +146,24 @@ pub fn related_to(
         let ((contract_addr, name, contract_id_str), uniqueness) = result?;
         let name = trim_padding(&name);
 
-        if (stop_name.is_empty() && !start_name.is_empty() && name != start_name)
-            || (!stop_name.is_empty() && name > stop_name)
-            || (contract_addr != target_contract_addr_str)
-        {
+        let is_out_of_range = if let Some(prefix) = &name_prefix {
+            !name.starts_with(prefix.as_str())
+        } else {
+            (stop_name.is_empty() && !start_name.is_empty() && name != start_name)
+                || (!stop_name.is_empty() && name > stop_name)
+        };
+
+        if is_out_of_range || contract_addr != target_contract_addr_str {
             break;
         }
 
         let related_contract_id = parse::<u64>(contract_id_str.clone())?;
 
+        if !include_expired && is_expired(deps.storage, related_contract_id, env.block.time)? {
+            cursor = Some((name, contract_id_str));
+            continue;
+        }
+
         if let Some(contract_rel) = memoized.get_mut(&related_contract_id) {
             contract_rel.relationships.push(RelationshipMetadata {
                 name: name.clone(),
@@ -156,6 +178,7 @@ pub fn related_to(
                         deps.storage,
                         related_contract_id,
                         params.details.clone(),
+                        params.select.as_deref(),
                     )?,
                     relationships: vec![RelationshipMetadata {
                         name: name.clone(),
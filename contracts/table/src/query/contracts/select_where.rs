@@ -0,0 +1,547 @@
+use std::marker::PhantomData;
+
+use crate::composite::{build_range_keys, load_key};
+use crate::error::ContractError;
+use crate::msg::{
+    ContractsWhereResponse, Cursor, Expression, Range, RangeFilter, RangeQueryParams,
+    RangeSelector, RelationshipFilter, Target, WhereCursor, WhereQueryParams,
+};
+use crate::state::{
+    is_case_insensitive, is_expired, load_contract_records, ContractID, PartitionID,
+    COMPOSITE_INDEX_METADATA, CONFIG_STR_MAX_LEN, CONTRACT_DYN_METADATA, CONTRACT_GROUP_IDS,
+    CONTRACT_METADATA, CONTRACT_TAGS, GROUP_METADATA, INDEX_METADATA, IX_GROUP, IX_TAG,
+    PARTITION_SIZES, PARTITION_TAG_COUNTS, REL_ID_2_ADDR, VALUES_BINARY, VALUES_BOOL, VALUES_I32,
+    VALUES_STRING, VALUES_TIME, VALUES_U128, VALUES_U16, VALUES_U32, VALUES_U64, VALUES_U8,
+};
+use crate::util::{pad, parse, parse_bool, trim_padding};
+use cosmwasm_std::{Binary, Deps, Env, Order, StdResult, Storage, Uint64};
+use cw_storage_plus::Bound;
+
+use super::range::load_entries;
+
+// Max number of index entries read from the driving index per query. When
+// reached, the query returns whatever it has matched so far, along with a
+// cursor for resuming the scan.
+pub const MAX_SCAN: usize = 500;
+
+// Number of entries read from a driving range index per batch.
+const RANGE_BATCH_SIZE: usize = 50;
+
+/// Paginate over contracts matching a boolean expression of range, tag, group
+/// and relationship filters. The top-level And term (or lone filter) with the
+/// lowest estimated size drives iteration over its index, while all other
+/// terms are checked against each contract's stored values.
+pub fn select_where(
+    deps: Deps,
+    env: Env,
+    params: WhereQueryParams,
+) -> Result<ContractsWhereResponse, ContractError> {
+    let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
+    let desc = params.desc.unwrap_or(false);
+    let include_expired = params.include_expired.unwrap_or(false);
+    let partition = params.partition;
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+
+    let terms: Vec<&Expression> = match &params.filter {
+        Expression::And(terms) => terms.iter().collect(),
+        expr => vec![expr],
+    };
+
+    // Resume with the same driving term used to generate the cursor, or else
+    // pick the most selective one.
+    let (driver, key) = if let Some(WhereCursor { driver, key }) = params.cursor {
+        if let Some(i) = driver {
+            let term = terms.get(i as usize).copied();
+            if estimate_size(deps.storage, partition, term, max_str_len)?.is_none() {
+                return Err(ContractError::InvalidCursor {
+                    reason: format!("term {} cannot drive iteration", i),
+                });
+            }
+        }
+        (driver.map(|i| i as usize), Some(key))
+    } else {
+        (plan(deps.storage, partition, &terms, max_str_len)?, None)
+    };
+
+    let is_match = |id: ContractID| -> Result<bool, ContractError> {
+        if !include_expired && is_expired(deps.storage, id, env.block.time)? {
+            return Ok(false);
+        }
+        for (i, term) in terms.iter().enumerate() {
+            if Some(i) != driver && !evaluate(deps.storage, id, term, max_str_len)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    };
+
+    let (contract_ids, key) = match driver.map(|i| terms[i]) {
+        Some(Expression::Tag(tag)) => {
+            let tag = pad(tag, max_str_len);
+            let (min, max) = build_id_bounds(order, key);
+            scan_ids(
+                Box::new(
+                    IX_TAG
+                        .prefix((partition, &tag))
+                        .keys(deps.storage, min, max, order),
+                ),
+                partition,
+                limit,
+                &is_match,
+            )?
+        },
+        Some(Expression::Group(group_id)) => {
+            let (min, max) = build_id_bounds(order, key);
+            scan_ids(
+                Box::new(
+                    IX_GROUP
+                        .prefix(*group_id)
+                        .keys(deps.storage, min, max, order),
+                ),
+                partition,
+                limit,
+                &|id| {
+                    // Groups transcend partitions, so filter out members
+                    // belonging to other partitions.
+                    Ok(
+                        CONTRACT_METADATA.load(deps.storage, id)?.partition == partition
+                            && is_match(id)?,
+                    )
+                },
+            )?
+        },
+        Some(Expression::Range(filter)) => {
+            // Guard against range iterators that aren't bounded to the
+            // partition, as with groups.
+            scan_range(deps, partition, filter, desc, key, limit, &|id| {
+                Ok(
+                    CONTRACT_METADATA.load(deps.storage, id)?.partition == partition
+                        && is_match(id)?,
+                )
+            })?
+        },
+        _ => {
+            // No term can drive iteration, so scan the whole partition.
+            let filter = RangeFilter {
+                index: RangeSelector::Id,
+                prefix: None,
+                params: Target::Between(Range {
+                    start: None,
+                    stop: None,
+                }),
+            };
+            scan_range(deps, partition, &filter, desc, key, limit, &is_match)?
+        },
+    };
+
+    let contracts = load_contract_records(
+        deps.storage,
+        &contract_ids,
+        params.details,
+        params.select.as_deref(),
+    )?;
+
+    Ok(ContractsWhereResponse {
+        contracts,
+        cursor: key.map(|key| WhereCursor {
+            driver: driver.map(|i| i as u32),
+            key,
+        }),
+    })
+}
+
+/// Return the index of the term with the lowest estimated size, if any term
+/// can drive iteration at all.
+fn plan(
+    storage: &dyn Storage,
+    partition: PartitionID,
+    terms: &[&Expression],
+    max_str_len: usize,
+) -> Result<Option<usize>, ContractError> {
+    let mut best: Option<(usize, u64)> = None;
+    for (i, term) in terms.iter().enumerate() {
+        if let Some(size) = estimate_size(storage, partition, Some(*term), max_str_len)? {
+            if best.map(|(_, best_size)| size < best_size).unwrap_or(true) {
+                best = Some((i, size));
+            }
+        }
+    }
+    Ok(best.map(|(i, _)| i))
+}
+
+/// Return an upper bound on the number of index entries a term would visit if
+/// used to drive iteration, or None if the term has no index of its own.
+fn estimate_size(
+    storage: &dyn Storage,
+    partition: PartitionID,
+    maybe_term: Option<&Expression>,
+    max_str_len: usize,
+) -> Result<Option<u64>, ContractError> {
+    Ok(match maybe_term {
+        Some(Expression::Tag(tag)) => Some(
+            PARTITION_TAG_COUNTS
+                .may_load(storage, (partition, &pad(tag, max_str_len)))?
+                .unwrap_or_default() as u64,
+        ),
+        Some(Expression::Group(group_id)) => Some(
+            GROUP_METADATA
+                .may_load(storage, *group_id)?
+                .map(|meta| meta.size.u64())
+                .unwrap_or_default(),
+        ),
+        Some(Expression::Range(filter)) => Some(match &filter.index {
+            RangeSelector::Composite(index_name) => COMPOSITE_INDEX_METADATA
+                .may_load(storage, index_name.to_owned())?
+                .map(|meta| meta.size.u64())
+                .unwrap_or_default(),
+            selector => match get_custom_index_name(selector) {
+                Some(index_name) => INDEX_METADATA
+                    .may_load(storage, index_name.to_owned())?
+                    .map(|meta| meta.size.u64())
+                    .unwrap_or_default(),
+                None => PARTITION_SIZES
+                    .may_load(storage, partition)?
+                    .unwrap_or_default()
+                    .u64(),
+            },
+        }),
+        _ => None,
+    })
+}
+
+fn get_custom_index_name(selector: &RangeSelector) -> Option<&String> {
+    match selector {
+        RangeSelector::String(name)
+        | RangeSelector::Bool(name)
+        | RangeSelector::Timestamp(name)
+        | RangeSelector::Int32(name)
+        | RangeSelector::Uint8(name)
+        | RangeSelector::Uint16(name)
+        | RangeSelector::Uint32(name)
+        | RangeSelector::Uint64(name)
+        | RangeSelector::Uint128(name)
+        | RangeSelector::Binary(name) => Some(name),
+        _ => None,
+    }
+}
+
+type IdBound<'a> = Option<Bound<'a, ContractID>>;
+
+fn build_id_bounds<'a>(
+    order: Order,
+    maybe_cursor: Option<Cursor>,
+) -> (IdBound<'a>, IdBound<'a>) {
+    let bound = maybe_cursor.map(|(_, _, id)| Bound::Exclusive((id.u64(), PhantomData)));
+    match order {
+        Order::Ascending => (bound, None),
+        Order::Descending => (None, bound),
+    }
+}
+
+/// Collect matching contract IDs from an iterator over a tag or group index.
+fn scan_ids<'a>(
+    iter: Box<dyn Iterator<Item = StdResult<ContractID>> + 'a>,
+    partition: PartitionID,
+    limit: usize,
+    is_match: &dyn Fn(ContractID) -> Result<bool, ContractError>,
+) -> Result<(Vec<ContractID>, Option<Cursor>), ContractError> {
+    let mut contract_ids: Vec<ContractID> = Vec::with_capacity(limit);
+    let mut cursor: Option<Cursor> = None;
+
+    for (n, result) in iter.enumerate() {
+        if n == MAX_SCAN {
+            return Ok((contract_ids, cursor));
+        }
+        let contract_id = result?;
+        cursor = Some((partition, String::new(), contract_id.into()));
+        if is_match(contract_id)? {
+            contract_ids.push(contract_id);
+            if contract_ids.len() == limit {
+                return Ok((contract_ids, cursor));
+            }
+        }
+    }
+
+    Ok((contract_ids, None))
+}
+
+/// Collect matching contract IDs by reading batches of entries from a built-in
+/// or custom range index.
+fn scan_range(
+    deps: Deps,
+    partition: PartitionID,
+    filter: &RangeFilter,
+    desc: bool,
+    mut cursor: Option<Cursor>,
+    limit: usize,
+    is_match: &dyn Fn(ContractID) -> Result<bool, ContractError>,
+) -> Result<(Vec<ContractID>, Option<Cursor>), ContractError> {
+    let mut contract_ids: Vec<ContractID> = Vec::with_capacity(limit);
+    let mut n_scanned: usize = 0;
+
+    loop {
+        let entries = load_entries(
+            deps,
+            RangeQueryParams {
+                index: filter.index.clone(),
+                partition,
+                partitions: None,
+                prefix: filter.prefix.clone(),
+                params: filter.params.clone(),
+                desc: Some(desc),
+                limit: Some(RANGE_BATCH_SIZE as u32),
+                cursor: cursor.clone(),
+                details: None,
+                select: None,
+                include_expired: None,
+            },
+        )?;
+
+        let is_exhausted = entries.len() < RANGE_BATCH_SIZE;
+
+        for (contract_id, entry_cursor) in entries {
+            n_scanned += 1;
+            cursor = Some(entry_cursor);
+            if is_match(contract_id)? {
+                contract_ids.push(contract_id);
+            }
+            if contract_ids.len() == limit || n_scanned == MAX_SCAN {
+                return Ok((contract_ids, cursor));
+            }
+        }
+
+        if is_exhausted {
+            return Ok((contract_ids, None));
+        }
+    }
+}
+
+/// Evaluate an expression against a single contract.
+fn evaluate(
+    storage: &dyn Storage,
+    id: ContractID,
+    expr: &Expression,
+    max_str_len: usize,
+) -> Result<bool, ContractError> {
+    Ok(match expr {
+        Expression::And(terms) => {
+            for term in terms.iter() {
+                if !evaluate(storage, id, term, max_str_len)? {
+                    return Ok(false);
+                }
+            }
+            true
+        },
+        Expression::Or(terms) => {
+            for term in terms.iter() {
+                if evaluate(storage, id, term, max_str_len)? {
+                    return Ok(true);
+                }
+            }
+            false
+        },
+        Expression::Not(term) => !evaluate(storage, id, term, max_str_len)?,
+        Expression::Tag(tag) => CONTRACT_TAGS.has(storage, (id, pad(tag, max_str_len))),
+        Expression::Group(group_id) => CONTRACT_GROUP_IDS.has(storage, (id, *group_id)),
+        Expression::RelatedTo(filter) => is_related(storage, id, filter, max_str_len)?,
+        Expression::Range(filter) => is_in_range(storage, id, filter, max_str_len)?,
+    })
+}
+
+fn is_related(
+    storage: &dyn Storage,
+    id: ContractID,
+    filter: &RelationshipFilter,
+    max_str_len: usize,
+) -> Result<bool, ContractError> {
+    let name = pad(&filter.name, max_str_len);
+    Ok(if let Some(addr) = &filter.address {
+        REL_ID_2_ADDR.has(storage, (id, name, addr.to_string()))
+    } else {
+        REL_ID_2_ADDR
+            .prefix((id, name))
+            .keys(storage, None, None, Order::Ascending)
+            .next()
+            .is_some()
+    })
+}
+
+fn is_in_range(
+    storage: &dyn Storage,
+    id: ContractID,
+    filter: &RangeFilter,
+    max_str_len: usize,
+) -> Result<bool, ContractError> {
+    let (start, stop) = match &filter.params {
+        Target::Equals(value) => (Some(value.clone()), Some(value.clone())),
+        Target::Between(Range { start, stop }) => (start.clone(), stop.clone()),
+        Target::StartsWith(prefix) => return is_prefixed_by(storage, id, filter, prefix),
+    };
+
+    let parse_str = |s: String| -> Result<String, ContractError> { Ok(s) };
+    let parse_binary =
+        |s: String| -> Result<Vec<u8>, ContractError> { Ok(Binary::from_base64(&s)?.to_vec()) };
+
+    match &filter.index {
+        RangeSelector::Id => is_between(Some(id), start, stop, &parse),
+        RangeSelector::CodeId => {
+            let code_id = CONTRACT_METADATA.load(storage, id)?.code_id.u64();
+            is_between(Some(code_id), start, stop, &parse)
+        },
+        RangeSelector::CreatedAt => {
+            let created_at = CONTRACT_METADATA.load(storage, id)?.created_at.nanos();
+            is_between(Some(created_at), start, stop, &parse)
+        },
+        RangeSelector::CreatedBy => {
+            let created_by = CONTRACT_METADATA.load(storage, id)?.created_by.to_string();
+            is_between(Some(created_by), start, stop, &parse_str)
+        },
+        RangeSelector::UpdatedAt => {
+            // Dynamic metadata doesn't exist until first update. In this case,
+            // the initial values are the creation-time values.
+            let updated_at = match CONTRACT_DYN_METADATA.may_load(storage, id)? {
+                Some(meta) => meta.updated_at,
+                None => CONTRACT_METADATA.load(storage, id)?.created_at,
+            };
+            is_between(Some(updated_at.nanos()), start, stop, &parse)
+        },
+        RangeSelector::UpdatedBy => {
+            let updated_by = match CONTRACT_DYN_METADATA.may_load(storage, id)? {
+                Some(meta) => meta.updated_by,
+                None => CONTRACT_METADATA.load(storage, id)?.created_by,
+            };
+            is_between(Some(updated_by.to_string()), start, stop, &parse_str)
+        },
+        RangeSelector::Rev => {
+            let rev = CONTRACT_DYN_METADATA
+                .may_load(storage, id)?
+                .map(|meta| meta.rev)
+                .unwrap_or(Uint64::one());
+            is_between(Some(rev.u64()), start, stop, &parse)
+        },
+        RangeSelector::String(name) => {
+            let case_insensitive = is_case_insensitive(storage, name)?;
+            let normalize = |s: String| {
+                if case_insensitive {
+                    s.to_lowercase()
+                } else {
+                    s
+                }
+            };
+            let parse_key = |s: String| -> Result<String, ContractError> { Ok(normalize(s)) };
+            let value = VALUES_STRING
+                .may_load(storage, (id, name))?
+                .map(|v| normalize(trim_padding(&v)));
+            is_between(value, start, stop, &parse_key)
+        },
+        RangeSelector::Bool(name) => {
+            let value = VALUES_BOOL
+                .may_load(storage, (id, name))?
+                .map(|v| if v { 1u8 } else { 0u8 });
+            is_between(value, start, stop, &parse_bool)
+        },
+        RangeSelector::Timestamp(name) => {
+            let value = VALUES_TIME
+                .may_load(storage, (id, name))?
+                .map(|v| v.nanos());
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Int32(name) => {
+            let value = VALUES_I32.may_load(storage, (id, name))?;
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Uint8(name) => {
+            let value = VALUES_U8.may_load(storage, (id, name))?;
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Uint16(name) => {
+            let value = VALUES_U16.may_load(storage, (id, name))?;
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Uint32(name) => {
+            let value = VALUES_U32.may_load(storage, (id, name))?;
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Uint64(name) => {
+            let value = VALUES_U64.may_load(storage, (id, name))?.map(|v| v.u64());
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Uint128(name) => {
+            let value = VALUES_U128.may_load(storage, (id, name))?.map(|v| v.u128());
+            is_between(value, start, stop, &parse)
+        },
+        RangeSelector::Binary(name) => {
+            let value = VALUES_BINARY
+                .may_load(storage, (id, name))?
+                .map(|v| v.to_vec());
+            is_between(value, start, stop, &parse_binary)
+        },
+        RangeSelector::Composite(name) => {
+            let meta = match COMPOSITE_INDEX_METADATA.may_load(storage, name.to_owned())? {
+                Some(meta) => meta,
+                None => return Ok(false),
+            };
+            let prefix = filter.prefix.clone().unwrap_or_default();
+            let (min, max) = build_range_keys(&meta, &prefix, start, stop, max_str_len)?;
+            Ok(match load_key(storage, id, &meta, max_str_len)? {
+                Some(key) => min <= key && key <= max,
+                None => false,
+            })
+        },
+    }
+}
+
+/// Check whether a contract's value in a custom String index starts with the
+/// given prefix.
+fn is_prefixed_by(
+    storage: &dyn Storage,
+    id: ContractID,
+    filter: &RangeFilter,
+    prefix: &str,
+) -> Result<bool, ContractError> {
+    let name = match &filter.index {
+        RangeSelector::String(name) => name,
+        _ => {
+            return Err(ContractError::ValidationError {
+                reason: "StartsWith only applies to string indices".to_owned(),
+            })
+        },
+    };
+    Ok(match VALUES_STRING.may_load(storage, (id, name))? {
+        Some(value) if is_case_insensitive(storage, name)? => {
+            value.to_lowercase().starts_with(&prefix.to_lowercase())
+        },
+        Some(value) => value.starts_with(prefix),
+        None => false,
+    })
+}
+
+/// Check whether a value lies within an inclusive range, where missing start
+/// or stop values leave the range open-ended. Missing values never match.
+fn is_between<T: PartialOrd>(
+    maybe_value: Option<T>,
+    start: Option<String>,
+    stop: Option<String>,
+    fn_parse: &dyn Fn(String) -> Result<T, ContractError>,
+) -> Result<bool, ContractError> {
+    let value = match maybe_value {
+        Some(value) => value,
+        None => return Ok(false),
+    };
+    if let Some(raw_start) = start {
+        if value < fn_parse(raw_start)? {
+            return Ok(false);
+        }
+    }
+    if let Some(raw_stop) = stop {
+        if value > fn_parse(raw_stop)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
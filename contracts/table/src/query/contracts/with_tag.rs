@@ -1,18 +1,61 @@
 use std::marker::PhantomData;
 
 use crate::error::ContractError;
-use crate::msg::{ContractRecord, ContractsByTagResponse, TagQueryParams};
-use crate::state::{load_contract_records, CONFIG_STR_MAX_LEN, IX_TAG};
-use crate::util::pad;
-use cosmwasm_std::{Deps, Order, Uint64};
+use crate::msg::{
+    ContractRecord, ContractsByTagMatchResponse, ContractsByTagResponse, Cursor, Range,
+    TagMatchQueryParams, TagQueryParams, Target,
+};
+use crate::state::{
+    exclude_expired, load_contract_records, resolve_query_partitions, CONFIG_STR_MAX_LEN, IX_TAG,
+};
+use crate::util::{increment_prefix, pad, trim_padding};
+use cosmwasm_std::{Deps, Env, Order, Uint64};
 use cw_storage_plus::Bound;
 
-/// Paginate over the tags + tag metadata within a given partition.
+use super::merge::{merge_entries, PartitionEntries};
+
+/// Paginate over the contracts with the given tag within the given partition
+/// or set of partitions.
 pub fn with_tag(
     deps: Deps,
+    env: Env,
     params: TagQueryParams,
 ) -> Result<ContractsByTagResponse, ContractError> {
-    // let exact = params.exact.unwrap_or(true);
+    // Since every entry has the same tag, the contract ID alone is enough to
+    // resume from.
+    let cursor = params
+        .cursor
+        .map(|id| (params.partition, params.tag.clone(), id));
+
+    let resp = with_tag_match(
+        deps,
+        env,
+        TagMatchQueryParams {
+            tag: Target::Equals(params.tag),
+            cursor,
+            desc: params.desc,
+            limit: params.limit,
+            partition: params.partition,
+            partitions: params.partitions,
+            details: params.details,
+            select: params.select,
+            include_expired: params.include_expired,
+        },
+    )?;
+
+    Ok(ContractsByTagResponse {
+        contracts: resp.contracts,
+        cursor: resp.cursor.map(|(_, _, id)| id),
+    })
+}
+
+/// Paginate over the contracts with tags matching the given target within the
+/// given partition or set of partitions.
+pub fn with_tag_match(
+    deps: Deps,
+    env: Env,
+    params: TagMatchQueryParams,
+) -> Result<ContractsByTagMatchResponse, ContractError> {
     let limit = params.limit.unwrap_or(20).clamp(1, 100) as usize;
     let desc = params.desc.unwrap_or(false);
     let order = if desc {
@@ -20,44 +63,98 @@ pub fn with_tag(
     } else {
         Order::Ascending
     };
-    let (min, max) = match order {
-        Order::Ascending => (
-            params
-                .cursor
-                .and_then(|start_id| Some(Bound::Exclusive((start_id.u64(), PhantomData)))),
-            None,
-        ),
-        Order::Descending => (
-            None,
-            params
-                .cursor
-                .and_then(|start_id| Some(Bound::Exclusive((start_id.u64(), PhantomData)))),
+
+    let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
+
+    // Tags are stored padded to the max string length, so bounds are built from
+    // padded tags. When matching a prefix, the stop tag is the exclusive upper
+    // bound of all tags starting with it.
+    let mut tag_prefix: Option<String> = None;
+    let (start_tag, stop_tag, is_stop_inclusive) = match params.tag {
+        Target::Equals(tag) => {
+            let tag = pad(&tag, max_str_len);
+            (Some(tag.clone()), Some(tag), true)
+        },
+        Target::Between(Range { start, stop }) => (
+            start.map(|s| pad(&s, max_str_len)),
+            stop.map(|s| pad(&s, max_str_len)),
+            true,
         ),
+        Target::StartsWith(prefix) => {
+            let stop = increment_prefix(&prefix).map(|s| pad(&s, max_str_len));
+            tag_prefix = Some(prefix.clone());
+            (Some(pad(&prefix, max_str_len)), stop, false)
+        },
     };
 
-    let max_str_len = CONFIG_STR_MAX_LEN.load(deps.storage)? as usize;
-    let cannonical_tag = pad(&params.tag, max_str_len);
+    // The cursor's (tag, contract ID) resumes each partition's iterator.
+    let cursor_tag = params
+        .cursor
+        .as_ref()
+        .map(|(_, tag, id)| (pad(tag, max_str_len), id.u64()));
 
-    // Collect contract ids, cursor and add them to push them on return vals
-    let mut contract_ids: Vec<u64> = Vec::with_capacity(4);
-    let mut cursor: Option<Uint64> = None;
-
-    for maybe_contract_id in IX_TAG
-        .prefix((params.partition, &cannonical_tag))
-        .keys(deps.storage, min, max, order)
-        .take(limit)
-    {
-        let contract_id = maybe_contract_id?;
-        contract_ids.push(contract_id);
+    let partitions = resolve_query_partitions(deps.storage, params.partition, &params.partitions)?;
+    let mut iters: Vec<PartitionEntries<String>> = Vec::with_capacity(partitions.len());
+
+    for partition in partitions.iter().copied() {
+        let start_bound = start_tag
+            .as_ref()
+            .map(|tag| Bound::Inclusive(((tag, u64::MIN), PhantomData)));
+        let stop_bound = stop_tag.as_ref().map(|tag| {
+            if is_stop_inclusive {
+                Bound::Inclusive(((tag, u64::MAX), PhantomData))
+            } else {
+                Bound::Exclusive(((tag, u64::MIN), PhantomData))
+            }
+        });
+        let cursor_bound = cursor_tag
+            .as_ref()
+            .map(|(tag, id)| Bound::Exclusive(((tag, *id), PhantomData)));
+
+        let (min, max) = match order {
+            Order::Ascending => (cursor_bound.or(start_bound), stop_bound),
+            Order::Descending => (start_bound, cursor_bound.or(stop_bound)),
+        };
+
+        let tag_prefix = tag_prefix.clone();
+
+        iters.push(Box::new(
+            IX_TAG
+                .sub_prefix(partition)
+                .keys(deps.storage, min, max, order)
+                .take_while(move |result| match (result, &tag_prefix) {
+                    (Ok((tag, _)), Some(prefix)) => tag.starts_with(prefix.as_str()),
+                    _ => true,
+                })
+                .map(move |result| result.map(|(tag, id)| (partition, tag, id))),
+        ));
     }
 
-    if contract_ids.len() == limit {
-        cursor = Some(Uint64::from(contract_ids.last().unwrap().clone()));
+    // Collect contract ids, cursor and add them to push them on return vals
+    let entries = merge_entries(iters, order, limit)?;
+    let mut cursor: Option<Cursor> = None;
+
+    if entries.len() == limit {
+        if let Some((partition, tag, contract_id)) = entries.last() {
+            cursor = Some((*partition, trim_padding(tag), Uint64::from(*contract_id)));
+        }
     }
 
+    let contract_ids: Vec<u64> = entries.into_iter().map(|(_, _, id)| id).collect();
+    let contract_ids = exclude_expired(
+        deps.storage,
+        contract_ids,
+        env.block.time,
+        params.include_expired,
+    )?;
+
     // Load contract records from IDs
-    let contracts: Vec<ContractRecord> =
-        load_contract_records(deps.storage, &contract_ids, params.details)?;
+    let contracts: Vec<ContractRecord> = load_contract_records(
+        deps.storage,
+        &contract_ids,
+        params.details,
+        params.select.as_deref(),
+    )?;
 
-    Ok(ContractsByTagResponse { contracts, cursor })
+    Ok(ContractsByTagMatchResponse { contracts, cursor })
 }
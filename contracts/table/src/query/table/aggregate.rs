@@ -0,0 +1,236 @@
+use std::marker::PhantomData;
+
+use crate::aggregates::load_aggregate;
+use crate::error::ContractError;
+use crate::msg::{
+    AggregateResponse, IndexMetadata, IndexType, IndexValue, PartitionSet,
+    TableAggregateQueryParams,
+};
+use crate::state::{resolve_query_partitions, CustomIndexMap, PartitionID, INDEX_METADATA};
+use crate::util::{build_index_storage_key, parse};
+use cosmwasm_std::{Deps, Int256, Order, SignedDecimal256, Storage, Timestamp, Uint64};
+use cw_storage_plus::{Bound, KeyDeserialize, Map, Prefixer, PrimaryKey};
+
+/// Max number of index entries scanned when an aggregate can't be read from
+/// the index's running aggregates.
+pub const MAX_SCAN_SIZE: usize = 10_000;
+
+/// Compute the count, sum, min, max and average of the values in a numeric
+/// custom index, within an optional range of values.
+pub fn query_aggregate(
+    deps: Deps,
+    params: TableAggregateQueryParams,
+) -> Result<AggregateResponse, ContractError> {
+    let meta = INDEX_METADATA
+        .may_load(deps.storage, params.index.clone())?
+        .ok_or_else(|| ContractError::ValidationError {
+            reason: format!("index {} does not exist", params.index),
+        })?;
+
+    if !meta.index_type.is_numeric() {
+        return Err(ContractError::ValidationError {
+            reason: format!("index {} is not a numeric index", meta.name),
+        });
+    }
+
+    let partitions = match params.partition {
+        Some(partition) => vec![partition],
+        None => resolve_query_partitions(deps.storage, 0, &Some(PartitionSet::All))?,
+    };
+
+    let is_bounded = params.range.is_some();
+    let (raw_start, raw_stop) = params
+        .range
+        .map(|range| (range.start, range.stop))
+        .unwrap_or_default();
+
+    let storage_key = &build_index_storage_key(&meta.name);
+    let store = deps.storage;
+
+    match meta.index_type {
+        IndexType::Timestamp => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, u64::MIN)?,
+            parse_or(raw_stop, u64::MAX)?,
+            &|v| IndexValue::Timestamp(Timestamp::from_nanos(v)),
+        ),
+        IndexType::Int32 => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, i32::MIN)?,
+            parse_or(raw_stop, i32::MAX)?,
+            &IndexValue::Int32,
+        ),
+        IndexType::Uint8 => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, u8::MIN)?,
+            parse_or(raw_stop, u8::MAX)?,
+            &IndexValue::Uint8,
+        ),
+        IndexType::Uint16 => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, u16::MIN)?,
+            parse_or(raw_stop, u16::MAX)?,
+            &IndexValue::Uint16,
+        ),
+        IndexType::Uint32 => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, u32::MIN)?,
+            parse_or(raw_stop, u32::MAX)?,
+            &IndexValue::Uint32,
+        ),
+        IndexType::Uint64 => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, u64::MIN)?,
+            parse_or(raw_stop, u64::MAX)?,
+            &|v| IndexValue::Uint64(v.into()),
+        ),
+        IndexType::Uint128 => aggregate(
+            store,
+            &meta,
+            storage_key,
+            &partitions,
+            is_bounded,
+            parse_or(raw_start, u128::MIN)?,
+            parse_or(raw_stop, u128::MAX)?,
+            &|v| IndexValue::Uint128(v.into()),
+        ),
+        IndexType::String | IndexType::Bool | IndexType::Binary => {
+            Err(ContractError::UnexpectedError {
+                reason: format!("index {} is not a numeric index", meta.name),
+            })
+        },
+    }
+}
+
+/// Aggregate the given partitions of a custom index between inclusive start
+/// and stop values. Count and sum are read from running aggregates when the
+/// range is unbounded and the index keeps them; otherwise, entries are scanned.
+#[allow(clippy::too_many_arguments)]
+fn aggregate<'a, T>(
+    storage: &dyn Storage,
+    meta: &IndexMetadata,
+    storage_key: &'a str,
+    partitions: &[PartitionID],
+    is_bounded: bool,
+    start: T,
+    stop: T,
+    to_value: &dyn Fn(T) -> IndexValue,
+) -> Result<AggregateResponse, ContractError>
+where
+    T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize<Output = T>,
+    T: Into<Int256> + Ord + Copy + 'static,
+{
+    let index: CustomIndexMap<T> = Map::new(storage_key);
+    let use_running_aggregates = !is_bounded && meta.aggregate.unwrap_or(false);
+
+    let mut count = Uint64::zero();
+    let mut sum = Int256::zero();
+    let mut min: Option<T> = None;
+    let mut max: Option<T> = None;
+    let mut n_scanned: usize = 0;
+
+    for partition in partitions.iter().copied() {
+        let build_bounds = || {
+            (
+                Some(Bound::Inclusive((
+                    (partition, start, u64::MIN),
+                    PhantomData,
+                ))),
+                Some(Bound::Inclusive(((partition, stop, u64::MAX), PhantomData))),
+            )
+        };
+
+        let (lo, hi) = build_bounds();
+        if let Some((_, v, _)) = index
+            .keys(storage, lo, hi, Order::Ascending)
+            .next()
+            .transpose()?
+        {
+            min = Some(min.map_or(v, |prev| prev.min(v)));
+        }
+
+        let (lo, hi) = build_bounds();
+        if let Some((_, v, _)) = index
+            .keys(storage, lo, hi, Order::Descending)
+            .next()
+            .transpose()?
+        {
+            max = Some(max.map_or(v, |prev| prev.max(v)));
+        }
+
+        if use_running_aggregates {
+            let agg = load_aggregate(storage, &meta.name, partition)?;
+            count += agg.count;
+            sum += agg.sum;
+            continue;
+        }
+
+        let (lo, hi) = build_bounds();
+        for result in index.keys(storage, lo, hi, Order::Ascending) {
+            let (_, v, _) = result?;
+            n_scanned += 1;
+            if n_scanned > MAX_SCAN_SIZE {
+                return Err(ContractError::ValidationError {
+                    reason: format!(
+                        "aggregate exceeds {} entries; narrow the range or enable running \
+                         aggregates for index {}",
+                        MAX_SCAN_SIZE, meta.name
+                    ),
+                });
+            }
+            count += Uint64::one();
+            sum += v.into();
+        }
+    }
+
+    let avg = if count.is_zero() {
+        None
+    } else {
+        Some(
+            SignedDecimal256::checked_from_ratio(sum, Int256::from(count)).map_err(|e| {
+                ContractError::UnexpectedError {
+                    reason: e.to_string(),
+                }
+            })?,
+        )
+    };
+
+    Ok(AggregateResponse {
+        count,
+        sum,
+        min: min.map(to_value),
+        max: max.map(to_value),
+        avg,
+    })
+}
+
+fn parse_or<T: std::str::FromStr>(
+    raw_value: Option<String>,
+    default: T,
+) -> Result<T, ContractError> {
+    raw_value.map_or(Ok(default), parse)
+}
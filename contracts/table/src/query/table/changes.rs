@@ -0,0 +1,38 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::models::Change;
+use crate::msg::{ChangesResponse, TableChangesQueryParams};
+use crate::state::{CHANGES, CHANGE_SEQ};
+use cosmwasm_std::{Deps, Order, StdResult, Uint64};
+use cw_storage_plus::Bound;
+
+/// Paginate over the change log in order of sequence number, starting after
+/// the given one.
+pub fn query_changes(
+    deps: Deps,
+    params: TableChangesQueryParams,
+) -> Result<ChangesResponse, ContractError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200) as usize;
+    let min = params
+        .since_seq
+        .map(|seq| Bound::Exclusive((seq.u64(), PhantomData)));
+
+    let changes: Vec<Change> = CHANGES
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(_, change)| change))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let cursor = if changes.len() == limit {
+        changes.last().map(|change| change.seq)
+    } else {
+        None
+    };
+
+    Ok(ChangesResponse {
+        changes,
+        cursor,
+        latest_seq: CHANGE_SEQ.may_load(deps.storage)?.unwrap_or(Uint64::zero()),
+    })
+}
@@ -0,0 +1,67 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::msg::{CodeIdView, CodeIdsResponse, TableCodeIdsQueryParams};
+use crate::state::{CODE_ID_ALLOWLIST, CODE_ID_SIZES};
+use cosmwasm_std::{Deps, Order, Uint64};
+use cw_storage_plus::Bound;
+
+pub const PAGE_SIZE: usize = 50;
+
+/// Return allowed code IDs, along with the number of contracts in the table
+/// created from each.
+pub fn query_code_ids(
+    deps: Deps,
+    params: TableCodeIdsQueryParams,
+) -> Result<CodeIdsResponse, ContractError> {
+    let mut code_ids: Vec<CodeIdView> = Vec::with_capacity(4);
+
+    let limit = params
+        .limit
+        .map(|n| n as usize)
+        .unwrap_or(PAGE_SIZE)
+        .clamp(1, PAGE_SIZE);
+    let desc = params.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+    let (min, max) = match order {
+        Order::Ascending => (
+            params
+                .cursor
+                .map(|id| Bound::Exclusive((id.u64(), PhantomData))),
+            None,
+        ),
+        Order::Descending => (
+            None,
+            params
+                .cursor
+                .map(|id| Bound::Exclusive((id.u64(), PhantomData))),
+        ),
+    };
+
+    for result in CODE_ID_ALLOWLIST
+        .range(deps.storage, min, max, order)
+        .take(limit)
+    {
+        let (code_id, metadata) = result?;
+        let size = CODE_ID_SIZES
+            .may_load(deps.storage, code_id)?
+            .unwrap_or_default();
+        code_ids.push(CodeIdView {
+            code_id: code_id.into(),
+            metadata,
+            size,
+        });
+    }
+
+    let cursor: Option<Uint64> = if code_ids.len() == limit {
+        code_ids.last().map(|view| view.code_id)
+    } else {
+        None
+    };
+
+    Ok(CodeIdsResponse { code_ids, cursor })
+}
@@ -0,0 +1,44 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::msg::{CompositeIndexMetadata, CompositeIndicesResponse, TableIndicesQueryParams};
+use crate::state::COMPOSITE_INDEX_METADATA;
+use cosmwasm_std::{Deps, Order};
+use cw_storage_plus::Bound;
+
+pub const PAGE_SIZE: usize = 50;
+
+/// Return composite index metadata records, created via create_composite_index.
+pub fn query_composite_indices(
+    deps: Deps,
+    params: TableIndicesQueryParams,
+) -> Result<CompositeIndicesResponse, ContractError> {
+    let mut indices: Vec<CompositeIndexMetadata> = Vec::with_capacity(4);
+
+    let desc = params.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+    let bound = params
+        .cursor
+        .map(|start_name| Bound::Exclusive((start_name, PhantomData)));
+    let (min, max) = match order {
+        Order::Ascending => (bound, None),
+        Order::Descending => (None, bound),
+    };
+
+    for result in COMPOSITE_INDEX_METADATA
+        .range(deps.storage, min, max, order)
+        .take(PAGE_SIZE)
+    {
+        let (_, meta) = result?;
+        indices.push(meta);
+    }
+
+    // Get Cursor for next page
+    let cursor: Option<String> = indices.last().map(|last| last.name.clone());
+
+    Ok(CompositeIndicesResponse { indices, cursor })
+}
@@ -0,0 +1,51 @@
+use crate::error::ContractError;
+use crate::msg::{CreationFailureView, CreationFailuresResponse, TableCreationFailuresQueryParams};
+use crate::state::CREATION_FAILURES;
+use cosmwasm_std::{Deps, Order, Uint64};
+use cw_storage_plus::Bound;
+
+pub const PAGE_SIZE: usize = 50;
+
+/// Return the failed instantiations recorded by best-effort CreateMany
+/// batches, by the reply job ID of each.
+pub fn query_creation_failures(
+    deps: Deps,
+    params: TableCreationFailuresQueryParams,
+) -> Result<CreationFailuresResponse, ContractError> {
+    let mut failures: Vec<CreationFailureView> = Vec::with_capacity(4);
+
+    let limit = params
+        .limit
+        .map(|n| n as usize)
+        .unwrap_or(PAGE_SIZE)
+        .clamp(1, PAGE_SIZE);
+    let desc = params.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+    let (min, max) = match order {
+        Order::Ascending => (params.cursor.map(|id| Bound::exclusive(id.u64())), None),
+        Order::Descending => (None, params.cursor.map(|id| Bound::exclusive(id.u64()))),
+    };
+
+    for result in CREATION_FAILURES
+        .range(deps.storage, min, max, order)
+        .take(limit)
+    {
+        let (job_id, failure) = result?;
+        failures.push(CreationFailureView {
+            job_id: job_id.into(),
+            failure,
+        });
+    }
+
+    let cursor: Option<Uint64> = if failures.len() == limit {
+        failures.last().map(|view| view.job_id)
+    } else {
+        None
+    };
+
+    Ok(CreationFailuresResponse { failures, cursor })
+}
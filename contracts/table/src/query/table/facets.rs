@@ -0,0 +1,77 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::facets::{decode_facet_value, encode_facet_value};
+use crate::msg::{FacetCount, FacetsResponse, TableFacetsQueryParams};
+use crate::state::{INDEX_FACET_COUNTS, INDEX_METADATA};
+use cosmwasm_std::{Deps, Order};
+use cw_storage_plus::Bound;
+
+pub const PAGE_SIZE: usize = 50;
+
+/// Return the number of contracts with each distinct value of a faceted custom
+/// index within the given partition.
+pub fn query_facets(
+    deps: Deps,
+    params: TableFacetsQueryParams,
+) -> Result<FacetsResponse, ContractError> {
+    let meta = INDEX_METADATA
+        .may_load(deps.storage, params.index.clone())?
+        .ok_or_else(|| ContractError::ValidationError {
+            reason: format!("index {} does not exist", params.index),
+        })?;
+
+    if !meta.facet.unwrap_or(false) {
+        return Err(ContractError::ValidationError {
+            reason: format!("index {} does not keep facet counts", meta.name),
+        });
+    }
+
+    let limit = params
+        .limit
+        .map(|n| n as usize)
+        .unwrap_or(PAGE_SIZE)
+        .clamp(1, PAGE_SIZE);
+    let desc = params.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+
+    let cursor_key = params
+        .cursor
+        .as_ref()
+        .map(|value| encode_facet_value(&meta, value))
+        .transpose()?;
+    let cursor_bound = cursor_key
+        .as_ref()
+        .map(|key| Bound::Exclusive((key.as_slice(), PhantomData)));
+
+    let (min, max) = match order {
+        Order::Ascending => (cursor_bound, None),
+        Order::Descending => (None, cursor_bound),
+    };
+
+    let mut facets: Vec<FacetCount> = Vec::with_capacity(8);
+
+    for result in INDEX_FACET_COUNTS
+        .prefix((&meta.name, params.partition))
+        .range(deps.storage, min, max, order)
+        .take(limit)
+    {
+        let (key, count) = result?;
+        facets.push(FacetCount {
+            value: decode_facet_value(&meta, &key)?,
+            count,
+        });
+    }
+
+    let cursor = if facets.len() == limit {
+        facets.last().map(|facet| facet.value.clone())
+    } else {
+        None
+    };
+
+    Ok(FacetsResponse { facets, cursor })
+}
@@ -0,0 +1,67 @@
+use std::marker::PhantomData;
+
+use crate::error::ContractError;
+use crate::models::ContractFlag;
+use crate::msg::{FlaggedContract, FlaggedResponse, TableFlaggedQueryParams};
+use crate::state::{load_contract_addr, CONTRACT_OPEN_FLAG_COUNTS};
+use crate::util::build_flags_storage_key;
+use cosmwasm_std::{Deps, Order, Uint64};
+use cw_storage_plus::{Bound, Deque};
+
+pub const PAGE_SIZE: usize = 50;
+
+/// Return contracts with open flags, along with their flag counts.
+pub fn query_flagged(
+    deps: Deps,
+    params: TableFlaggedQueryParams,
+) -> Result<FlaggedResponse, ContractError> {
+    let mut contracts: Vec<FlaggedContract> = Vec::with_capacity(4);
+    let mut cursor: Option<Uint64> = None;
+
+    let limit = params
+        .limit
+        .map(|n| n as usize)
+        .unwrap_or(PAGE_SIZE)
+        .clamp(1, PAGE_SIZE);
+    let desc = params.desc.unwrap_or(false);
+    let order = if desc {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+    let (min, max) = match order {
+        Order::Ascending => (
+            params
+                .cursor
+                .map(|id| Bound::Exclusive((id.u64(), PhantomData))),
+            None,
+        ),
+        Order::Descending => (
+            None,
+            params
+                .cursor
+                .map(|id| Bound::Exclusive((id.u64(), PhantomData))),
+        ),
+    };
+
+    for result in CONTRACT_OPEN_FLAG_COUNTS
+        .range(deps.storage, min, max, order)
+        .take(limit)
+    {
+        let (contract_id, n_open) = result?;
+        let flags_deque_key = build_flags_storage_key(contract_id);
+        let flags: Deque<ContractFlag> = Deque::new(flags_deque_key.as_str());
+        contracts.push(FlaggedContract {
+            address: load_contract_addr(deps.storage, contract_id)?,
+            n_open,
+            n_total: flags.len(deps.storage)?,
+        });
+        cursor = Some(contract_id.into());
+    }
+
+    if contracts.len() < limit {
+        cursor = None;
+    }
+
+    Ok(FlaggedResponse { contracts, cursor })
+}
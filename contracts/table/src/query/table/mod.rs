@@ -1,8 +1,22 @@
+mod aggregate;
+mod changes;
+mod code_ids;
+mod composite_indices;
+mod creation_failures;
+mod facets;
+mod flagged;
 mod groups;
 mod indices;
 mod partitions;
 mod tags;
 
+pub use aggregate::query_aggregate as aggregate;
+pub use changes::query_changes as changes;
+pub use code_ids::query_code_ids as code_ids;
+pub use composite_indices::query_composite_indices as composite_indices;
+pub use creation_failures::query_creation_failures as creation_failures;
+pub use facets::query_facets as facets;
+pub use flagged::query_flagged as flagged;
 pub use groups::query_groups as groups;
 pub use indices::query_indices as indices;
 pub use partitions::query_partitions as partitions;
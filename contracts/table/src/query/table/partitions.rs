@@ -59,6 +59,7 @@ pub fn query_partitions(
             size,
             description: meta.description,
             name: meta.name,
+            policy: meta.policy,
         })
     }
 
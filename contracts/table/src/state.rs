@@ -1,19 +1,26 @@
+use std::collections::BTreeMap;
+
 use crate::context::Context;
 use crate::models::{
-    ContractMetadataView, ContractMetadataViewDetails, Details, DynamicContractMetadata, ReplyJob,
+    BulkUpdateJob, Change, ContractArchive, ContractMetadataView, ContractMetadataViewDetails,
+    CreationFailure, Details, DynamicContractMetadata, FlagResolution, IndexAggregate,
+    MigrationFailure, ReplyJob, Subscription,
 };
 use crate::msg::{
-    Config, ContractRecord, GroupCreationParams, GroupMetadata, IndexCreationParams, IndexMetadata,
-    IndexType, InstantiateMsg, PartitionCreationParams, PartitionMetadata, PartitionSelector,
-    TableInfo,
+    AutoSuspensionConfig, ChangeRetention, CodeIdMetadata, CompositeIndexColumn,
+    CompositeIndexCreationParams, CompositeIndexMetadata, Config, ContractRecord,
+    CreationFeeConfig, GroupCreationParams, GroupMetadata, IndexCreationParams, IndexMetadata,
+    IndexStatus, IndexType, InstantiateMsg, KeyValue, PartitionCreationParams, PartitionMetadata,
+    PartitionSelector, PartitionSet, TableInfo,
 };
+use crate::util::trim_padding;
 use crate::{error::ContractError, models::ContractMetadata};
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, DepsMut, Env, MessageInfo, Order, Storage, Timestamp, Uint128,
-    Uint64,
+    to_json_binary, Addr, Binary, DepsMut, Env, MessageInfo, Order, StdResult, Storage, Timestamp,
+    Uint128, Uint64,
 };
 use cw_acl::client::Acl;
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Item, KeyDeserialize, Map, Prefixer, PrimaryKey};
 use gelotto_core::models::owner::Owner;
 
 // TODO: store size of each partition Map<u16, Uint64>
@@ -24,15 +31,23 @@ pub type GroupID = u32;
 pub type ContractID = u64;
 pub type IndexMap<K> = Map<'static, K, u8>;
 pub type CustomIndexMap<'a, T> = Map<'a, (PartitionID, T, ContractID), u8>;
+pub type CompositeIndexMap<'a> = Map<'a, (PartitionID, &'a [u8], ContractID), u8>;
 
 // Marker/dummy value for IndexMap values
 pub const X: u8 = 1;
 
+// Max number of partitions a single query can merge results from.
+pub const MAX_QUERY_PARTITIONS: usize = 50;
+
 // Table contract config settings:
 pub const CONFIG_OWNER: Item<Owner> = Item::new("owner");
 pub const CONFIG_CODE_ID_ALLOWLIST_ENABLED: Item<bool> = Item::new("code_id_allowlist_enabled");
 pub const CONFIG_BACKUP: Item<Binary> = Item::new("config_backup");
 pub const CONFIG_STR_MAX_LEN: Item<u16> = Item::new("config_indexed_str_max_len");
+pub const CONFIG_AUTO_SUSPENSION: Item<AutoSuspensionConfig> = Item::new("config_auto_suspension");
+pub const CONFIG_CREATION_FEES: Item<CreationFeeConfig> = Item::new("config_creation_fees");
+pub const CONFIG_ARCHIVE_ON_DELETE: Item<bool> = Item::new("config_archive_on_delete");
+pub const CONFIG_CHANGE_RETENTION: Item<ChangeRetention> = Item::new("config_change_retention");
 
 // Top-level metadata describing what this cw-table is and contains.
 pub const TABLE_INFO: Item<TableInfo> = Item::new("table_info");
@@ -52,24 +67,79 @@ pub const CONTRACT_METADATA: Map<ContractID, ContractMetadata> = Map::new("contr
 pub const CONTRACT_DYN_METADATA: Map<ContractID, DynamicContractMetadata> =
     Map::new("contract_dyn_meta");
 
+// Archived contracts, which are kept out of all indices until restored. An
+// archived contract's address maps to its ID through ARCHIVED_ADDR_2_ID
+// instead of CONTRACT_ADDR_2_ID.
+pub const CONTRACT_ARCHIVES: Map<ContractID, ContractArchive> = Map::new("contract_archives");
+pub const ARCHIVED_ADDR_2_ID: Map<&Addr, ContractID> = Map::new("archived_addr_2_id");
+
 // Flags indicating that a given contract is suspended
 pub const CONTRACT_SUSPENSIONS: Map<ContractID, bool> = Map::new("contract_suspensions");
 
+// Number of unresolved flags per flagged contract. Flags are appended to each
+// contract's flag Deque in order, so the open flags are always the last N.
+pub const CONTRACT_OPEN_FLAG_COUNTS: Map<ContractID, u32> = Map::new("contract_open_flag_counts");
+
+// Flag resolutions, keyed by the length of the contract's flag Deque at the
+// time of resolution. Each resolves the flags positioned before it that aren't
+// resolved by an earlier one.
+pub const CONTRACT_FLAG_RESOLUTIONS: Map<(ContractID, u32), FlagResolution> =
+    Map::new("contract_flag_resolutions");
+
+// Combined weight of the open flags counting toward auto-suspension, which are
+// those positioned at or after the contract's flag window start.
+pub const CONTRACT_OPEN_FLAG_WEIGHTS: Map<ContractID, u64> = Map::new("contract_open_flag_weights");
+pub const CONTRACT_FLAG_WINDOW_STARTS: Map<ContractID, u32> =
+    Map::new("contract_flag_window_starts");
+
+// Position and weight of each sender's flag counting toward auto-suspension.
+// An entry no longer counts once its position falls before the window start.
+pub const CONTRACT_FLAGGERS: Map<(ContractID, &Addr), (u32, u32)> = Map::new("contract_flaggers");
+
 pub const CONTRACT_GROUP_IDS: IndexMap<(ContractID, GroupID)> = Map::new("contract_groups");
 
 // Lookup table for finding all tags associated with a contract ID
 pub const CONTRACT_TAGS: IndexMap<(ContractID, String)> = Map::new("contract_tags");
 
+// Time after which each expiring contract is excluded from queries and can be
+// swept from the table.
+pub const CONTRACT_EXPIRATIONS: Map<ContractID, Timestamp> = Map::new("contract_expirations");
+
 // Flag indicating that a given contract uses implements the lifecycle interface
 pub const CONTRACT_USES_LIFECYCLE_HOOKS: Map<u64, bool> = Map::new("lifecycle_hooks_toggles");
 
 // Jobs for processing in the cw reply entrypoint
 pub const REPLY_JOBS: Map<u64, ReplyJob> = Map::new("reply_jobs");
+
+// The most recent failed migration of each contract, cleared on success.
+pub const MIGRATION_FAILURES: Map<ContractID, MigrationFailure> = Map::new("migration_failures");
+
+// Change log of contracts, keyed by sequence number, and the latest sequence
+// number assigned.
+pub const CHANGES: Map<u64, Change> = Map::new("changes");
+pub const CHANGE_SEQ: Item<Uint64> = Item::new("change_seq");
+
+// Contracts subscribed to TableEvents.
+pub const SUBSCRIPTIONS: Map<&Addr, Subscription> = Map::new("subscriptions");
+
+// Max number of subscribers, bounding the submsgs added per event.
+pub const MAX_SUBSCRIBERS: usize = 20;
+
+// Max gas a subscriber may use to handle each event. A reply can't catch a
+// subscriber running out of the transaction's gas, only out of its own limit.
+pub const MAX_SUBSCRIBER_GAS_LIMIT: u64 = 1_000_000;
+
+// The current bulk update, if any, which is resumed batch by batch.
+pub const BULK_UPDATE_JOB: Item<BulkUpdateJob> = Item::new("bulk_update_job");
+
+// Failed instantiations from best-effort CreateMany batches, keyed by job ID.
+pub const CREATION_FAILURES: Map<u64, CreationFailure> = Map::new("creation_failures");
 pub const REPLY_JOB_ID_COUNTER: Item<Uint64> = Item::new("reply_job_id_counter");
 
 // Allow list, where the keys are the Code ID's that can be instantiated through
-// the create() API. Only used if the allowlist is enabled through config.
-pub const CODE_ID_ALLOWLIST: IndexMap<u64> = Map::new("code_id_allowlist");
+// the create() API. Only enforced if the allowlist is enabled through config,
+// though each code ID's creation defaults apply regardless.
+pub const CODE_ID_ALLOWLIST: Map<u64, CodeIdMetadata> = Map::new("code_id_allowlist");
 
 pub const PARTITION_ID_COUNTER: Item<PartitionID> = Item::new("partition_id_counter");
 pub const PARTITION_NAME_2_ID: Map<String, PartitionID> = Map::new("partition_name_2_id");
@@ -80,6 +150,9 @@ pub const PARTITION_METADATA: Map<PartitionID, PartitionMetadata> = Map::new("pa
 // Number of contracts in each partition.
 pub const PARTITION_SIZES: Map<PartitionID, Uint64> = Map::new("partition_sizes");
 
+// Number of contracts in the table, across all partitions, with each code ID.
+pub const CODE_ID_SIZES: Map<u64, Uint64> = Map::new("code_id_sizes");
+
 // Each contract can be associated with many tags. TAG_COUNTS records the total
 // number of contracts with which each tag is associated.
 pub const PARTITION_TAG_COUNTS: Map<(PartitionID, &String), u32> = Map::new("partition_tag_counts");
@@ -91,6 +164,25 @@ pub const CONTRACT_INDEX_TYPES: Map<(ContractID, &String), IndexType> =
 // Metadata for custom indices.
 pub const INDEX_METADATA: Map<String, IndexMetadata> = Map::new("index_metadata");
 
+// Running count and sum of the values in each partition of a custom index
+// created with aggregates enabled.
+pub const INDEX_AGGREGATES: Map<(&String, PartitionID), IndexAggregate> =
+    Map::new("index_aggregates");
+
+// Number of contracts with each distinct value in each partition of a custom
+// index created with facets enabled, keyed by the value's encoded index key.
+pub const INDEX_FACET_COUNTS: Map<(&String, PartitionID, &[u8]), u32> =
+    Map::new("index_facet_counts");
+
+// Metadata for composite indices, whose columns are existing custom indices.
+pub const COMPOSITE_INDEX_METADATA: Map<String, CompositeIndexMetadata> =
+    Map::new("composite_index_metadata");
+
+// Lookup table for finding the composite indices that include a given custom
+// index as one of their columns, like (column name, composite index name).
+pub const COMPOSITE_INDEX_COLUMNS: IndexMap<(String, String)> =
+    Map::new("composite_index_columns");
+
 // INDEX_* are built-in index maps owned and managed by this contract.
 pub const IX_CONTRACT_ID: IndexMap<(PartitionID, u64, ContractID)> = Map::new("ix_contract_id");
 pub const IX_CODE_ID: IndexMap<(PartitionID, u64, ContractID)> = Map::new("ix_code_id");
@@ -101,6 +193,10 @@ pub const IX_UPDATED_BY: IndexMap<(PartitionID, String, ContractID)> = Map::new(
 pub const IX_REV: IndexMap<(PartitionID, u64, ContractID)> = Map::new("ix_created_by");
 pub const IX_TAG: IndexMap<(PartitionID, &String, ContractID)> = Map::new("ix_tag");
 
+// Expiring contracts by expiration time. Unlike other built-in indices, this
+// one isn't partitioned, so that expired contracts can be swept table-wide.
+pub const IX_EXPIRES_AT: IndexMap<(u64, ContractID)> = Map::new("ix_expires_at");
+
 // Groups transcend partitions, i.e. two contracts may belong to the same group
 // despite beloning to separate partitions.
 pub const IX_GROUP: IndexMap<(GroupID, ContractID)> = Map::new("ix_group");
@@ -120,8 +216,8 @@ pub const VALUES_U64: Map<(ContractID, &String), Uint64> = Map::new("values_u64"
 pub const VALUES_U128: Map<(ContractID, &String), Uint128> = Map::new("values_u128");
 pub const VALUES_BINARY: Map<(ContractID, &String), Binary> = Map::new("values_binary");
 
-/// Relationships define an arbitrary M-N named relationship between a contract
-/// ID and an arbitrary Addr, like (contract_id, "winner", user_addr)
+// Relationships define an arbitrary M-N named relationship between a contract
+// ID and an arbitrary Addr, like (contract_id, "winner", user_addr)
 
 pub const UNIQUE: u8 = 1;
 pub const NOT_UNIQUE: u8 = 2;
@@ -134,6 +230,7 @@ pub const GROUP_METADATA: Map<GroupID, GroupMetadata> = Map::new("group_metadata
 pub const GROUP_ID_COUNTER: Item<GroupID> = Item::new("group_id_counter");
 pub const GROUP_IX_NAME: IndexMap<(String, GroupID)> = Map::new("group_ix_name");
 pub const GROUP_IX_CREATED_AT: IndexMap<(u64, GroupID)> = Map::new("group_ix_created_at");
+pub const GROUP_REPLACEMENTS: Map<GroupID, GroupID> = Map::new("group_replacements");
 
 pub fn initialize(ctx: Context, msg: InstantiateMsg) -> Result<(), ContractError> {
     let Context { deps, env, info } = ctx;
@@ -145,6 +242,26 @@ pub fn initialize(ctx: Context, msg: InstantiateMsg) -> Result<(), ContractError
     CONFIG_CODE_ID_ALLOWLIST_ENABLED.save(deps.storage, &msg.config.code_id_allowlist_enabled)?;
     CONFIG_STR_MAX_LEN.save(deps.storage, &msg.config.max_str_len)?;
 
+    if let Some(auto_suspension) = &msg.config.auto_suspension {
+        auto_suspension.validate()?;
+        CONFIG_AUTO_SUSPENSION.save(deps.storage, auto_suspension)?;
+    }
+
+    if let Some(creation_fees) = &msg.config.creation_fees {
+        creation_fees.validate(deps.api)?;
+        CONFIG_CREATION_FEES.save(deps.storage, creation_fees)?;
+    }
+
+    CONFIG_ARCHIVE_ON_DELETE.save(
+        deps.storage,
+        &msg.config.archive_on_delete.unwrap_or(false),
+    )?;
+
+    if let Some(change_retention) = &msg.config.change_retention {
+        change_retention.validate()?;
+        CONFIG_CHANGE_RETENTION.save(deps.storage, change_retention)?;
+    }
+
     CONTRACT_ID_COUNTER.save(deps.storage, &Uint64::zero())?;
     REPLY_JOB_ID_COUNTER.save(deps.storage, &Uint64::zero())?;
     GROUP_ID_COUNTER.save(deps.storage, &0)?;
@@ -155,6 +272,7 @@ pub fn initialize(ctx: Context, msg: InstantiateMsg) -> Result<(), ContractError
         vec![PartitionCreationParams {
             name: Some("Partition 1".to_owned()),
             description: None,
+            policy: None,
         }]
     }) {
         create_partition(deps.storage, env.block.time, &params)?;
@@ -164,6 +282,10 @@ pub fn initialize(ctx: Context, msg: InstantiateMsg) -> Result<(), ContractError
         create_index(deps.storage, params)?;
     }
 
+    for params in msg.composite_indices.unwrap_or_default() {
+        create_composite_index(deps.storage, params)?;
+    }
+
     for params in msg.groups.unwrap_or_default() {
         create_group(deps.storage, params, &info, &env)?;
     }
@@ -207,6 +329,23 @@ pub fn create_index(
     storage: &mut dyn Storage,
     params: IndexCreationParams,
 ) -> Result<IndexMetadata, ContractError> {
+    if params.case_insensitive.unwrap_or(false) && params.index_type != IndexType::String {
+        return Err(ContractError::ValidationError {
+            reason: format!("case-insensitive index {} must be a string index", params.name),
+        });
+    }
+    if params.aggregate.unwrap_or(false) && !params.index_type.is_numeric() {
+        return Err(ContractError::ValidationError {
+            reason: format!("aggregated index {} must be a numeric index", params.name),
+        });
+    }
+    // Existing contracts may already have values under the index's name, which
+    // are added to the index by RebuildIndex.
+    let is_empty = CONTRACT_METADATA
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_none();
+
     INDEX_METADATA.update(
         storage,
         params.name.clone(),
@@ -220,12 +359,119 @@ pub fn create_index(
                     size: Uint64::zero(),
                     index_type: params.index_type,
                     name: params.name,
+                    case_insensitive: params.case_insensitive,
+                    status: Some(if is_empty {
+                        IndexStatus::Ready
+                    } else {
+                        IndexStatus::Building {
+                            cursor: None,
+                            scanned: Uint64::zero(),
+                        }
+                    }),
+                    aggregate: params.aggregate,
+                    facet: params.facet,
                 })
             }
         },
     )
 }
 
+/// Does the custom index stored under the given key have the given entry?
+pub fn has_index_entry<'a, T>(
+    storage: &dyn Storage,
+    storage_key: &'a str,
+    key: (PartitionID, T, ContractID),
+) -> bool
+where
+    T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    let index: CustomIndexMap<T> = Map::new(storage_key);
+    index.has(storage, key)
+}
+
+/// Is the given custom index declared case-insensitive?
+pub fn is_case_insensitive(
+    storage: &dyn Storage,
+    index_name: &String,
+) -> Result<bool, ContractError> {
+    Ok(INDEX_METADATA
+        .may_load(storage, index_name.to_owned())?
+        .and_then(|meta| meta.case_insensitive)
+        .unwrap_or(false))
+}
+
+pub fn create_composite_index(
+    storage: &mut dyn Storage,
+    params: CompositeIndexCreationParams,
+) -> Result<CompositeIndexMetadata, ContractError> {
+    if !(2..=3).contains(&params.columns.len()) {
+        return Err(ContractError::ValidationError {
+            reason: "composite index must have 2 or 3 columns".to_owned(),
+        });
+    }
+    if INDEX_METADATA.has(storage, params.name.clone())
+        || COMPOSITE_INDEX_METADATA.has(storage, params.name.clone())
+    {
+        return Err(ContractError::NotAuthorized {
+            reason: format!("index {} already exists", params.name),
+        });
+    }
+
+    let mut columns: Vec<CompositeIndexColumn> = Vec::with_capacity(params.columns.len());
+
+    for column_name in params.columns.iter() {
+        if columns.iter().any(|c| c.name == *column_name) {
+            return Err(ContractError::ValidationError {
+                reason: format!("duplicate composite index column {}", column_name),
+            });
+        }
+        let (index_type, case_insensitive) =
+            match INDEX_METADATA.may_load(storage, column_name.clone())? {
+                Some(meta) => (meta.index_type, meta.case_insensitive),
+                None => {
+                    return Err(ContractError::ValidationError {
+                        reason: format!("index {} does not exist", column_name),
+                    })
+                },
+            };
+        if index_type == IndexType::Binary {
+            return Err(ContractError::ValidationError {
+                reason: format!("binary index {} cannot be a composite column", column_name),
+            });
+        }
+        COMPOSITE_INDEX_COLUMNS.save(storage, (column_name.clone(), params.name.clone()), &X)?;
+        columns.push(CompositeIndexColumn {
+            name: column_name.clone(),
+            index_type,
+            case_insensitive,
+        });
+    }
+
+    // Existing contracts are added to the index by RebuildIndex.
+    let is_empty = CONTRACT_METADATA
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_none();
+
+    let meta = CompositeIndexMetadata {
+        name: params.name,
+        size: Uint64::zero(),
+        columns,
+        status: Some(if is_empty {
+            IndexStatus::Ready
+        } else {
+            IndexStatus::Building {
+                cursor: None,
+                scanned: Uint64::zero(),
+            }
+        }),
+    };
+
+    COMPOSITE_INDEX_METADATA.save(storage, meta.name.clone(), &meta)?;
+
+    Ok(meta)
+}
+
 pub fn build_contract_metadata_view(
     storage: &dyn Storage,
     id: ContractID,
@@ -248,6 +494,7 @@ pub fn build_contract_metadata_view(
         partition: meta.partition,
         created_at: meta.created_at,
         updated_at,
+        expires_at: CONTRACT_EXPIRATIONS.may_load(storage, id)?,
         rev,
         details: if with_details {
             Some(ContractMetadataViewDetails {
@@ -298,6 +545,34 @@ pub fn ensure_partition_exists(
     Ok(())
 }
 
+/// Resolve the partitions targeted by a query, which is the given partition
+/// unless a set of partitions is also given.
+pub fn resolve_query_partitions(
+    storage: &dyn Storage,
+    partition: PartitionID,
+    partitions: &Option<PartitionSet>,
+) -> Result<Vec<PartitionID>, ContractError> {
+    let partitions: Vec<PartitionID> = match partitions {
+        None => vec![partition],
+        Some(PartitionSet::All) => PARTITION_METADATA
+            .keys(storage, None, None, Order::Ascending)
+            .take(MAX_QUERY_PARTITIONS + 1)
+            .collect::<StdResult<Vec<PartitionID>>>()?,
+        Some(PartitionSet::Only(ids)) => {
+            let mut ids = ids.clone();
+            ids.sort_unstable();
+            ids.dedup();
+            ids
+        },
+    };
+    if partitions.len() > MAX_QUERY_PARTITIONS {
+        return Err(ContractError::ValidationError {
+            reason: format!("cannot query more than {} partitions", MAX_QUERY_PARTITIONS),
+        });
+    }
+    Ok(partitions)
+}
+
 pub fn is_suspended(storage: &dyn Storage, contract_id: ContractID) -> Result<bool, ContractError> {
     if let Some(is_suspended) = CONTRACT_SUSPENSIONS.may_load(storage, contract_id)? {
         return Ok(is_suspended);
@@ -305,6 +580,54 @@ pub fn is_suspended(storage: &dyn Storage, contract_id: ContractID) -> Result<bo
     Ok(false)
 }
 
+pub fn is_expired(
+    storage: &dyn Storage,
+    contract_id: ContractID,
+    time: Timestamp,
+) -> Result<bool, ContractError> {
+    if let Some(expires_at) = CONTRACT_EXPIRATIONS.may_load(storage, contract_id)? {
+        return Ok(expires_at <= time);
+    }
+    Ok(false)
+}
+
+/// Remove expired contracts from the given contract IDs, unless expired
+/// contracts are to be included.
+pub fn exclude_expired(
+    storage: &dyn Storage,
+    contract_ids: Vec<ContractID>,
+    time: Timestamp,
+    include_expired: Option<bool>,
+) -> Result<Vec<ContractID>, ContractError> {
+    if include_expired.unwrap_or(false) {
+        return Ok(contract_ids);
+    }
+    let mut unexpired: Vec<ContractID> = Vec::with_capacity(contract_ids.len());
+    for id in contract_ids.into_iter() {
+        if !is_expired(storage, id, time)? {
+            unexpired.push(id);
+        }
+    }
+    Ok(unexpired)
+}
+
+/// Set or clear a contract's expiration time, replacing any previous one.
+pub fn set_expiration(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    expires_at: Option<Timestamp>,
+) -> Result<(), ContractError> {
+    if let Some(prev_expires_at) = CONTRACT_EXPIRATIONS.may_load(storage, contract_id)? {
+        IX_EXPIRES_AT.remove(storage, (prev_expires_at.nanos(), contract_id));
+        CONTRACT_EXPIRATIONS.remove(storage, contract_id);
+    }
+    if let Some(expires_at) = expires_at {
+        CONTRACT_EXPIRATIONS.save(storage, contract_id, &expires_at)?;
+        IX_EXPIRES_AT.save(storage, (expires_at.nanos(), contract_id), &X)?;
+    }
+    Ok(())
+}
+
 pub fn ensure_contract_not_suspended(
     storage: &dyn Storage,
     contract_id: ContractID,
@@ -317,6 +640,37 @@ pub fn ensure_contract_not_suspended(
     Ok(())
 }
 
+/// Stop counting a flag being removed from the contract's flag Deque toward
+/// auto-suspension.
+pub fn uncount_flag(
+    storage: &mut dyn Storage,
+    contract_id: ContractID,
+    position: u32,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    if let Some((flag_position, weight)) =
+        CONTRACT_FLAGGERS.may_load(storage, (contract_id, sender))?
+    {
+        if flag_position != position {
+            return Ok(());
+        }
+        CONTRACT_FLAGGERS.remove(storage, (contract_id, sender));
+        let window_start = CONTRACT_FLAG_WINDOW_STARTS
+            .may_load(storage, contract_id)?
+            .unwrap_or_default();
+        if position >= window_start {
+            if let Some(total) = CONTRACT_OPEN_FLAG_WEIGHTS.may_load(storage, contract_id)? {
+                CONTRACT_OPEN_FLAG_WEIGHTS.save(
+                    storage,
+                    contract_id,
+                    &total.saturating_sub(weight as u64),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn save_config(storage: &mut dyn Storage, config: &Config) -> Result<(), ContractError> {
     // Load and save existing config as backup. This can be restored by the
     // updated owner by executing the Restore msg.
@@ -327,6 +681,26 @@ pub fn save_config(storage: &mut dyn Storage, config: &Config) -> Result<(), Con
     // Overwrite existing config settings with new ones
     CONFIG_OWNER.save(storage, &config.owner)?;
     CONFIG_CODE_ID_ALLOWLIST_ENABLED.save(storage, &config.code_id_allowlist_enabled)?;
+
+    if let Some(auto_suspension) = &config.auto_suspension {
+        CONFIG_AUTO_SUSPENSION.save(storage, auto_suspension)?;
+    } else {
+        CONFIG_AUTO_SUSPENSION.remove(storage);
+    }
+
+    if let Some(creation_fees) = &config.creation_fees {
+        CONFIG_CREATION_FEES.save(storage, creation_fees)?;
+    } else {
+        CONFIG_CREATION_FEES.remove(storage);
+    }
+
+    CONFIG_ARCHIVE_ON_DELETE.save(storage, &config.archive_on_delete.unwrap_or(false))?;
+
+    if let Some(change_retention) = &config.change_retention {
+        CONFIG_CHANGE_RETENTION.save(storage, change_retention)?;
+    } else {
+        CONFIG_CHANGE_RETENTION.remove(storage);
+    }
     Ok(())
 }
 
@@ -335,9 +709,23 @@ pub fn load_config(storage: &dyn Storage) -> Result<Config, ContractError> {
         owner: CONFIG_OWNER.load(storage)?,
         code_id_allowlist_enabled: CONFIG_CODE_ID_ALLOWLIST_ENABLED.load(storage)?,
         max_str_len: CONFIG_STR_MAX_LEN.load(storage)?,
+        auto_suspension: CONFIG_AUTO_SUSPENSION.may_load(storage)?,
+        creation_fees: CONFIG_CREATION_FEES.may_load(storage)?,
+        archive_on_delete: CONFIG_ARCHIVE_ON_DELETE.may_load(storage)?,
+        change_retention: CONFIG_CHANGE_RETENTION.may_load(storage)?,
     })
 }
 
+pub fn create_reply_job(storage: &mut dyn Storage, job: ReplyJob) -> Result<u64, ContractError> {
+    let job_id: u64 = REPLY_JOB_ID_COUNTER
+        .update(storage, |n| -> Result<_, ContractError> {
+            Ok(n + Uint64::one())
+        })?
+        .into();
+    REPLY_JOBS.save(storage, job_id, &job)?;
+    Ok(job_id)
+}
+
 pub fn load_reply_job(storage: &dyn Storage, job_id: u64) -> Result<ReplyJob, ContractError> {
     if let Some(job) = REPLY_JOBS.may_load(storage, job_id)? {
         Ok(job)
@@ -374,12 +762,25 @@ pub fn load_contract_id(
     }
 }
 
+/// Load the ID of a contract, whether active or archived.
+pub fn load_any_contract_id(
+    storage: &dyn Storage,
+    contract_addr: &Addr,
+) -> Result<ContractID, ContractError> {
+    if let Some(id) = ARCHIVED_ADDR_2_ID.may_load(storage, contract_addr)? {
+        return Ok(id);
+    }
+    load_contract_id(storage, contract_addr)
+}
+
 pub fn load_next_contract_id(
     storage: &mut dyn Storage,
     contract_addr: &Addr,
 ) -> Result<u64, ContractError> {
-    // Make sure that the contract doesn't already exist.
-    if CONTRACT_ADDR_2_ID.has(storage, contract_addr) {
+    // Make sure that the contract doesn't already exist, even if archived.
+    if CONTRACT_ADDR_2_ID.has(storage, contract_addr)
+        || ARCHIVED_ADDR_2_ID.has(storage, contract_addr)
+    {
         return Err(ContractError::NotAuthorized {
             reason: "address already exists".to_owned(),
         });
@@ -392,7 +793,7 @@ pub fn load_next_contract_id(
         .into();
 
     CONTRACT_ADDR_2_ID.save(storage, contract_addr, &contract_id.into())?;
-    CONTRACT_ID_2_ADDR.save(storage, contract_id.into(), contract_addr)?;
+    CONTRACT_ID_2_ADDR.save(storage, contract_id, contract_addr)?;
 
     Ok(contract_id)
 }
@@ -401,14 +802,14 @@ pub fn create_relationship(
     storage: &mut dyn Storage,
     contract_id: ContractID,
     addr: &Addr,
-    name: &String,
+    name: &str,
 ) -> Result<(), ContractError> {
     REL_ADDR_2_ID.save(
         storage,
-        (addr.into(), name.clone(), contract_id.to_string()),
+        (addr.into(), name.to_owned(), contract_id.to_string()),
         &X,
     )?;
-    REL_ID_2_ADDR.save(storage, (contract_id, name.clone(), addr.to_string()), &X)?;
+    REL_ID_2_ADDR.save(storage, (contract_id, name.to_owned(), addr.to_string()), &X)?;
     Ok(())
 }
 
@@ -416,23 +817,81 @@ pub fn delete_relationship(
     storage: &mut dyn Storage,
     contract_id: ContractID,
     addr: &Addr,
-    cannonical_name: &String,
+    cannonical_name: &str,
 ) -> Result<(), ContractError> {
     REL_ADDR_2_ID.remove(
         storage,
         (
             addr.into(),
-            cannonical_name.clone(),
+            cannonical_name.to_owned(),
             contract_id.to_string(),
         ),
     );
     REL_ID_2_ADDR.remove(
         storage,
-        (contract_id, cannonical_name.clone(), addr.to_string()),
+        (contract_id, cannonical_name.to_owned(), addr.to_string()),
     );
     Ok(())
 }
 
+/// Add one contract to the count of contracts with the given code ID.
+pub fn increment_code_id_size(
+    storage: &mut dyn Storage,
+    code_id: u64,
+) -> Result<Uint64, ContractError> {
+    CODE_ID_SIZES.update(storage, code_id, |n| -> Result<_, ContractError> {
+        n.unwrap_or_default()
+            .checked_add(Uint64::one())
+            .map_err(|e| ContractError::UnexpectedError {
+                reason: e.to_string(),
+            })
+    })
+}
+
+/// Remove one contract from the count of contracts with the given code ID.
+pub fn decrement_code_id_size(
+    storage: &mut dyn Storage,
+    code_id: u64,
+) -> Result<(), ContractError> {
+    let n = CODE_ID_SIZES
+        .may_load(storage, code_id)?
+        .unwrap_or_default()
+        .checked_sub(Uint64::one())
+        .map_err(|_| ContractError::UnexpectedError {
+            reason: format!("trying to subtract from 0 count of code ID {}", code_id),
+        })?;
+    if n.is_zero() {
+        CODE_ID_SIZES.remove(storage, code_id);
+    } else {
+        CODE_ID_SIZES.save(storage, code_id, &n)?;
+    }
+    Ok(())
+}
+
+/// Recount the contracts with each code ID from the code ID index, replacing
+/// any existing counts. Run on migration, so that contracts created before the
+/// counts were kept are included.
+pub fn rebuild_code_id_sizes(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let mut sizes: BTreeMap<u64, u64> = BTreeMap::new();
+    for result in IX_CODE_ID.keys(storage, None, None, Order::Ascending) {
+        let (_, code_id, _) = result?;
+        *sizes.entry(code_id).or_default() += 1;
+    }
+
+    for code_id in CODE_ID_SIZES
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<u64>>>()?
+    {
+        CODE_ID_SIZES.remove(storage, code_id);
+    }
+
+    for (code_id, n) in sizes.into_iter() {
+        CODE_ID_SIZES.save(storage, code_id, &n.into())?;
+    }
+
+    Ok(())
+}
+
 pub fn increment_tag_count(
     storage: &mut dyn Storage,
     partition: PartitionID,
@@ -440,7 +899,7 @@ pub fn increment_tag_count(
 ) -> Result<u32, ContractError> {
     PARTITION_TAG_COUNTS.update(
         storage,
-        (partition, &cannonical_tag),
+        (partition, cannonical_tag),
         |n| -> Result<_, ContractError> {
             n.unwrap_or_default()
                 .checked_add(1)
@@ -461,7 +920,7 @@ pub fn decrement_tag_count(
 ) -> Result<u32, ContractError> {
     PARTITION_TAG_COUNTS.update(
         storage,
-        (partition, &cannonical_tag),
+        (partition, cannonical_tag),
         |n| -> Result<_, ContractError> {
             n.unwrap_or_default()
                 .checked_sub(1)
@@ -479,6 +938,7 @@ pub fn load_one_contract_record(
     storage: &dyn Storage,
     id: u64,
     maybe_detail_level: Option<Details>,
+    select: Option<&[String]>,
 ) -> Result<ContractRecord, ContractError> {
     let record = ContractRecord {
         address: load_contract_addr(storage, id)?,
@@ -491,14 +951,20 @@ pub fn load_one_contract_record(
         } else {
             None
         },
+        values: if let Some(names) = select {
+            Some(load_selected_values(storage, id, names)?)
+        } else {
+            None
+        },
     };
     Ok(record)
 }
 
 pub fn load_contract_records(
     storage: &dyn Storage,
-    contract_ids: &Vec<u64>,
+    contract_ids: &[u64],
     maybe_detail_level: Option<Details>,
+    select: Option<&[String]>,
 ) -> Result<Vec<ContractRecord>, ContractError> {
     let mut contracts: Vec<ContractRecord> = Vec::with_capacity(contract_ids.len());
 
@@ -514,6 +980,11 @@ pub fn load_contract_records(
             } else {
                 None
             },
+            values: if let Some(names) = select {
+                Some(load_selected_values(storage, *id, names)?)
+            } else {
+                None
+            },
         };
         contracts.push(record);
     }
@@ -521,6 +992,61 @@ pub fn load_contract_records(
     Ok(contracts)
 }
 
+/// Load a contract's values for the given custom indices, skipping any index
+/// that doesn't exist.
+pub fn load_selected_values(
+    storage: &dyn Storage,
+    contract_id: ContractID,
+    index_names: &[String],
+) -> Result<Vec<KeyValue>, ContractError> {
+    let mut values: Vec<KeyValue> = Vec::with_capacity(index_names.len());
+    for index_name in index_names.iter() {
+        let index_type = match CONTRACT_INDEX_TYPES.may_load(storage, (contract_id, index_name))? {
+            Some(index_type) => index_type,
+            None => match INDEX_METADATA.may_load(storage, index_name.to_owned())? {
+                Some(meta) => meta.index_type,
+                None => continue,
+            },
+        };
+        values.push(load_key_value(
+            storage,
+            contract_id,
+            index_name,
+            &index_type,
+        )?);
+    }
+    Ok(values)
+}
+
+/// Load a contract's value for the given custom index as a typed KeyValue. The
+/// value is None if the contract has no value for the index.
+pub fn load_key_value(
+    storage: &dyn Storage,
+    contract_id: ContractID,
+    index_name: &String,
+    index_type: &IndexType,
+) -> Result<KeyValue, ContractError> {
+    let key = (contract_id, index_name);
+    let name = index_name.to_owned();
+    Ok(match index_type {
+        IndexType::String => KeyValue::String(
+            name,
+            VALUES_STRING
+                .may_load(storage, key)?
+                .map(|v| trim_padding(&v)),
+        ),
+        IndexType::Bool => KeyValue::Bool(name, VALUES_BOOL.may_load(storage, key)?),
+        IndexType::Timestamp => KeyValue::Timestamp(name, VALUES_TIME.may_load(storage, key)?),
+        IndexType::Int32 => KeyValue::Int32(name, VALUES_I32.may_load(storage, key)?),
+        IndexType::Uint8 => KeyValue::Uint8(name, VALUES_U8.may_load(storage, key)?),
+        IndexType::Uint16 => KeyValue::Uint16(name, VALUES_U16.may_load(storage, key)?),
+        IndexType::Uint32 => KeyValue::Uint32(name, VALUES_U32.may_load(storage, key)?),
+        IndexType::Uint64 => KeyValue::Uint64(name, VALUES_U64.may_load(storage, key)?),
+        IndexType::Uint128 => KeyValue::Uint128(name, VALUES_U128.may_load(storage, key)?),
+        IndexType::Binary => KeyValue::Binary(name, VALUES_BINARY.may_load(storage, key)?),
+    })
+}
+
 pub fn resolve_partition_id(
     storage: &dyn Storage,
     selector: PartitionSelector,
@@ -559,7 +1085,7 @@ pub fn append_group(
                         reason: format!(
                             "Error incrementing group {} size: {}",
                             group_id,
-                            e.to_string()
+                            e
                         ),
                     }
                 })?;
@@ -596,7 +1122,7 @@ pub fn remove_from_group(
                         reason: format!(
                             "Error decrementing group {} size: {}",
                             group_id,
-                            e.to_string()
+                            e
                         ),
                     }
                 })?;
@@ -624,7 +1150,7 @@ pub fn load_contract_group_ids(
             reason: format!(
                 "error loading contract {} group ids: {}",
                 contract_id,
-                e.to_string()
+                e
             ),
         })?;
         group_ids.push(group_id);
@@ -660,6 +1186,7 @@ pub fn create_partition(
                     description: params.description.clone(),
                     created_at: time,
                     name,
+                    policy: params.policy.clone(),
                 })
             }
         },
@@ -681,6 +1208,35 @@ pub fn exists_contract_address(storage: &dyn Storage, addr: &Addr) -> bool {
     CONTRACT_ADDR_2_ID.has(storage, addr)
 }
 
+pub fn incr_decr_composite_index_size(
+    storage: &mut dyn Storage,
+    index_name: &String,
+    is_positive: bool,
+) -> Result<(), ContractError> {
+    COMPOSITE_INDEX_METADATA.update(
+        storage,
+        index_name.clone(),
+        |maybe_meta| -> Result<_, ContractError> {
+            if let Some(mut meta) = maybe_meta {
+                meta.size = if is_positive {
+                    meta.size.checked_add(Uint64::one())
+                } else {
+                    meta.size.checked_sub(Uint64::one())
+                }
+                .map_err(|_| ContractError::UnexpectedError {
+                    reason: format!("Overflow updating composite index {} size", index_name),
+                })?;
+                Ok(meta)
+            } else {
+                Err(ContractError::UnexpectedError {
+                    reason: format!("Composite index {} not found", index_name),
+                })
+            }
+        },
+    )?;
+    Ok(())
+}
+
 pub fn incr_decr_index_size(
     storage: &mut dyn Storage,
     index_name: &String,
@@ -0,0 +1,94 @@
+use cosmwasm_std::{to_json_binary, Addr, Env, Order, ReplyOn, Storage, SubMsg, WasmMsg};
+
+use crate::{
+    error::ContractError,
+    events::{TableEvent, TableEventArgs, TableEventMsgEnvelope},
+    state::{ContractID, CONTRACT_METADATA, IX_GROUP, SUBSCRIPTIONS},
+};
+
+// Reply ID of submsgs sent to subscribers. Reply job IDs start at 1, so this
+// never collides with one.
+pub const EVENT_REPLY_ID: u64 = 0;
+
+pub fn build_event_args(
+    env: &Env,
+    contract: &Addr,
+    contract_id: ContractID,
+    initiator: &Addr,
+) -> TableEventArgs {
+    TableEventArgs {
+        table: env.contract.address.clone(),
+        contract: contract.clone(),
+        id: contract_id.into(),
+        initiator: initiator.clone(),
+    }
+}
+
+/// Build a submsg sending the event to each subscriber to its kind whose
+/// filters match the contract. Errors in subscribers are caught in reply, so
+/// they never revert the table's own changes. This must be called before a
+/// contract's metadata is deleted.
+pub fn build_event_submsgs(
+    storage: &dyn Storage,
+    event: TableEvent,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let kind = event.kind();
+    let (contract_id, partitions) = match &event {
+        TableEvent::Move(args) => (args.id.u64(), vec![args.src, args.dst]),
+        TableEvent::Create(args)
+        | TableEvent::Update(args)
+        | TableEvent::Flag(args)
+        | TableEvent::Suspend(args)
+        | TableEvent::Resume(args)
+        | TableEvent::Delete(args)
+        | TableEvent::Restore(args) => (args.id.u64(), vec![]),
+    };
+
+    let meta = CONTRACT_METADATA.load(storage, contract_id)?;
+    let partitions = if partitions.is_empty() {
+        vec![meta.partition]
+    } else {
+        partitions
+    };
+
+    let msg = to_json_binary(&TableEventMsgEnvelope::TableEvent(event))?;
+    let mut submsgs: Vec<SubMsg> = Vec::with_capacity(2);
+
+    for result in SUBSCRIPTIONS.range(storage, None, None, Order::Ascending) {
+        let (subscriber, sub) = result?;
+        if !sub.kinds.contains(&kind) {
+            continue;
+        }
+        if let Some(sub_partitions) = &sub.partitions {
+            if !partitions.iter().any(|p| sub_partitions.contains(p)) {
+                continue;
+            }
+        }
+        if let Some(code_ids) = &sub.code_ids {
+            if !code_ids.contains(&meta.code_id) {
+                continue;
+            }
+        }
+        if let Some(group_ids) = &sub.groups {
+            if !group_ids
+                .iter()
+                .any(|group_id| IX_GROUP.has(storage, (*group_id, contract_id)))
+            {
+                continue;
+            }
+        }
+        submsgs.push(SubMsg {
+            id: EVENT_REPLY_ID,
+            msg: WasmMsg::Execute {
+                contract_addr: subscriber.into(),
+                msg: msg.clone(),
+                funds: vec![],
+            }
+            .into(),
+            gas_limit: Some(sub.gas_limit),
+            reply_on: ReplyOn::Error,
+        });
+    }
+
+    Ok(submsgs)
+}
@@ -25,6 +25,14 @@ pub fn build_index_storage_key(name: &String) -> String {
     format!("_ix_{}", name)
 }
 
+pub fn build_composite_index_storage_key(name: &String) -> String {
+    format!("_cix_{}", name)
+}
+
+pub fn build_flags_storage_key(contract_id: u64) -> String {
+    format!("_flags_{}", contract_id)
+}
+
 pub fn pad(
     input: &str,
     target_length: usize,
@@ -36,6 +44,38 @@ pub fn pad(
     result
 }
 
-pub fn trim_padding(input: &String) -> String {
+/// Build the padded key under which a value is stored in a custom String index,
+/// lowercased if the index is case-insensitive.
+pub fn build_string_index_key(
+    value: &str,
+    case_insensitive: bool,
+    max_str_len: usize,
+) -> String {
+    if case_insensitive {
+        pad(&value.to_lowercase(), max_str_len)
+    } else {
+        pad(value, max_str_len)
+    }
+}
+
+/// Return the smallest string that is greater than every string starting with
+/// the given prefix, or None if no such string exists.
+pub fn increment_prefix(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(c) = chars.pop() {
+        // Skip over the surrogate code points, which aren't valid chars.
+        let next = match c as u32 {
+            0xD7FF => char::from_u32(0xE000),
+            n => char::from_u32(n + 1),
+        };
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
+pub fn trim_padding(input: &str) -> String {
     input.trim_end_matches('\0').to_string()
 }